@@ -1,16 +1,68 @@
 use std::env;
 use std::fs::OpenOptions;
-use std::io::Write;
+use std::io::{BufRead, Write};
 use std::path::PathBuf;
-use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
 use std::sync::Arc;
-use rdev::{listen, Event, EventType};
+use std::thread;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use rdev::{listen, Event, EventType, Key, ListenError};
+use serde_json::{json, Value};
+
+const USAGE: &str = "Usage: event_capture <events.log path> [--mouse-hz N] [--mouse-min-distance PX] \
+                     [--redact-keys [--keep-modifiers] [--keep-key NAME]...] \
+                     [--baseline EPOCH_SECS | --wait-for-start] [--clipboard]";
+
+// left readable by --keep-modifiers: they show shortcuts and navigation, not text
+const MODIFIER_KEYS: &[&str] = &[
+    "ShiftLeft", "ShiftRight", "ControlLeft", "ControlRight", "Alt", "AltGr", "MetaLeft", "MetaRight",
+    "CapsLock", "Escape", "UpArrow", "DownArrow", "LeftArrow", "RightArrow", "Home", "End", "PageUp", "PageDown",
+    "F1", "F2", "F3", "F4", "F5", "F6", "F7", "F8", "F9", "F10", "F11", "F12",
+];
+
+// the modifier of the copy/cut/paste shortcuts: Cmd on macOS, Ctrl elsewhere
+#[cfg(target_os = "macos")]
+const SHORTCUT_MODIFIERS: [Key; 2] = [Key::MetaLeft, Key::MetaRight];
+#[cfg(not(target_os = "macos"))]
+const SHORTCUT_MODIFIERS: [Key; 2] = [Key::ControlLeft, Key::ControlRight];
 
 fn main() {
-    // first arg is the output path
-    let path = env::args().nth(1)
-        .expect("Usage: event_capture <events.log path>");
+    // first arg is the output path, then optional mouse decimation flags
+    let mut args = env::args().skip(1);
+    let path = args.next().expect(USAGE);
     let out_path = PathBuf::from(path);
+    let mut mouse_hz = 30.0;
+    let mut min_distance = 8.0;
+    let mut redact_keys = false;
+    let mut keep_keys: Vec<String> = Vec::new();
+    let mut baseline = None;
+    let mut wait_for_start = false;
+    let mut clipboard = false;
+    while let Some(flag) = args.next() {
+        let mut number = || -> f64 { args.next().and_then(|v| v.parse().ok()).expect(USAGE) };
+        match flag.as_str() {
+            "--mouse-hz" => mouse_hz = number(),
+            "--mouse-min-distance" => min_distance = number(),
+            "--redact-keys" => redact_keys = true,
+            "--keep-modifiers" => keep_keys.extend(MODIFIER_KEYS.iter().map(|k| k.to_string())),
+            "--keep-key" => keep_keys.push(args.next().expect(USAGE)),
+            "--baseline" => baseline = Some(number()),
+            "--wait-for-start" => wait_for_start = true,
+            "--clipboard" => clipboard = true,
+            _ => panic!("{}", USAGE),
+        }
+    }
+    // key names as logged; with --redact-keys anything not kept becomes "Redacted"
+    let key_name = move |key: rdev::Key| {
+        let name = format!("{key:?}");
+        if redact_keys && !keep_keys.contains(&name) { "Redacted".to_string() } else { name }
+    };
+    // a move is logged once it is far enough from, or long enough after, the last logged one
+    let min_interval = Duration::from_secs_f64(1.0 / mouse_hz.max(0.1));
+    let mut last_move: Option<(f64, f64, SystemTime)> = None;
+    let mut pending_move: Option<Value> = None;
+    // which of SHORTCUT_MODIFIERS are held, for --clipboard
+    let mut modifier_down = [false; 2];
 
     // create parent dir
     if let Some(parent) = out_path.parent() {
@@ -31,19 +83,132 @@ fn main() {
         alive.store(false, Ordering::Relaxed);
     }).unwrap();
 
+    // Each event carries "offset", seconds since the recording started, once
+    // that start is known: from --baseline, or from a `START <epoch secs>`
+    // line on stdin when the app only learns it after spawning us. Stored as
+    // f64 bits, NaN until known.
+    let start = Arc::new(AtomicU64::new(baseline.unwrap_or(f64::NAN).to_bits()));
+    let started = start.clone();
+
+    // the app also sends PAUSE / RESUME on stdin; the tap stays installed while
+    // paused so resuming is instant, events are just dropped
+    let paused = Arc::new(AtomicBool::new(false));
+    let control = paused.clone();
+    thread::spawn(move || {
+        for line in std::io::stdin().lock().lines().map_while(Result::ok) {
+            match line.trim().split_once(' ').unwrap_or((line.trim(), "")) {
+                ("PAUSE", _) => control.store(true, Ordering::Relaxed),
+                ("RESUME", _) => control.store(false, Ordering::Relaxed),
+                ("START", epoch) => {
+                    if let Ok(epoch) = epoch.parse::<f64>() {
+                        started.store(epoch.to_bits(), Ordering::Relaxed);
+                    }
+                }
+                _ => {}
+            }
+        }
+    });
+
+    // The app waits for one status line on stdout. listen() blocks once the
+    // event tap is installed and only returns if that fails, so READY goes
+    // out after a grace period unless it has failed by then.
+    let failed = Arc::new(AtomicBool::new(false));
+    let tap_failed = failed.clone();
+    thread::spawn(move || {
+        thread::sleep(Duration::from_millis(250));
+        if !tap_failed.load(Ordering::Relaxed) {
+            println!("READY");
+        }
+    });
+
     // run on main thread with CFRunLoop properly set up
-    let _ = listen(move |ev: Event| {
+    let result = listen(move |ev: Event| {
         if !running.load(Ordering::Relaxed) { return; }
+        // tracked even while paused, so a release then isn't missed
+        if let EventType::KeyPress(k) | EventType::KeyRelease(k) = ev.event_type {
+            if let Some(i) = SHORTCUT_MODIFIERS.iter().position(|m| *m == k) {
+                modifier_down[i] = matches!(ev.event_type, EventType::KeyPress(_));
+            }
+        }
+        if paused.load(Ordering::Relaxed) {
+            // nothing typed while paused may reach the log, held-back moves included
+            pending_move = None;
+            return;
+        }
         
-        let desc = match ev.event_type {
-            EventType::KeyPress(k) => format!("KeyPress {k:?}"),
-            EventType::KeyRelease(k) => format!("KeyRelease {k:?}"),
-            EventType::ButtonPress(b) => format!("MouseDown {b:?}"),
-            EventType::ButtonRelease(b) => format!("MouseUp {b:?}"),
-            EventType::MouseMove { x, y } => format!("MouseMove {x:.0},{y:.0}"),
-            EventType::Wheel { delta_x, delta_y } => format!("Wheel {delta_x},{delta_y}"),
+        let timestamp = ev.time
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs_f64())
+            .unwrap_or(0.0);
+        let baseline = f64::from_bits(start.load(Ordering::Relaxed));
+        // with --wait-for-start, nothing before the recording began is logged
+        if wait_for_start && (baseline.is_nan() || timestamp < baseline) {
+            return;
+        }
+
+        let mut record: Value = match ev.event_type {
+            EventType::KeyPress(k) => json!({ "type": "KeyPress", "key": key_name(k) }),
+            EventType::KeyRelease(k) => json!({ "type": "KeyRelease", "key": key_name(k) }),
+            EventType::ButtonPress(b) => json!({ "type": "MouseDown", "button": format!("{b:?}") }),
+            EventType::ButtonRelease(b) => json!({ "type": "MouseUp", "button": format!("{b:?}") }),
+            EventType::MouseMove { x, y } => json!({ "type": "MouseMove", "x": x, "y": y }),
+            EventType::Wheel { delta_x, delta_y } => {
+                json!({ "type": "Wheel", "delta_x": delta_x, "delta_y": delta_y })
+            }
         };
+        record["timestamp"] = json!(timestamp);
+        if !baseline.is_nan() {
+            // rounded to the microsecond so the log stays readable
+            record["offset"] = json!(((timestamp - baseline) * 1e6).round() / 1e6);
+        }
+
+        if let EventType::MouseMove { x, y } = ev.event_type {
+            let keep = last_move.is_none_or(|(lx, ly, at)| {
+                (x - lx).hypot(y - ly) >= min_distance
+                    || ev.time.duration_since(at).is_ok_and(|dt| dt >= min_interval)
+            });
+            if !keep {
+                // held back so the next click still gets an exact position
+                pending_move = Some(record);
+                return;
+            }
+            last_move = Some((x, y, ev.time));
+            pending_move = None;
+        } else if let Some(pending) = pending_move.take() {
+            if let (Some(x), Some(y)) = (pending["x"].as_f64(), pending["y"].as_f64()) {
+                last_move = Some((x, y, ev.time));
+            }
+            let _ = writeln!(file, "{}", pending);
+        }
+
+        // one JSON object per line, written immediately so a killed helper still leaves a usable log
+        let _ = writeln!(file, "{}", record);
 
-        let _ = writeln!(file, "{:?}: {}", ev.time, desc);
+        // rdev sees no clipboard, so the shortcuts stand in for it
+        let action = match ev.event_type {
+            EventType::KeyPress(Key::KeyC) => Some("Copy"),
+            EventType::KeyPress(Key::KeyX) => Some("Cut"),
+            EventType::KeyPress(Key::KeyV) => Some("Paste"),
+            _ => None,
+        };
+        // they'd name the keys --redact-keys hides, so redaction wins
+        if let (true, false, true, Some(action)) = (clipboard, redact_keys, modifier_down.contains(&true), action) {
+            let mut semantic = json!({ "type": action, "timestamp": timestamp });
+            if let Some(offset) = record.get("offset") {
+                semantic["offset"] = offset.clone();
+            }
+            let _ = writeln!(file, "{}", semantic);
+        }
     });
+
+    if let Err(e) = result {
+        failed.store(true, Ordering::Relaxed);
+        // on macOS a missing Input Monitoring grant surfaces as a failed event tap
+        if matches!(e, ListenError::EventTapError) {
+            println!("PERMISSION_DENIED");
+        } else {
+            println!("ERROR {:?}", e);
+        }
+        std::process::exit(1);
+    }
 }
@@ -0,0 +1,71 @@
+//! `append_to_session`: recording more onto a finished session. The new take
+//! is recorded as a session of its own in a folder inside the existing one,
+//! so its event log, thumbnail and manifest stay separate, and is joined onto
+//! the session's output when it stops.
+
+use std::path::{Path, PathBuf};
+
+use chrono::Local;
+
+use crate::{concat, write_manifest, RecordingError, RecordingManifest};
+
+/// The manifest of the session in `dir`, if it can take another `format` take:
+/// finished, and a single file of that format.
+pub fn validate(dir: &Path, format: &str) -> Result<RecordingManifest, RecordingError> {
+    let manifest: RecordingManifest = std::fs::read_to_string(dir.join("manifest.json"))
+        .ok()
+        .and_then(|json| serde_json::from_str(&json).ok())
+        .ok_or_else(|| RecordingError::InvalidOptions(format!("{} has no finished recording", dir.display())))?;
+    if !manifest.segments.is_empty() {
+        return Err(RecordingError::InvalidOptions(
+            "a recording split by max_file_size_mb can't be appended to".into(),
+        ));
+    }
+    let output = Path::new(&manifest.output_path);
+    if !output.is_file() {
+        return Err(RecordingError::InvalidOptions(format!("{} is missing", output.display())));
+    }
+    let existing = output.extension().and_then(|e| e.to_str()).unwrap_or_default();
+    if existing != format {
+        return Err(RecordingError::InvalidOptions(format!(
+            "the session holds {} video; append with output_format \"{}\"",
+            existing, existing
+        )));
+    }
+    Ok(manifest)
+}
+
+/// A fresh folder for the next take inside `session_dir`.
+pub fn take_dir(session_dir: &Path) -> Result<PathBuf, RecordingError> {
+    let dir = session_dir.join(format!("take_{}", Local::now().format("%Y%m%d_%H%M%S")));
+    std::fs::create_dir_all(&dir)?;
+    Ok(dir)
+}
+
+/// Join the finished take described by `take` onto the output of the session
+/// in `session_dir`, and update that session's manifest. The streams are
+/// copied when they match and re-encoded when they don't. Returns the
+/// session's output and manifest paths, and a warning if the join degraded.
+pub fn join(
+    bin: &str,
+    session_dir: &Path,
+    take: &RecordingManifest,
+) -> Result<(PathBuf, PathBuf, Option<String>), RecordingError> {
+    let take_output = PathBuf::from(&take.output_path);
+    let format = take_output.extension().and_then(|e| e.to_str()).unwrap_or("mp4");
+    let mut manifest = validate(session_dir, format)?;
+    let output = PathBuf::from(&manifest.output_path);
+    let joined = output.with_file_name(format!("output.joined.{}", format));
+    let warning = concat::concat(bin, &[output.clone(), take_output.clone()], &joined)?;
+    std::fs::rename(&joined, &output)?;
+
+    manifest.duration_secs += take.duration_secs;
+    manifest.ended_at = take.ended_at.clone();
+    if let Some(dir) = take_output.parent() {
+        manifest.appended.push(dir.to_string_lossy().into());
+    }
+    // the hashes, event log and mapping describe the first take only
+    manifest.hashes_verifiable = false;
+    let manifest_path = write_manifest(session_dir, &manifest)?;
+    Ok((output, manifest_path, warning))
+}
@@ -0,0 +1,123 @@
+//! Click highlighting: a final ffmpeg pass that draws an expanding ring at
+//! every mouse click recorded by the event helper.
+
+use std::path::Path;
+
+use image::{Rgba, RgbaImage};
+use serde::{Deserialize, Serialize};
+
+use crate::events;
+use crate::ffmpeg::{self, FilterGraph};
+use crate::{RecordingError, RecordingOptions};
+
+// ring diameter at full size, in video pixels
+const RING_SIZE: u32 = 96;
+const RING_THICKNESS: f32 = 6.0;
+// how long each ring takes to expand, and stays visible
+const RING_SECS: f64 = 0.4;
+
+/// Maps global screen coordinates from the event log to video pixels.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct ClickMapping {
+    pub origin: (f64, f64), // top-left of the captured display, in screen coordinates
+    pub scale: f64,         // video pixels per screen coordinate
+    pub crop: (f64, f64),   // capture region offset, in video pixels
+}
+
+impl ClickMapping {
+    pub fn to_video(self, x: f64, y: f64) -> (f64, f64) {
+        (
+            (x - self.origin.0) * self.scale - self.crop.0,
+            (y - self.origin.1) * self.scale - self.crop.1,
+        )
+    }
+}
+
+/// Clicks from the NDJSON event log as (seconds into the video, x, y).
+/// MouseDown carries no position, so the last MouseMove is used.
+/// `speed` maps wall-clock offsets onto a sped-up video's timeline.
+fn load_clicks(
+    events_file: &Path,
+    start_epoch: f64,
+    speed: f64,
+    mapping: ClickMapping,
+    (w, h): (u32, u32),
+) -> Vec<(f64, i64, i64)> {
+    let mut pos = None;
+    let mut clicks = Vec::new();
+    for event in events::read_events(events_file) {
+        match event["type"].as_str() {
+            Some("MouseMove") => {
+                if let (Some(x), Some(y)) = (event["x"].as_f64(), event["y"].as_f64()) {
+                    pos = Some(mapping.to_video(x, y));
+                }
+            }
+            Some("MouseDown") => {
+                let (Some((x, y)), Some(t)) = (pos, events::video_time(&event, start_epoch, speed)) else { continue };
+                if x >= 0.0 && y >= 0.0 && x < w as f64 && y < h as f64 {
+                    clicks.push((t, x as i64, y as i64));
+                }
+            }
+            _ => {}
+        }
+    }
+    clicks
+}
+
+fn write_ring(path: &Path) -> Result<(), RecordingError> {
+    let r = RING_SIZE as f32 / 2.0;
+    let ring = RgbaImage::from_fn(RING_SIZE, RING_SIZE, |x, y| {
+        let d = ((x as f32 + 0.5 - r).powi(2) + (y as f32 + 0.5 - r).powi(2)).sqrt();
+        if d <= r && d >= r - RING_THICKNESS {
+            Rgba([255, 214, 0, 200])
+        } else {
+            Rgba([0, 0, 0, 0])
+        }
+    });
+    ring.save(path)?;
+    Ok(())
+}
+
+/// Re-encode `video` with a ring drawn at each click, replacing it in place.
+/// Returns the number of clicks drawn; the video is untouched when there are none.
+pub fn highlight_clicks(
+    opts: &RecordingOptions,
+    video: &Path,
+    events_file: &Path,
+    start_epoch: f64,
+    mapping: ClickMapping,
+    (w, h): (u32, u32),
+) -> Result<usize, RecordingError> {
+    let speed = opts.speed_factor()?.unwrap_or(1.0);
+    let clicks = load_clicks(events_file, start_epoch, speed, mapping, (w, h));
+    if clicks.is_empty() {
+        return Ok(0);
+    }
+    // ffmpeg runs in the session folder, next to the ring
+    let dir = video.parent().unwrap_or(Path::new("."));
+    let ring = dir.join("click_ring.png");
+    write_ring(&ring)?;
+
+    let mut graph = FilterGraph::new();
+    let labels: String = (0..clicks.len()).map(|i| format!("[r{}]", i)).collect();
+    graph.side(format!("[1:v]split={}{}", clicks.len(), labels));
+    for (i, (t, x, y)) in clicks.iter().enumerate() {
+        graph.side(format!(
+            "[r{i}]scale=w='{size}*(0.3+0.7*clip((t-{t:.3})/{dur},0,1))':h=-1:eval=frame[s{i}]",
+            size = RING_SIZE,
+            dur = RING_SECS,
+        ));
+        graph.chain(
+            &format!("[s{}]", i),
+            &format!(
+                "overlay=x={x}-w/2:y={y}-h/2:enable='between(t,{t:.3},{end:.3})':shortest=1",
+                end = t + RING_SECS
+            ),
+        );
+    }
+    let fps = opts.output_fps().to_string();
+    let inputs = ["-loop", "1", "-framerate", &fps, "-i", "click_ring.png"];
+    let reencoded = ffmpeg::reencode_in_place(opts, video, &inputs, graph, "clicks");
+    let _ = std::fs::remove_file(&ring);
+    reencoded.map(|()| clicks.len())
+}
@@ -0,0 +1,108 @@
+//! Compositing every display into one frame for `capture_all_monitors`.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+use crossbeam_channel::{bounded, Sender};
+
+use crate::source::next_frame_checked;
+use crate::FrameSource;
+
+/// Where one display's frames land on the canvas, in pixels.
+#[derive(Debug, Clone, Copy)]
+pub struct Tile {
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+}
+
+/// Canvas size and per-display placement.
+#[derive(Debug, Clone)]
+pub struct Layout {
+    pub width: u32,
+    pub height: u32,
+    pub tiles: Vec<Tile>,
+}
+
+/// Place displays by their desktop origins (screen coordinates), converted to
+/// pixels with `scale`; `sizes` are the real frame sizes. Whatever the tiles
+/// don't cover (gaps, mismatched heights) stays black.
+pub fn layout(origins: &[(i32, i32)], scale: f64, sizes: &[(u32, u32)]) -> Layout {
+    let min_x = origins.iter().map(|o| o.0).min().unwrap_or(0);
+    let min_y = origins.iter().map(|o| o.1).min().unwrap_or(0);
+    let tiles: Vec<Tile> = origins
+        .iter()
+        .zip(sizes)
+        .map(|(&(x, y), &(width, height))| Tile {
+            x: ((x - min_x) as f64 * scale).round() as u32,
+            y: ((y - min_y) as f64 * scale).round() as u32,
+            width,
+            height,
+        })
+        .collect();
+    // encoders want even dimensions
+    let width = tiles.iter().map(|t| t.x + t.width).max().unwrap_or(0).next_multiple_of(2);
+    let height = tiles.iter().map(|t| t.y + t.height).max().unwrap_or(0).next_multiple_of(2);
+    Layout { width, height, tiles }
+}
+
+/// Copy a tightly packed BGRA frame into the canvas at `tile`, clipped to the canvas.
+fn blit(canvas: &mut [u8], canvas_w: u32, canvas_h: u32, tile: Tile, frame: &[u8]) {
+    let cols = tile.width.min(canvas_w.saturating_sub(tile.x)) as usize;
+    let rows = tile.height.min(canvas_h.saturating_sub(tile.y));
+    let src_stride = tile.width as usize * 4;
+    let dst_stride = canvas_w as usize * 4;
+    for row in 0..rows as usize {
+        let src = &frame[(row * src_stride).min(frame.len())..];
+        let Some(src) = src.get(..cols * 4) else { break };
+        let dst = (tile.y as usize + row) * dst_stride + tile.x as usize * 4;
+        canvas[dst..dst + cols * 4].copy_from_slice(src);
+    }
+}
+
+/// Start one grabber per display, each painting into a shared canvas, and a
+/// compositor forwarding a copy of the canvas after every update. The
+/// compositor ends, closing `frame_tx`, once every grabber has stopped. A frame
+/// that can't be grabbed, or no longer fits its tile, is reported on `frame_tx`
+/// and stops its grabber.
+pub fn spawn(capturers: Vec<Box<dyn FrameSource>>, layout: Layout, alive: Arc<AtomicBool>, frame_tx: Sender<Result<Vec<u8>, String>>) {
+    let canvas = Arc::new(Mutex::new(vec![0u8; layout.width as usize * layout.height as usize * 4]));
+    let (dirty_tx, dirty_rx) = bounded::<()>(1);
+
+    for (mut capturer, tile) in capturers.into_iter().zip(layout.tiles.iter().copied()) {
+        let canvas = canvas.clone();
+        let dirty_tx = dirty_tx.clone();
+        let alive = alive.clone();
+        let error_tx = frame_tx.clone();
+        let (canvas_w, canvas_h) = (layout.width, layout.height);
+        thread::spawn(move || {
+            while alive.load(Ordering::Relaxed) {
+                match next_frame_checked(capturer.as_mut(), (tile.width, tile.height), false) {
+                    Ok(Some(data)) => {
+                        blit(&mut canvas.lock().unwrap(), canvas_w, canvas_h, tile, &data);
+                        // a pending notification already covers this update
+                        let _ = dirty_tx.try_send(());
+                    }
+                    Ok(None) => break,
+                    Err(e) => {
+                        let _ = error_tx.send(Err(e));
+                        break;
+                    }
+                }
+            }
+            capturer.stop();
+        });
+    }
+    drop(dirty_tx);
+
+    thread::spawn(move || {
+        while dirty_rx.recv().is_ok() {
+            let frame = canvas.lock().unwrap().clone();
+            if frame_tx.send(Ok(frame)).is_err() {
+                break;
+            }
+        }
+    });
+}
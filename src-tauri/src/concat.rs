@@ -0,0 +1,142 @@
+//! Joining finished recordings into one file: a stream copy through the concat
+//! demuxer when the inputs match, otherwise a re-encode through the concat filter.
+
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+use crate::ffmpeg;
+use crate::RecordingError;
+
+/// What ffprobe reports about a recording's streams. Inputs that agree on all
+/// of it can be joined by stream copy.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct StreamInfo {
+    codec: String,
+    width: u32,
+    height: u32,
+    pix_fmt: String,
+    frame_rate: String, // as a fraction, e.g. "30/1"
+    time_base: String,
+    audio: Option<Vec<String>>, // codec, sample rate and channels, if there is an audio stream
+}
+
+fn probe(ffprobe: &Path, path: &Path) -> Result<StreamInfo, RecordingError> {
+    let unreadable = || RecordingError::FfmpegFailed(format!("could not read the video stream of {}", path.display()));
+    // ffprobe prints the entries in its own order, which is the one listed here
+    let video = ffmpeg::ffprobe_lines(
+        ffprobe,
+        &["-select_streams", "v:0", "-show_entries", "stream=codec_name,width,height,pix_fmt,r_frame_rate,time_base"],
+        path,
+    )
+    .ok_or_else(unreadable)?;
+    let [codec, width, height, pix_fmt, frame_rate, time_base] = video.as_slice() else { return Err(unreadable()) };
+    let audio = ffmpeg::ffprobe_lines(
+        ffprobe,
+        &["-select_streams", "a:0", "-show_entries", "stream=codec_name,sample_rate,channels"],
+        path,
+    )
+    .filter(|values| !values.is_empty());
+    Ok(StreamInfo {
+        codec: codec.clone(),
+        width: width.parse().map_err(|_| unreadable())?,
+        height: height.parse().map_err(|_| unreadable())?,
+        pix_fmt: pix_fmt.clone(),
+        frame_rate: frame_rate.clone(),
+        time_base: time_base.clone(),
+        audio,
+    })
+}
+
+/// Quote a path for a concat demuxer list file.
+fn list_entry(path: &Path) -> String {
+    format!("file '{}'\n", path.to_string_lossy().replace('\'', "'\\''"))
+}
+
+/// Join `inputs` into `output`, returning a warning when they had to be rescaled
+/// or lost their audio on the way.
+pub fn concat(bin: &str, inputs: &[PathBuf], output: &Path) -> Result<Option<String>, RecordingError> {
+    if inputs.len() < 2 {
+        return Err(RecordingError::InvalidOptions("concatenating needs at least two recordings".into()));
+    }
+    for input in inputs {
+        match std::fs::metadata(input) {
+            Ok(m) if m.is_file() && m.len() > 0 => {}
+            Ok(_) => return Err(RecordingError::InvalidOptions(format!("{} is empty", input.display()))),
+            Err(e) => return Err(RecordingError::Io(format!("{}: {}", input.display(), e))),
+        }
+        if input == output {
+            return Err(RecordingError::InvalidOptions("the output must not be one of the inputs".into()));
+        }
+    }
+
+    let ffprobe = ffmpeg::ffprobe_bin(bin);
+    let infos = inputs.iter().map(|p| probe(&ffprobe, p)).collect::<Result<Vec<_>, _>>()?;
+    let first = &infos[0];
+
+    let mut cmd = Command::new(bin);
+    cmd.args(["-y", "-hide_banner", "-loglevel", "error"]);
+    let mut list_file = None;
+    let mut warning = None;
+    if infos.iter().all(|info| info == first) {
+        // identical streams, timing included: the demuxer joins them without re-encoding
+        let list = output.with_extension("concat.txt");
+        std::fs::write(&list, inputs.iter().map(|p| list_entry(p)).collect::<String>())?;
+        cmd.args(["-f", "concat", "-safe", "0", "-i"]).arg(&list).args(["-c", "copy"]);
+        list_file = Some(list);
+    } else {
+        let (w, h) = (first.width, first.height);
+        if infos.iter().any(|info| (info.width, info.height) != (w, h)) {
+            warning = Some(format!("Recordings differ in resolution; all were scaled to {}x{}", w, h));
+        }
+        // the concat filter needs every segment to have the same streams
+        let audio = infos.iter().all(|info| info.audio.is_some());
+        if !audio && infos.iter().any(|info| info.audio.is_some()) {
+            warning = Some(match warning {
+                Some(w) => format!("{}; audio was dropped because not every recording has it", w),
+                None => "Audio was dropped because not every recording has it".into(),
+            });
+        }
+
+        // the concat filter wants one frame rate too; the first input's is kept
+        let rate = &first.frame_rate;
+        let mut filters = Vec::new();
+        let mut segments = String::new();
+        for (i, input) in inputs.iter().enumerate() {
+            cmd.arg("-i").arg(input);
+            filters.push(format!(
+                "[{i}:v]fps={rate},scale={w}:{h}:force_original_aspect_ratio=decrease,pad={w}:{h}:(ow-iw)/2:(oh-ih)/2,setsar=1[v{i}]"
+            ));
+            segments.push_str(&format!("[v{}]", i));
+            if audio {
+                segments.push_str(&format!("[{}:a]", i));
+            }
+        }
+        filters.push(format!("{}concat=n={}:v=1:a={}[v]{}", segments, inputs.len(), audio as u8, if audio { "[a]" } else { "" }));
+        cmd.args(["-filter_complex", &filters.join(";"), "-map", "[v]"]);
+        cmd.args(["-c:v", "libx264", "-preset", "veryfast", "-crf", "20", "-pix_fmt", "yuv420p"]);
+        if audio {
+            cmd.args(["-map", "[a]", "-c:a", "aac", "-b:a", "160k"]);
+        }
+    }
+
+    let result = cmd
+        .arg(output)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .output()
+        .map_err(|e| ffmpeg::spawn_error(bin, e));
+    if let Some(list) = list_file {
+        let _ = std::fs::remove_file(list);
+    }
+    let result = result?;
+    if !result.status.success() {
+        let _ = std::fs::remove_file(output);
+        return Err(RecordingError::FfmpegFailed(format!(
+            "concat exited with {}: {}",
+            result.status,
+            String::from_utf8_lossy(&result.stderr).trim()
+        )));
+    }
+    Ok(warning)
+}
@@ -0,0 +1,148 @@
+//! Synthetic cursor: a final ffmpeg pass that draws an arrow pointer where
+//! the event log puts the mouse, for capture backends that leave the cursor
+//! out of the frames. Positions are sampled as the helper logs moves, so the
+//! pointer steps between them rather than gliding.
+
+use std::fmt::Write as _;
+use std::path::Path;
+
+use image::{Rgba, RgbaImage};
+
+use crate::clicks::ClickMapping;
+use crate::events;
+use crate::ffmpeg::{self, FilterGraph};
+use crate::{RecordingError, RecordingOptions};
+
+// the arrow outline with its tip at the origin, in points (19pt tall, like the system pointer)
+const ARROW: &[(f64, f64)] = &[(0.0, 0.0), (0.0, 17.0), (4.2, 13.2), (7.0, 19.0), (9.4, 18.0), (6.7, 12.3), (12.0, 12.3)];
+const ARROW_SIZE: (f64, f64) = (12.0, 19.0);
+// edge samples per pixel side when rasterizing, for smooth edges
+const SUPERSAMPLE: u32 = 4;
+// where the overlay goes while the pointer is outside the video
+const HIDDEN: i64 = -10000;
+
+fn inside(polygon: &[(f64, f64)], (x, y): (f64, f64)) -> bool {
+    let mut inside = false;
+    let mut j = polygon.len() - 1;
+    for (i, &(xi, yi)) in polygon.iter().enumerate() {
+        let (xj, yj) = polygon[j];
+        if (yi > y) != (yj > y) && x < (xj - xi) * (y - yi) / (yj - yi) + xi {
+            inside = !inside;
+        }
+        j = i;
+    }
+    inside
+}
+
+/// The arrow drawn at `scale` video pixels per point: white with a black
+/// border, on transparency.
+fn write_arrow(path: &Path, scale: f64) -> Result<(), RecordingError> {
+    let border = 1.2;
+    let outer: Vec<(f64, f64)> = ARROW.to_vec();
+    // the white fill is the outline pulled in towards its middle
+    let (cx, cy) = (4.5, 11.0);
+    let inner: Vec<(f64, f64)> = ARROW
+        .iter()
+        .map(|&(x, y)| {
+            let (dx, dy) = (x - cx, y - cy);
+            let len = (dx * dx + dy * dy).sqrt().max(f64::EPSILON);
+            (x - dx / len * border, y - dy / len * border)
+        })
+        .collect();
+    let width = ((ARROW_SIZE.0 + 1.0) * scale).ceil() as u32;
+    let height = ((ARROW_SIZE.1 + 1.0) * scale).ceil() as u32;
+    let samples = (SUPERSAMPLE * SUPERSAMPLE) as f64;
+    let arrow = RgbaImage::from_fn(width, height, |px, py| {
+        let (mut covered, mut fill) = (0.0, 0.0);
+        for sy in 0..SUPERSAMPLE {
+            for sx in 0..SUPERSAMPLE {
+                let point = (
+                    (px as f64 + (sx as f64 + 0.5) / SUPERSAMPLE as f64) / scale,
+                    (py as f64 + (sy as f64 + 0.5) / SUPERSAMPLE as f64) / scale,
+                );
+                if inside(&outer, point) {
+                    covered += 1.0;
+                    if inside(&inner, point) {
+                        fill += 1.0;
+                    }
+                }
+            }
+        }
+        let shade = if covered > 0.0 { (255.0 * fill / covered) as u8 } else { 0 };
+        Rgba([shade, shade, shade, (255.0 * covered / samples) as u8])
+    });
+    arrow.save(path)?;
+    Ok(())
+}
+
+/// An ffmpeg `sendcmd` script moving the overlay named `overlay@cursor` to
+/// each logged pointer position at its time in the video. Returns the script
+/// and how many moves it holds.
+fn move_script(
+    events_file: &Path,
+    start_epoch: f64,
+    speed: f64,
+    mapping: ClickMapping,
+    (w, h): (u32, u32),
+) -> (String, usize) {
+    let mut script = String::new();
+    let mut moves = 0;
+    let mut last = None;
+    for event in events::read_events(events_file) {
+        if event["type"] != "MouseMove" {
+            continue;
+        }
+        let (Some(x), Some(y), Some(t)) =
+            (event["x"].as_f64(), event["y"].as_f64(), events::video_time(&event, start_epoch, speed))
+        else {
+            continue;
+        };
+        let (x, y) = mapping.to_video(x, y);
+        let at = if x >= 0.0 && y >= 0.0 && x < w as f64 && y < h as f64 {
+            (x as i64, y as i64)
+        } else {
+            (HIDDEN, HIDDEN)
+        };
+        if last == Some(at) {
+            continue;
+        }
+        last = Some(at);
+        moves += 1;
+        let _ = writeln!(script, "{:.3} overlay@cursor x {}, overlay@cursor y {};", t, at.0, at.1);
+    }
+    (script, moves)
+}
+
+/// Re-encode `video` with a pointer drawn at the logged mouse positions,
+/// replacing it in place. Returns the number of moves drawn; the video is
+/// untouched when the log has none.
+pub fn draw_cursor(
+    opts: &RecordingOptions,
+    video: &Path,
+    events_file: &Path,
+    start_epoch: f64,
+    mapping: ClickMapping,
+    size: (u32, u32),
+) -> Result<usize, RecordingError> {
+    let speed = opts.speed_factor()?.unwrap_or(1.0);
+    let (script, moves) = move_script(events_file, start_epoch, speed, mapping, size);
+    if moves == 0 {
+        return Ok(0);
+    }
+    // ffmpeg runs in the session folder so the filter args need no path escaping
+    let dir = video.parent().unwrap_or(Path::new("."));
+    std::fs::write(dir.join("cursor_moves.txt"), script)?;
+    let arrow = dir.join("cursor.png");
+    // mapping.scale is video pixels per screen point, so the pointer keeps its on-screen size
+    write_arrow(&arrow, mapping.scale.max(0.5))?;
+
+    let mut graph = FilterGraph::new();
+    graph.chain("", "sendcmd=f=cursor_moves.txt");
+    graph.chain("[1:v]", &format!("overlay@cursor=x={HIDDEN}:y={HIDDEN}:shortest=1"));
+    let fps = opts.output_fps().to_string();
+    let inputs = ["-loop", "1", "-framerate", &fps, "-i", "cursor.png"];
+    let reencoded = ffmpeg::reencode_in_place(opts, video, &inputs, graph, "cursor");
+    let _ = std::fs::remove_file(&arrow);
+    let _ = std::fs::remove_file(dir.join("cursor_moves.txt"));
+    reencoded.map(|()| moves)
+}
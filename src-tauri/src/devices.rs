@@ -0,0 +1,181 @@
+//! Capture device enumeration (cameras) via ffmpeg's platform input devices.
+//! • macOS: avfoundation, Windows: dshow, Linux: v4l2 (`/dev/video*`).
+
+use std::path::Path;
+use std::process::{Command, Stdio};
+
+use serde::Serialize;
+
+#[derive(Debug, Serialize, Clone)]
+pub struct CameraInfo {
+    pub index: usize,
+    pub name: String,
+    /// Value handed to ffmpeg's `-i` for this device.
+    pub input: String,
+}
+
+// audio devices that capture what the system plays rather than a microphone
+const LOOPBACK_NAMES: &[&str] = &["blackhole", "soundflower", "loopback", "stereo mix", "virtual-audio-capturer"];
+
+fn is_loopback(name: &str) -> bool {
+    let name = name.to_lowercase();
+    LOOPBACK_NAMES.iter().any(|l| name.contains(l))
+}
+
+/// Run ffmpeg's device listing and return its stderr, where the list is printed.
+fn device_listing(ffmpeg: &str, args: &[&str]) -> String {
+    Command::new(ffmpeg)
+        .args(["-hide_banner"])
+        .args(args)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .output()
+        .map(|out| String::from_utf8_lossy(&out.stderr).into_owned())
+        .unwrap_or_default()
+}
+
+/// Parse avfoundation's listing:
+/// `[AVFoundation indev @ 0x..] [0] FaceTime HD Camera` under a "video devices:" header.
+fn parse_avfoundation(listing: &str, kind: &str) -> Vec<(usize, String)> {
+    let header = format!("AVFoundation {} devices:", kind);
+    let mut in_section = false;
+    let mut devices = Vec::new();
+    for line in listing.lines() {
+        if line.contains("AVFoundation") && line.ends_with("devices:") {
+            in_section = line.contains(&header);
+            continue;
+        }
+        if !in_section {
+            continue;
+        }
+        // strip the "[AVFoundation indev @ 0x..] " prefix, leaving "[0] Name"
+        let Some(rest) = line.split_once("] ").map(|(_, r)| r) else { continue };
+        let Some((idx, name)) = rest.strip_prefix('[').and_then(|r| r.split_once("] ")) else { continue };
+        if let Ok(idx) = idx.parse() {
+            devices.push((idx, name.trim().to_string()));
+        }
+    }
+    devices
+}
+
+/// Parse dshow's listing. Newer ffmpeg prints `"Name" (video)`, older builds
+/// print the quoted names under "DirectShow video devices" headers.
+fn parse_dshow(listing: &str, kind: &str) -> Vec<String> {
+    let mut section = "";
+    let mut devices = Vec::new();
+    for line in listing.lines() {
+        if line.contains("DirectShow video devices") {
+            section = "video";
+            continue;
+        }
+        if line.contains("DirectShow audio devices") {
+            section = "audio";
+            continue;
+        }
+        if line.contains("Alternative name") {
+            continue;
+        }
+        let Some(start) = line.find('"') else { continue };
+        let Some(len) = line[start + 1..].find('"') else { continue };
+        let name = &line[start + 1..start + 1 + len];
+        let tail = &line[start + len + 2..];
+        let line_kind = if tail.contains("(video)") {
+            "video"
+        } else if tail.contains("(audio)") {
+            "audio"
+        } else {
+            section
+        };
+        if line_kind == kind {
+            devices.push(name.to_string());
+        }
+    }
+    devices
+}
+
+/// List the cameras ffmpeg can open on this platform.
+pub fn list_cameras(ffmpeg: &str) -> Vec<CameraInfo> {
+    match std::env::consts::OS {
+        "macos" => {
+            let listing = device_listing(ffmpeg, &["-f", "avfoundation", "-list_devices", "true", "-i", ""]);
+            parse_avfoundation(&listing, "video")
+                .into_iter()
+                // screens are listed as video devices too
+                .filter(|(_, name)| !name.starts_with("Capture screen"))
+                .enumerate()
+                .map(|(index, (dev, name))| CameraInfo { index, name, input: format!("{}:none", dev) })
+                .collect()
+        }
+        "windows" => {
+            let listing = device_listing(ffmpeg, &["-list_devices", "true", "-f", "dshow", "-i", "dummy"]);
+            parse_dshow(&listing, "video")
+                .into_iter()
+                .enumerate()
+                .map(|(index, name)| CameraInfo { index, input: format!("video={}", name), name })
+                .collect()
+        }
+        _ => {
+            let mut nodes: Vec<_> = std::fs::read_dir("/dev")
+                .map(|dir| {
+                    dir.filter_map(|e| e.ok())
+                        .map(|e| e.file_name().to_string_lossy().into_owned())
+                        .filter(|n| n.starts_with("video"))
+                        .collect()
+                })
+                .unwrap_or_default();
+            nodes.sort_by_key(|n| n.trim_start_matches("video").parse::<u32>().unwrap_or(u32::MAX));
+            nodes
+                .into_iter()
+                .enumerate()
+                .map(|(index, node)| {
+                    let sys_name = Path::new("/sys/class/video4linux").join(&node).join("name");
+                    let name = std::fs::read_to_string(sys_name)
+                        .map(|n| n.trim().to_string())
+                        .unwrap_or_else(|_| node.clone());
+                    CameraInfo { index, name, input: format!("/dev/{}", node) }
+                })
+                .collect()
+        }
+    }
+}
+
+/// ffmpeg input arguments for the default microphone or, with `system`, the
+/// loopback/monitor source. `None` if no matching device exists.
+pub fn audio_input_args(ffmpeg: &str, system: bool) -> Option<Vec<String>> {
+    let (format, input) = match std::env::consts::OS {
+        "macos" => {
+            // macOS has no native loopback; BlackHole and friends show up as inputs
+            let listing = device_listing(ffmpeg, &["-f", "avfoundation", "-list_devices", "true", "-i", ""]);
+            let (index, _) = parse_avfoundation(&listing, "audio")
+                .into_iter()
+                .find(|(_, name)| is_loopback(name) == system)?;
+            ("avfoundation", format!(":{}", index))
+        }
+        "windows" => {
+            let listing = device_listing(ffmpeg, &["-list_devices", "true", "-f", "dshow", "-i", "dummy"]);
+            let name = parse_dshow(&listing, "audio")
+                .into_iter()
+                .find(|name| is_loopback(name) == system)?;
+            ("dshow", format!("audio={}", name))
+        }
+        // PulseAudio (and PipeWire's pulse server) expose the default sink's monitor
+        _ => ("pulse", if system { "@DEFAULT_MONITOR@" } else { "default" }.to_string()),
+    };
+    Some(vec!["-thread_queue_size".into(), "1024".into(), "-f".into(), format.into(), "-i".into(), input])
+}
+
+/// ffmpeg input arguments (`-f ... -i ...`) for a camera.
+pub fn camera_input_args(camera: &CameraInfo) -> Vec<String> {
+    let format = match std::env::consts::OS {
+        "macos" => "avfoundation",
+        "windows" => "dshow",
+        _ => "v4l2",
+    };
+    let mut args: Vec<String> = vec!["-thread_queue_size".into(), "512".into(), "-f".into(), format.into()];
+    if format == "avfoundation" {
+        // avfoundation refuses to open without an explicitly supported rate
+        args.extend(["-framerate".into(), "30".into()]);
+    }
+    args.extend(["-i".into(), camera.input.clone()]);
+    args
+}
@@ -0,0 +1,86 @@
+//! Structured errors returned by the Tauri commands.
+//! Serialized as `{ "code": "...", "message": "..." }` so the frontend can branch on `code`.
+
+use std::fmt;
+
+use serde::ser::{Serialize, SerializeStruct, Serializer};
+
+#[derive(Debug, Clone)]
+pub enum RecordingError {
+    AlreadyRecording,
+    NotRecording,
+    Unsupported,
+    PermissionDenied,
+    Cancelled,
+    InvalidOptions(String),
+    FfmpegNotFound(String), // the binary that was tried
+    FfmpegFailed(String),
+    Capture(String),
+    Helper(String),
+    Hotkey(String),
+    Audio(String),
+    Io(String),
+}
+
+impl RecordingError {
+    pub fn code(&self) -> &'static str {
+        match self {
+            RecordingError::AlreadyRecording => "already_recording",
+            RecordingError::NotRecording => "not_recording",
+            RecordingError::Unsupported => "unsupported",
+            RecordingError::PermissionDenied => "permission_denied",
+            RecordingError::Cancelled => "cancelled",
+            RecordingError::InvalidOptions(_) => "invalid_options",
+            RecordingError::FfmpegNotFound(_) => "ffmpeg_not_found",
+            RecordingError::FfmpegFailed(_) => "ffmpeg_failed",
+            RecordingError::Capture(_) => "capture",
+            RecordingError::Helper(_) => "helper",
+            RecordingError::Hotkey(_) => "hotkey",
+            RecordingError::Audio(_) => "audio",
+            RecordingError::Io(_) => "io",
+        }
+    }
+}
+
+impl fmt::Display for RecordingError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RecordingError::AlreadyRecording => write!(f, "Recording already running"),
+            RecordingError::NotRecording => write!(f, "No recording in progress"),
+            RecordingError::Unsupported => write!(f, "Screen capture unsupported on this platform"),
+            RecordingError::PermissionDenied => write!(f, "Screen-record permission denied"),
+            RecordingError::Cancelled => write!(f, "Recording cancelled before it started"),
+            RecordingError::InvalidOptions(msg) => write!(f, "{}", msg),
+            RecordingError::FfmpegNotFound(bin) => write!(f, "ffmpeg not found ({})", bin),
+            RecordingError::FfmpegFailed(msg) => write!(f, "ffmpeg failed: {}", msg),
+            RecordingError::Capture(msg) => write!(f, "Screen capture failed: {}", msg),
+            RecordingError::Helper(msg) => write!(f, "Event helper failed: {}", msg),
+            RecordingError::Hotkey(msg) => write!(f, "Hotkey error: {}", msg),
+            RecordingError::Audio(msg) => write!(f, "Audio capture failed: {}", msg),
+            RecordingError::Io(msg) => write!(f, "{}", msg),
+        }
+    }
+}
+
+impl std::error::Error for RecordingError {}
+
+impl Serialize for RecordingError {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut s = serializer.serialize_struct("RecordingError", 2)?;
+        s.serialize_field("code", self.code())?;
+        s.serialize_field("message", &self.to_string())?;
+        s.end()
+    }
+}
+
+impl From<std::io::Error> for RecordingError {
+    fn from(e: std::io::Error) -> Self {
+        RecordingError::Io(e.to_string())
+    }
+}
+
+impl From<image::ImageError> for RecordingError {
+    fn from(e: image::ImageError) -> Self {
+        RecordingError::Io(e.to_string())
+    }
+}
@@ -0,0 +1,78 @@
+//! Rough encoded sizes, for planning a recording and for checking there's
+//! room when one starts. The figures are bits per output pixel per frame,
+//! tuned for screen content, which compresses far better than camera video;
+//! a screen full of motion can run well over them. Nothing is encoded.
+
+use crate::{FrameImageFormat, RecordingError, RecordingOptions};
+
+// H.264 at CRF 23 with the ultrafast preset
+const H264_BITS_PER_PIXEL: f64 = 0.15;
+const H264_DEFAULT_CRF: f64 = 23.0;
+// VP9 in realtime mode at CRF 31, and the bitrate libvpx targets without a CRF
+const VP9_BITS_PER_PIXEL: f64 = 0.1;
+const VP9_DEFAULT_CRF: f64 = 31.0;
+const VP9_DEFAULT_BITS_PER_SEC: f64 = 256_000.0;
+// CRF steps that double the bitrate (x264, VP9)
+const H264_CRF_DOUBLING: f64 = 6.0;
+const VP9_CRF_DOUBLING: f64 = 8.0;
+// slower x264 presets reach the same quality in fewer bits, relative to ultrafast
+const PRESET_FACTORS: &[(&str, f64)] = &[
+    ("ultrafast", 1.0), ("superfast", 0.8), ("veryfast", 0.65), ("faster", 0.6), ("fast", 0.55),
+    ("medium", 0.5), ("slow", 0.47), ("slower", 0.45), ("veryslow", 0.42), ("placebo", 0.4),
+];
+// lossless video keeps every pixel exactly
+const LOSSLESS_BITS_PER_PIXEL: f64 = 1.5;
+const GIF_BITS_PER_PIXEL: f64 = 0.3;
+const APNG_BITS_PER_PIXEL: f64 = 1.0;
+const PNG_BITS_PER_PIXEL: f64 = 2.0;
+
+/// Bits per second of a `crf`-driven codec: `base` bits per pixel at
+/// `default_crf`, doubling every `doubling` steps down.
+fn crf_scaled(base: f64, crf: Option<u8>, default_crf: f64, doubling: f64) -> f64 {
+    let crf = crf.map_or(default_crf, f64::from);
+    base * 2f64.powf((default_crf - crf) / doubling)
+}
+
+/// Estimated bytes per second of recording with `opts` at `w`x`h` output,
+/// audio included. A timelapse shrinks it by its speed-up.
+pub fn bytes_per_sec(opts: &RecordingOptions, (w, h): (u32, u32)) -> Result<f64, RecordingError> {
+    // validates the codec settings the figures below rely on
+    opts.video_codec_args()?;
+    let pixels_per_sec = w as f64 * h as f64 * opts.output_fps() as f64;
+    let crf = opts.crf()?;
+    let video_bits = match (opts.frame_image_format()?, opts.output_format()?) {
+        (Some(FrameImageFormat::Png), _) => pixels_per_sec * PNG_BITS_PER_PIXEL,
+        // about 1 bit per pixel at quality 90, halving every 15 below
+        (Some(FrameImageFormat::Jpeg(q)), _) => pixels_per_sec * 2f64.powf((q as f64 - 90.0) / 15.0),
+        (None, "mp4" | "webm") if opts.lossless => pixels_per_sec * LOSSLESS_BITS_PER_PIXEL,
+        (None, "mp4" | "webm") if opts.bitrate_kbps.is_some() => {
+            opts.bitrate_kbps.unwrap_or_default() as f64 * 1000.0
+        }
+        (None, "webm") if crf.is_none() => VP9_DEFAULT_BITS_PER_SEC,
+        (None, "webm") => {
+            pixels_per_sec * crf_scaled(VP9_BITS_PER_PIXEL, crf, VP9_DEFAULT_CRF, VP9_CRF_DOUBLING)
+        }
+        (None, "gif") => pixels_per_sec * GIF_BITS_PER_PIXEL,
+        (None, "apng") => pixels_per_sec * APNG_BITS_PER_PIXEL,
+        (None, "webp") if opts.webp_lossless => pixels_per_sec * APNG_BITS_PER_PIXEL,
+        // 0.16 at the default quality of 75
+        (None, "webp") => pixels_per_sec * 0.02 * 2f64.powf(opts.webp_quality.unwrap_or(75) as f64 / 25.0),
+        (None, _) => {
+            let preset = opts.preset()?;
+            let factor = match opts.encoder.as_deref() {
+                // hardware encoders ignore x264 presets
+                Some(encoder) if encoder != "libx264" => 1.0,
+                _ => PRESET_FACTORS.iter().find(|(p, _)| *p == preset).map_or(1.0, |(_, f)| *f),
+            };
+            pixels_per_sec * factor * crf_scaled(H264_BITS_PER_PIXEL, crf, H264_DEFAULT_CRF, H264_CRF_DOUBLING)
+        }
+    };
+    // the bitrates audio_codec_args asks for
+    let audio_bits = match (opts.audio_source.is_some(), opts.output_format()?) {
+        (false, _) => 0.0,
+        (true, "webm") => 128_000.0,
+        (true, _) => 160_000.0,
+    };
+    let speed = opts.speed_factor()?.unwrap_or(1.0);
+    Ok((video_bits + audio_bits) / 8.0 / speed)
+}
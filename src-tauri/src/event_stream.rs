@@ -0,0 +1,51 @@
+//! Live forwarding of logged input events to `subscribe_events` channels,
+//! for an activity feed during recording. The helper applies key redaction
+//! and pausing before it writes a line, so tailing its log forwards exactly
+//! what the recording keeps.
+
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+use serde_json::Value;
+use tauri::ipc::Channel;
+
+pub type Subscribers = Arc<Mutex<Vec<Channel<Value>>>>;
+
+// how often the log is checked for new lines
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Forward each line appended to the event log at `path` to `subscribers`
+/// until `alive` clears, then once more for what was written meanwhile.
+pub fn spawn_tail(path: PathBuf, alive: Arc<AtomicBool>, subscribers: Subscribers) {
+    thread::spawn(move || {
+        let Ok(file) = File::open(&path) else { return };
+        let mut reader = BufReader::new(file);
+        let mut line = String::new();
+        loop {
+            let running = alive.load(Ordering::Relaxed);
+            // read_line returns a partial line at EOF; keep it until the rest arrives
+            while reader.read_line(&mut line).is_ok_and(|n| n > 0) && line.ends_with('\n') {
+                forward(&line, &subscribers);
+                line.clear();
+            }
+            if !running {
+                break;
+            }
+            thread::sleep(POLL_INTERVAL);
+        }
+    });
+}
+
+/// Send one log line to every subscriber, dropping those whose webview is gone.
+fn forward(line: &str, subscribers: &Subscribers) {
+    let Ok(event) = serde_json::from_str::<Value>(line) else { return };
+    if event["type"] == "Ready" {
+        return;
+    }
+    subscribers.lock().unwrap().retain(|channel| channel.send(event.clone()).is_ok());
+}
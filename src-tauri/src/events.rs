@@ -0,0 +1,107 @@
+//! Reading the helper's NDJSON event log and placing events on the video timeline.
+//! Both the helper and the app stamp times as seconds since the UNIX epoch, so
+//! offsets are taken against the session start in that same base.
+
+use std::path::{Path, PathBuf};
+
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::RecordingError;
+
+#[derive(Serialize)]
+struct IndexedEvent<'a> {
+    frame: u64,
+    time_offset: f64, // seconds into the output video
+    event: &'a Value,
+}
+
+/// Every parseable record in the log, in order; the helper's startup line is skipped.
+pub fn read_events(path: &Path) -> Vec<Value> {
+    let Ok(log) = std::fs::read_to_string(path) else { return Vec::new() };
+    log.lines()
+        .filter_map(|line| serde_json::from_str::<Value>(line).ok())
+        .filter(|event| event["type"] != "Ready")
+        .collect()
+}
+
+/// Seconds into the output video at which `event` happened, or `None` if it
+/// predates the recording. `speed` is the timelapse factor (1.0 for real time).
+/// The helper's recording-relative `offset` is used when present; logs written
+/// before it existed fall back to the epoch `timestamp`.
+pub fn video_time(event: &Value, start_epoch: f64, speed: f64) -> Option<f64> {
+    let offset = match event["offset"].as_f64() {
+        Some(offset) => offset,
+        None => event["timestamp"].as_f64()? - start_epoch,
+    };
+    (offset >= 0.0).then_some(offset / speed)
+}
+
+/// Write `events_indexed.json` next to the log, pairing each event with the
+/// output frame it lands on.
+pub fn write_indexed(events_file: &Path, start_epoch: f64, fps: u32, speed: f64) -> Result<PathBuf, RecordingError> {
+    let events = read_events(events_file);
+    let indexed: Vec<IndexedEvent> = events
+        .iter()
+        .filter_map(|event| {
+            let time_offset = video_time(event, start_epoch, speed)?;
+            Some(IndexedEvent { frame: (time_offset * fps as f64) as u64, time_offset, event })
+        })
+        .collect();
+
+    let path = events_file.with_file_name("events_indexed.json");
+    let json = serde_json::to_string(&indexed)
+        .map_err(|e| RecordingError::Io(format!("Failed to serialize indexed events: {}", e)))?;
+    std::fs::write(&path, json)?;
+    Ok(path)
+}
+
+// presses closer together than this (key repeat, double taps) make one marker
+const MIN_CHAPTER_GAP: f64 = 1.0;
+
+/// Format seconds as a WebVTT timestamp (`HH:MM:SS.mmm`).
+fn vtt_time(secs: f64) -> String {
+    let ms = (secs.max(0.0) * 1000.0).round() as u64;
+    format!("{:02}:{:02}:{:02}.{:03}", ms / 3_600_000, ms / 60_000 % 60, ms / 1000 % 60, ms % 1000)
+}
+
+/// Write `chapters.vtt` next to the log, starting a new chapter at each press
+/// of `key` (an rdev key name such as "F8"). `video_secs` is the output length.
+/// Returns `None` when the key was never pressed.
+pub fn write_chapters(
+    events_file: &Path,
+    start_epoch: f64,
+    speed: f64,
+    key: &str,
+    video_secs: f64,
+) -> Result<Option<PathBuf>, RecordingError> {
+    let mut markers: Vec<f64> = Vec::new();
+    for event in read_events(events_file) {
+        if event["type"] != "KeyPress" || event["key"] != key {
+            continue;
+        }
+        let Some(t) = video_time(&event, start_epoch, speed) else { continue };
+        if t < video_secs && markers.last().is_none_or(|last| t - last >= MIN_CHAPTER_GAP) {
+            markers.push(t);
+        }
+    }
+    if markers.is_empty() {
+        return Ok(None);
+    }
+
+    // the first chapter runs from the start to the first marker
+    if markers[0] >= MIN_CHAPTER_GAP {
+        markers.insert(0, 0.0);
+    } else {
+        markers[0] = 0.0;
+    }
+    let mut vtt = String::from("WEBVTT\n");
+    for (i, start) in markers.iter().enumerate() {
+        let end = markers.get(i + 1).copied().unwrap_or(video_secs);
+        vtt.push_str(&format!("\n{}\n{} --> {}\nChapter {}\n", i + 1, vtt_time(*start), vtt_time(end), i + 1));
+    }
+
+    let path = events_file.with_file_name("chapters.vtt");
+    std::fs::write(&path, vtt)?;
+    Ok(Some(path))
+}
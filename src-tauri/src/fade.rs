@@ -0,0 +1,77 @@
+//! Fade-in and fade-out: a final ffmpeg pass over the finished recording,
+//! since where the fade-out starts depends on a length only known at stop.
+
+use std::path::Path;
+use std::process::{Command, Stdio};
+
+use crate::ffmpeg;
+use crate::{RecordingError, RecordingOptions};
+
+/// Fade lengths that fit `duration`: when the two together are longer than
+/// the video they shrink in proportion, so both still happen.
+pub fn fit(fade_in: f64, fade_out: f64, duration: f64) -> (f64, f64) {
+    let total = fade_in + fade_out;
+    if total <= duration || total <= 0.0 {
+        return (fade_in, fade_out);
+    }
+    let scale = duration.max(0.0) / total;
+    (fade_in * scale, fade_out * scale)
+}
+
+/// Re-encode `video` with the requested fades, returning the lengths used.
+/// `duration` is the media duration of the file as measured after encoding.
+pub fn apply(
+    opts: &RecordingOptions,
+    video: &Path,
+    (fade_in, fade_out): (f64, f64),
+    duration: f64,
+) -> Result<(f64, f64), RecordingError> {
+    let (fade_in, fade_out) = fit(fade_in, fade_out, duration);
+    let mut video_filters = Vec::new();
+    let mut audio_filters = Vec::new();
+    if fade_in > 0.0 {
+        video_filters.push(format!("fade=t=in:st=0:d={:.3}", fade_in));
+        audio_filters.push(format!("afade=t=in:st=0:d={:.3}", fade_in));
+    }
+    if fade_out > 0.0 {
+        let start = (duration - fade_out).max(0.0);
+        video_filters.push(format!("fade=t=out:st={:.3}:d={:.3}", start, fade_out));
+        audio_filters.push(format!("afade=t=out:st={:.3}:d={:.3}", start, fade_out));
+    }
+    if video_filters.is_empty() {
+        return Ok((0.0, 0.0));
+    }
+    if opts.output_format()? == "gif" {
+        video_filters.push("split[a][b];[a]palettegen[p];[b][p]paletteuse".into());
+    }
+
+    let ffprobe = ffmpeg::ffprobe_bin(opts.ffmpeg_bin());
+    let has_audio = ffmpeg::ffprobe_lines(&ffprobe, &["-select_streams", "a", "-show_entries", "stream=index"], video)
+        .is_some_and(|streams| !streams.is_empty());
+
+    let dir = video.parent().unwrap_or(Path::new("."));
+    let ext = video.extension().and_then(|e| e.to_str()).unwrap_or("mp4");
+    let tmp = dir.join(format!("output.fade.{}", ext));
+    let mut command = Command::new(opts.ffmpeg_bin());
+    command
+        .args(["-y", "-i"])
+        .arg(video)
+        .args(["-map_metadata", "0", "-vf", &video_filters.join(",")])
+        .args(opts.video_codec_args()?);
+    if has_audio {
+        command.args(["-af", &audio_filters.join(",")]).args(opts.audio_codec_args()?);
+    }
+    let status = command
+        .arg(&tmp)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .map_err(|e| ffmpeg::spawn_error(opts.ffmpeg_bin(), e))?;
+    if !status.success() {
+        let _ = std::fs::remove_file(&tmp);
+        return Err(RecordingError::FfmpegFailed(format!("fade pass exited with {}", status)));
+    }
+    std::fs::rename(&tmp, video)?;
+    Ok((fade_in, fade_out))
+}
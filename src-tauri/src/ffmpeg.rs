@@ -0,0 +1,338 @@
+//! Helpers for assembling and supervising ffmpeg invocations.
+
+use std::collections::VecDeque;
+use std::io::{BufRead, BufReader};
+use std::path::{Path, PathBuf};
+use std::process::{Child, ChildStderr, Command, ExitStatus, Stdio};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+use crate::{RecordingError, RecordingOptions};
+
+/// Incrementally built `-filter_complex` graph over the piped screen input (`0:v`).
+pub struct FilterGraph {
+    parts: Vec<String>,
+    last: String,
+    audio: Option<String>,
+}
+
+impl FilterGraph {
+    pub fn new() -> Self {
+        FilterGraph { parts: Vec::new(), last: "0:v".into(), audio: None }
+    }
+
+    /// Add a side chain with its own labels, e.g. `[1:v]scale=320:-2[cam]`.
+    pub fn side(&mut self, part: String) {
+        self.parts.push(part);
+    }
+
+    /// Feed the current video output (plus any `extra_inputs` labels) into `filter`.
+    pub fn chain(&mut self, extra_inputs: &str, filter: &str) {
+        let out = format!("v{}", self.parts.len());
+        self.parts.push(format!("[{}]{}{}[{}]", self.last, extra_inputs, filter, out));
+        self.last = out;
+    }
+
+    /// Map an audio stream (`2:a`) or graph output (`[aout]`) alongside the video.
+    pub fn map_audio(&mut self, spec: String) {
+        self.audio = Some(spec);
+    }
+
+    /// `-filter_complex ... -map <video> [-map <audio>]`.
+    pub fn into_args(self) -> Vec<String> {
+        let mut args = Vec::new();
+        if !self.parts.is_empty() {
+            args.extend(["-filter_complex".into(), self.parts.join(";")]);
+        }
+        // the raw input is mapped directly; graph outputs need brackets
+        let video = if self.last == "0:v" { self.last } else { format!("[{}]", self.last) };
+        args.extend(["-map".into(), video]);
+        if let Some(audio) = self.audio {
+            args.extend(["-map".into(), audio]);
+        }
+        args
+    }
+}
+
+// lines of ffmpeg's stderr kept for error reports
+const STDERR_TAIL_LINES: usize = 20;
+
+/// The last lines ffmpeg wrote to stderr, collected by a reader thread.
+#[derive(Clone, Default)]
+pub struct StderrTail {
+    lines: Arc<Mutex<VecDeque<String>>>,
+    reader: Arc<Mutex<Option<JoinHandle<()>>>>,
+}
+
+impl StderrTail {
+    /// Drain `stderr` on a background thread until ffmpeg closes it.
+    pub fn capture(stderr: ChildStderr) -> Self {
+        let tail = StderrTail::default();
+        let lines = tail.lines.clone();
+        let reader = thread::spawn(move || {
+            for line in BufReader::new(stderr).lines().map_while(Result::ok) {
+                let mut lines = lines.lock().unwrap();
+                if lines.len() == STDERR_TAIL_LINES {
+                    lines.pop_front();
+                }
+                lines.push_back(line);
+            }
+        });
+        *tail.reader.lock().unwrap() = Some(reader);
+        tail
+    }
+
+    /// The buffered lines joined with newlines; empty if ffmpeg said nothing.
+    pub fn text(&self) -> String {
+        self.lines.lock().unwrap().iter().map(String::as_str).collect::<Vec<_>>().join("\n")
+    }
+
+    /// `text` once everything ffmpeg wrote has been read; for a process that
+    /// has exited, whose last lines are usually the ones that explain why.
+    pub fn final_text(&self) -> String {
+        if let Some(reader) = self.reader.lock().unwrap().take() {
+            let _ = reader.join();
+        }
+        self.text()
+    }
+}
+
+/// Re-encode `video` through `graph` with the recording's codecs, replacing
+/// it. `inputs` are input arguments after the video, which is input 0, and
+/// `pass` names the pass in the temporary file and in errors. ffmpeg runs in
+/// the video's folder, so files next to it are named without escaping paths.
+pub fn reencode_in_place(
+    opts: &RecordingOptions,
+    video: &Path,
+    inputs: &[&str],
+    mut graph: FilterGraph,
+    pass: &str,
+) -> Result<(), RecordingError> {
+    if opts.output_format()? == "gif" {
+        graph.chain("", "split[a][b];[a]palettegen[p];[b][p]paletteuse");
+    }
+    graph.map_audio("0:a?".into());
+
+    let dir = video.parent().unwrap_or(Path::new("."));
+    let ext = video.extension().and_then(|e| e.to_str()).unwrap_or("mp4");
+    let tmp = dir.join(format!("output.{}.{}", pass, ext));
+    let mut child = Command::new(opts.ffmpeg_bin())
+        .current_dir(dir)
+        .args(["-y", "-loglevel", "error", "-i"])
+        .arg(video)
+        .args(inputs)
+        .args(graph.into_args())
+        .args(["-map_metadata", "0"])
+        .args(opts.video_codec_args()?)
+        .args(opts.audio_codec_args()?)
+        .arg(&tmp)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| spawn_error(opts.ffmpeg_bin(), e))?;
+    let stderr = child.stderr.take().map(StderrTail::capture);
+    let status = child.wait()?;
+    if !status.success() {
+        let _ = std::fs::remove_file(&tmp);
+        let message = format!("{} pass exited with {}", pass, status);
+        return Err(RecordingError::FfmpegFailed(
+            match stderr.as_ref().map(StderrTail::final_text).filter(|t| !t.is_empty()) {
+                Some(output) => format!("{}:\n{}", message, output),
+                None => message,
+            },
+        ));
+    }
+    std::fs::rename(&tmp, video)?;
+    Ok(())
+}
+
+/// The ffprobe next to `bin`, or the one on PATH when `bin` is a bare name.
+pub fn ffprobe_bin(bin: &str) -> PathBuf {
+    let name = format!("ffprobe{}", std::env::consts::EXE_SUFFIX);
+    match Path::new(bin).parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir.join(name),
+        _ => PathBuf::from(name),
+    }
+}
+
+/// Run ffprobe and return its stdout lines, or `None` if it failed.
+pub fn ffprobe_lines(ffprobe: &Path, args: &[&str], path: &Path) -> Option<Vec<String>> {
+    let out = Command::new(ffprobe)
+        .args(["-v", "error"])
+        .args(args)
+        .args(["-of", "default=noprint_wrappers=1:nokey=1"])
+        .arg(path)
+        .stdin(Stdio::null())
+        .stderr(Stdio::null())
+        .output()
+        .ok()?;
+    out.status.success().then(|| {
+        String::from_utf8_lossy(&out.stdout)
+            .lines()
+            .map(|l| l.trim().to_string())
+            .filter(|l| !l.is_empty())
+            .collect()
+    })
+}
+
+/// Container duration in seconds, as ffprobe reports it.
+pub fn duration(ffprobe: &Path, path: &Path) -> Option<f64> {
+    ffprobe_lines(ffprobe, &["-show_entries", "format=duration"], path)?.first()?.parse().ok()
+}
+
+/// Make a user-provided string safe to pass as an ffmpeg metadata value.
+/// Arguments never go through a shell, but control characters (newlines in
+/// particular) end up in muxer headers and can break them.
+pub fn sanitize_metadata(value: &str) -> String {
+    value
+        .chars()
+        .map(|c| if c.is_control() { ' ' } else { c })
+        .collect::<String>()
+        .trim()
+        .to_string()
+}
+
+/// Map a failure to spawn ffmpeg, distinguishing a missing binary.
+pub fn spawn_error(bin: &str, e: std::io::Error) -> RecordingError {
+    if e.kind() == std::io::ErrorKind::NotFound {
+        RecordingError::FfmpegNotFound(bin.into())
+    } else {
+        RecordingError::FfmpegFailed(format!("failed to launch {}: {}", bin, e))
+    }
+}
+
+/// Check that ffmpeg can open `input_args` and read a moment of data within `timeout`.
+pub fn probe_input(bin: &str, input_args: &[String], timeout: Duration) -> bool {
+    let child = Command::new(bin)
+        .args(["-hide_banner", "-loglevel", "error"])
+        .args(input_args)
+        .args(["-t", "0.2", "-f", "null", "-"])
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn();
+    let Ok(mut child) = child else { return false };
+
+    let deadline = Instant::now() + timeout;
+    loop {
+        match child.try_wait() {
+            Ok(Some(status)) => return status.success(),
+            Ok(None) if Instant::now() < deadline => thread::sleep(Duration::from_millis(50)),
+            _ => {
+                let _ = child.kill();
+                let _ = child.wait();
+                return false;
+            }
+        }
+    }
+}
+
+/// Wait for `child` to exit, for at most `timeout`. `None` means it is still running.
+pub fn wait_within(child: &mut Child, timeout: Duration) -> std::io::Result<Option<ExitStatus>> {
+    let deadline = Instant::now() + timeout;
+    loop {
+        match child.try_wait()? {
+            Some(status) => return Ok(Some(status)),
+            None if Instant::now() < deadline => thread::sleep(Duration::from_millis(50)),
+            None => return Ok(None),
+        }
+    }
+}
+
+/// Save the frame at `at_secs` of `video` as a JPEG at most 640px wide.
+pub fn thumbnail(bin: &str, video: &Path, at_secs: f64, out: &Path) -> Result<(), String> {
+    let result = Command::new(bin)
+        .args(["-y", "-hide_banner", "-loglevel", "error", "-ss", &format!("{:.3}", at_secs), "-i"])
+        .arg(video)
+        .args(["-frames:v", "1", "-vf", "scale='min(640,iw)':-2", "-q:v", "4"])
+        .arg(out)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .output()
+        .map_err(|e| e.to_string())?;
+    if result.status.success() && out.is_file() {
+        Ok(())
+    } else {
+        Err(String::from_utf8_lossy(&result.stderr).trim().to_string())
+    }
+}
+
+/// Names of the encoders this ffmpeg build lists in `ffmpeg -encoders`.
+pub fn list_encoders(bin: &str) -> Vec<String> {
+    let Ok(out) = Command::new(bin)
+        .args(["-hide_banner", "-encoders"])
+        .stdin(Stdio::null())
+        .stderr(Stdio::null())
+        .output()
+    else {
+        return Vec::new();
+    };
+    // lines look like " V....D h264_nvenc           NVIDIA NVENC H.264 encoder"
+    String::from_utf8_lossy(&out.stdout)
+        .lines()
+        .filter_map(|line| {
+            let mut cols = line.split_whitespace();
+            let flags = cols.next()?;
+            let name = cols.next()?;
+            // the legend above the list ("V..... = Video") has the same shape
+            (flags.len() == 6 && flags.starts_with('V') && name != "=").then(|| name.to_string())
+        })
+        .collect()
+}
+
+/// Whether `encoder` can actually encode here; hardware encoders are listed
+/// even when the GPU or driver they need is missing.
+pub fn encoder_works(bin: &str, encoder: &str) -> bool {
+    Command::new(bin)
+        .args(["-hide_banner", "-loglevel", "error",
+               "-f", "lavfi", "-i", "color=black:s=256x256",
+               "-frames:v", "1", "-c:v", encoder, "-f", "null", "-"])
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .is_ok_and(|s| s.success())
+}
+
+// monospace first so the timecode doesn't jitter
+const FONT_CANDIDATES: &[&str] = &[
+    "/System/Library/Fonts/Menlo.ttc",
+    "/System/Library/Fonts/Monaco.ttf",
+    "/System/Library/Fonts/Supplemental/Courier New.ttf",
+    "C:/Windows/Fonts/consola.ttf",
+    "C:/Windows/Fonts/cour.ttf",
+    "C:/Windows/Fonts/arial.ttf",
+    "/usr/share/fonts/truetype/dejavu/DejaVuSansMono.ttf",
+    "/usr/share/fonts/TTF/DejaVuSansMono.ttf",
+    "/usr/share/fonts/dejavu/DejaVuSansMono.ttf",
+    "/usr/share/fonts/truetype/liberation/LiberationMono-Regular.ttf",
+];
+
+/// A font file for `drawtext`, which can't fall back to a default without fontconfig.
+pub fn find_font() -> Option<&'static str> {
+    FONT_CANDIDATES.iter().copied().find(|p| Path::new(p).is_file())
+}
+
+/// Escape a path for use as a quoted filter option value.
+pub fn escape_filter_path(path: &str) -> String {
+    path.replace('\\', "/").replace(':', "\\:").replace('\'', "\\'")
+}
+
+/// Run `filter` over a synthetic frame; on failure returns ffmpeg's error output.
+pub fn check_filter(bin: &str, filter: &str) -> Result<(), String> {
+    let out = Command::new(bin)
+        .args(["-hide_banner", "-loglevel", "error",
+               "-f", "lavfi", "-i", "color=black:s=256x256",
+               "-frames:v", "1", "-vf", filter, "-f", "null", "-"])
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .output()
+        .map_err(|e| e.to_string())?;
+    if out.status.success() {
+        Ok(())
+    } else {
+        Err(String::from_utf8_lossy(&out.stderr).trim().to_string())
+    }
+}
@@ -0,0 +1,53 @@
+//! `follow_cursor`: a crop window that moves with the mouse, for zoomed-in
+//! tutorials. The pointer is queried directly before each frame is cropped,
+//! so no event helper or input permission is needed. The window's centre
+//! eases towards the pointer instead of jumping with every small move.
+
+use std::time::Instant;
+
+use mouse_position::mouse_position::Mouse;
+
+use crate::clicks::ClickMapping;
+
+// seconds for the window to cover about two thirds of the way to the pointer
+const SMOOTHING_SECS: f64 = 0.2;
+
+/// Where the crop window is and how it gets to the pointer.
+pub struct CursorFollower {
+    size: (u32, u32),
+    frame: (u32, u32),
+    mapping: ClickMapping, // pointer coordinates to frame pixels
+    center: (f64, f64),
+    moved: Instant,
+}
+
+impl CursorFollower {
+    /// A `size` window inside frames of `frame`, starting in the middle.
+    pub fn new(size: (u32, u32), frame: (u32, u32), mapping: ClickMapping) -> Self {
+        CursorFollower {
+            size,
+            frame,
+            mapping,
+            center: (frame.0 as f64 / 2.0, frame.1 as f64 / 2.0),
+            moved: Instant::now(),
+        }
+    }
+
+    /// The region to crop the next frame to, moved towards the pointer and
+    /// kept inside the frame. A failed query leaves the window where it is.
+    pub fn region(&mut self) -> (u32, u32, u32, u32) {
+        let elapsed = self.moved.elapsed().as_secs_f64();
+        self.moved = Instant::now();
+        if let Mouse::Position { x, y } = Mouse::get_mouse_position() {
+            let (tx, ty) = self.mapping.to_video(x as f64, y as f64);
+            let step = 1.0 - (-elapsed / SMOOTHING_SECS).exp();
+            self.center.0 += (tx - self.center.0) * step;
+            self.center.1 += (ty - self.center.1) * step;
+        }
+        let (w, h) = self.size;
+        let place = |center: f64, size: u32, limit: u32| {
+            (center - size as f64 / 2.0).round().clamp(0.0, (limit - size) as f64) as u32
+        };
+        (place(self.center.0, w, self.frame.0), place(self.center.1, h, self.frame.1), w, h)
+    }
+}
@@ -0,0 +1,45 @@
+//! `frame_timestamps.csv`: when each frame was handed to the encoder, for
+//! lining the video up with other recorders. The encoder stamps frames at an
+//! even `index / fps`; this keeps the real, jittery timing so a later remux
+//! can use it instead. It's the send time, not the grab: a frame repeated to
+//! fill a slot gets a row of its own, and the grabber may have taken a frame
+//! up to one frame interval before it was sent. Only frames `motion_only`
+//! held back while confirming a change carry the time they were seen.
+
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+use crate::RecordingError;
+
+// rows are buffered in between, keeping file writes off the capture loop
+const FLUSH_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Appends one `frame_index,offset_ms` row per frame sent to the encoder.
+pub struct FrameTimestamps {
+    file: BufWriter<File>,
+    last_flush: Instant,
+}
+
+impl FrameTimestamps {
+    pub fn create(path: &Path) -> Result<Self, RecordingError> {
+        let mut file = BufWriter::new(File::create(path)?);
+        writeln!(file, "frame_index,offset_ms")?;
+        Ok(FrameTimestamps { file, last_flush: Instant::now() })
+    }
+
+    /// Note frame `index`, sent to the encoder `offset` after the recording started.
+    pub fn record(&mut self, index: u64, offset: Duration) -> std::io::Result<()> {
+        writeln!(self.file, "{},{:.3}", index, offset.as_secs_f64() * 1000.0)?;
+        if self.last_flush.elapsed() >= FLUSH_INTERVAL {
+            self.last_flush = Instant::now();
+            self.file.flush()?;
+        }
+        Ok(())
+    }
+
+    pub fn finish(mut self) -> std::io::Result<()> {
+        self.file.flush()
+    }
+}
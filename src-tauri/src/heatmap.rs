@@ -0,0 +1,129 @@
+//! Mouse activity images for a finished session: the cursor's path as a trail,
+//! or a density heatmap of where it spent its time. Both are transparent PNGs
+//! at the recording's resolution, meant to be laid over a frame of the video.
+
+use std::path::{Path, PathBuf};
+
+use image::{Rgba, RgbaImage};
+
+use crate::events;
+use crate::{RecordingError, RecordingManifest};
+
+// radius of the blob each sample adds to the heatmap, in video pixels
+const HEAT_RADIUS: i64 = 24;
+
+/// Which image `export` draws.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Style {
+    Trail,
+    Heatmap,
+}
+
+impl Style {
+    pub fn parse(value: Option<&str>) -> Result<Self, RecordingError> {
+        match value.unwrap_or("heatmap") {
+            "heatmap" => Ok(Style::Heatmap),
+            "trail" => Ok(Style::Trail),
+            other => Err(RecordingError::InvalidOptions(format!(
+                "Unknown heatmap style '{}': expected heatmap or trail",
+                other
+            ))),
+        }
+    }
+}
+
+/// Blend from blue through green and yellow to red as `t` goes from 0 to 1.
+fn ramp(t: f64) -> [u8; 3] {
+    const STOPS: [[f64; 3]; 4] = [[0.0, 64.0, 255.0], [0.0, 220.0, 90.0], [255.0, 220.0, 0.0], [255.0, 32.0, 0.0]];
+    let t = t.clamp(0.0, 1.0) * (STOPS.len() - 1) as f64;
+    let i = (t.floor() as usize).min(STOPS.len() - 2);
+    let f = t - i as f64;
+    let mix = |c: usize| (STOPS[i][c] + (STOPS[i + 1][c] - STOPS[i][c]) * f) as u8;
+    [mix(0), mix(1), mix(2)]
+}
+
+fn draw_trail(img: &mut RgbaImage, points: &[(f64, f64)]) {
+    let (w, h) = (img.width() as i64, img.height() as i64);
+    let segments = points.len().saturating_sub(1).max(1) as f64;
+    for (i, pair) in points.windows(2).enumerate() {
+        // older movement is blue, recent movement red
+        let [r, g, b] = ramp(i as f64 / segments);
+        let ((x0, y0), (x1, y1)) = (pair[0], pair[1]);
+        let steps = (x1 - x0).abs().max((y1 - y0).abs()).ceil().max(1.0) as usize;
+        for s in 0..=steps {
+            let t = s as f64 / steps as f64;
+            let (x, y) = ((x0 + (x1 - x0) * t) as i64, (y0 + (y1 - y0) * t) as i64);
+            // 2px wide so the path is visible at full-HD sizes
+            for (dx, dy) in [(0, 0), (1, 0), (0, 1), (1, 1)] {
+                let (px, py) = (x + dx, y + dy);
+                if px >= 0 && py >= 0 && px < w && py < h {
+                    img.put_pixel(px as u32, py as u32, Rgba([r, g, b, 230]));
+                }
+            }
+        }
+    }
+}
+
+fn draw_heatmap(img: &mut RgbaImage, points: &[(f64, f64)]) {
+    let (w, h) = (img.width() as i64, img.height() as i64);
+    let mut density = vec![0f64; (w * h) as usize];
+    let sigma2 = (HEAT_RADIUS as f64 / 2.0).powi(2);
+    for &(x, y) in points {
+        let (cx, cy) = (x as i64, y as i64);
+        for py in (cy - HEAT_RADIUS).max(0)..(cy + HEAT_RADIUS + 1).min(h) {
+            for px in (cx - HEAT_RADIUS).max(0)..(cx + HEAT_RADIUS + 1).min(w) {
+                let d2 = ((px - cx).pow(2) + (py - cy).pow(2)) as f64;
+                density[(py * w + px) as usize] += (-d2 / (2.0 * sigma2)).exp();
+            }
+        }
+    }
+    let max = density.iter().copied().fold(0.0, f64::max);
+    if max <= 0.0 {
+        return;
+    }
+    for (i, &d) in density.iter().enumerate() {
+        if d <= 0.0 {
+            continue;
+        }
+        // square root keeps rarely visited areas visible next to hot spots
+        let t = (d / max).sqrt();
+        let [r, g, b] = ramp(t);
+        img.put_pixel((i as i64 % w) as u32, (i as i64 / w) as u32, Rgba([r, g, b, (t * 200.0) as u8]));
+    }
+}
+
+/// Render the session's mouse movement into `mouse_heatmap.png` in `session_dir`.
+pub fn export(session_dir: &Path, style: Style) -> Result<PathBuf, RecordingError> {
+    let manifest: RecordingManifest = std::fs::read_to_string(session_dir.join("manifest.json"))
+        .ok()
+        .and_then(|json| serde_json::from_str(&json).ok())
+        .ok_or_else(|| RecordingError::Io(format!("{} has no readable manifest.json", session_dir.display())))?;
+    let events_file = manifest
+        .events_file
+        .as_deref()
+        .ok_or_else(|| RecordingError::InvalidOptions("this recording has no event log".into()))?;
+    let mapping = manifest.screen_mapping.ok_or_else(|| {
+        RecordingError::InvalidOptions("mouse positions can't be placed on this recording's frame".into())
+    })?;
+
+    let (w, h) = (manifest.width as f64, manifest.height as f64);
+    let points: Vec<(f64, f64)> = events::read_events(Path::new(events_file))
+        .iter()
+        .filter(|event| event["type"] == "MouseMove")
+        .filter(|event| event["timestamp"].as_f64().is_some_and(|t| t >= manifest.started_epoch))
+        .filter_map(|event| Some(mapping.to_video(event["x"].as_f64()?, event["y"].as_f64()?)))
+        .filter(|&(x, y)| x >= 0.0 && y >= 0.0 && x < w && y < h)
+        .collect();
+    if points.is_empty() {
+        return Err(RecordingError::InvalidOptions("no mouse movement was recorded inside the frame".into()));
+    }
+
+    let mut img = RgbaImage::new(manifest.width, manifest.height);
+    match style {
+        Style::Trail => draw_trail(&mut img, &points),
+        Style::Heatmap => draw_heatmap(&mut img, &points),
+    }
+    let path = session_dir.join("mouse_heatmap.png");
+    img.save(&path)?;
+    Ok(path)
+}
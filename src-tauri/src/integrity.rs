@@ -0,0 +1,131 @@
+//! Per-frame SHA-256 hashes of the raw BGRA handed to the encoder, and a check
+//! of a finished recording against them. Lossy encoding changes pixels, so
+//! only lossless recordings without pixel-altering filters can be verified;
+//! for the rest the hashes still document exactly what was captured.
+
+use std::fs::File;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::path::Path;
+use std::process::{Command, Stdio};
+
+use serde::Serialize;
+use serde_json::json;
+use sha2::{Digest, Sha256};
+
+use crate::{ffmpeg, RecordingError, RecordingManifest};
+
+// mismatching frame indices listed in a report; the count covers the rest
+const MAX_REPORTED_MISMATCHES: usize = 100;
+
+/// Appends one `{"frame_index", "hash"}` line per frame to `frame_hashes.jsonl`.
+pub struct FrameHasher {
+    file: File,
+    index: u64,
+    last_hash: String,
+}
+
+impl FrameHasher {
+    pub fn create(path: &Path) -> Result<Self, RecordingError> {
+        Ok(FrameHasher { file: File::create(path)?, index: 0, last_hash: String::new() })
+    }
+
+    /// Hash the next frame. Each line is written straight through, so the
+    /// file holds every frame that reached the encoder even after a crash.
+    pub fn record(&mut self, frame: &[u8]) -> std::io::Result<()> {
+        self.last_hash = sha256_hex(frame);
+        self.repeat()
+    }
+
+    /// The previous frame again, for a slot the encoder fills with it.
+    pub fn repeat(&mut self) -> std::io::Result<()> {
+        let line = json!({ "frame_index": self.index, "hash": self.last_hash });
+        self.index += 1;
+        writeln!(self.file, "{}", line)
+    }
+}
+
+fn sha256_hex(data: &[u8]) -> String {
+    Sha256::digest(data).iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// Returned by `verify_integrity`.
+#[derive(Debug, Serialize)]
+pub struct IntegrityReport {
+    pub passed: bool,
+    pub frames_recorded: u64,
+    pub frames_decoded: u64,
+    /// Frames whose decoded pixels don't match their hash (the first 100).
+    pub mismatched_frames: Vec<u64>,
+    pub mismatch_count: u64,
+}
+
+/// Decode the session's video and compare every frame with its recorded hash.
+pub fn verify(bin: &str, session_dir: &Path) -> Result<IntegrityReport, RecordingError> {
+    let manifest: RecordingManifest = std::fs::read_to_string(session_dir.join("manifest.json"))
+        .ok()
+        .and_then(|json| serde_json::from_str(&json).ok())
+        .ok_or_else(|| RecordingError::Io(format!("{} has no readable manifest.json", session_dir.display())))?;
+    let hashes_file = manifest
+        .frame_hashes_file
+        .as_deref()
+        .ok_or_else(|| RecordingError::InvalidOptions("this recording was made without frame_hashes".into()))?;
+    if !manifest.hashes_verifiable {
+        return Err(RecordingError::InvalidOptions(
+            "only lossless recordings without filters, webcam or speed changes can be checked; \
+             the hashes describe the frames before lossy encoding"
+                .into(),
+        ));
+    }
+
+    let expected: Vec<String> = BufReader::new(File::open(hashes_file)?)
+        .lines()
+        .map_while(Result::ok)
+        .filter_map(|line| {
+            let entry: serde_json::Value = serde_json::from_str(&line).ok()?;
+            entry["hash"].as_str().map(String::from)
+        })
+        .collect();
+
+    let mut child = Command::new(bin)
+        .args(["-v", "error", "-i"])
+        .arg(&manifest.output_path)
+        .args(["-map", "0:v:0", "-f", "rawvideo", "-pix_fmt", "bgra", "-"])
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()
+        .map_err(|e| ffmpeg::spawn_error(bin, e))?;
+    let mut stdout = child
+        .stdout
+        .take()
+        .ok_or_else(|| RecordingError::FfmpegFailed("stdout unavailable".into()))?;
+
+    let mut frame = vec![0u8; manifest.width as usize * manifest.height as usize * 4];
+    let mut decoded = 0u64;
+    let mut mismatched_frames = Vec::new();
+    let mut mismatch_count = 0u64;
+    while stdout.read_exact(&mut frame).is_ok() {
+        let matches = expected.get(decoded as usize).is_some_and(|hash| *hash == sha256_hex(&frame));
+        if !matches {
+            mismatch_count += 1;
+            if mismatched_frames.len() < MAX_REPORTED_MISMATCHES {
+                mismatched_frames.push(decoded);
+            }
+        }
+        decoded += 1;
+    }
+    let status = child.wait()?;
+    if !status.success() {
+        return Err(RecordingError::FfmpegFailed(format!("decoding for verification exited with {}", status)));
+    }
+
+    let recorded = expected.len() as u64;
+    Ok(IntegrityReport {
+        // frames missing from the video count against it as well
+        passed: mismatch_count == 0 && decoded == recorded,
+        frames_recorded: recorded,
+        frames_decoded: decoded,
+        mismatched_frames,
+        mismatch_count,
+    })
+}
@@ -0,0 +1,185 @@
+//! Keycaps: a final ffmpeg pass that captions each key pressed, modifiers
+//! included ("⌘C"), in the bottom-right corner for shortcut tutorials. A new
+//! press replaces the caption on screen, and repeats of the same one count
+//! up instead ("⌘Z ×3").
+
+use std::fmt::Write as _;
+use std::path::Path;
+
+use crate::events;
+use crate::ffmpeg::{self, FilterGraph};
+use crate::{RecordingError, RecordingOptions};
+
+// how long a caption stays up when nothing replaces it
+const CAPTION_SECS: f64 = 1.5;
+// (rdev names, symbol on macOS, prefix elsewhere), in the order they're shown
+const MODIFIERS: &[(&[&str], &str, &str)] = &[
+    (&["ControlLeft", "ControlRight"], "⌃", "Ctrl+"),
+    (&["Alt", "AltGr"], "⌥", "Alt+"),
+    (&["ShiftLeft", "ShiftRight"], "⇧", "Shift+"),
+    (&["MetaLeft", "MetaRight"], "⌘", "Win+"),
+];
+
+/// How a non-modifier key is shown, from its rdev name.
+fn key_label(name: &str) -> String {
+    if let Some(letter) = name.strip_prefix("Key").filter(|l| l.len() == 1) {
+        return letter.into();
+    }
+    if let Some(digit) = name.strip_prefix("Num").filter(|d| d.len() == 1) {
+        return digit.into();
+    }
+    let label = match name {
+        "Return" | "KpReturn" => "Return",
+        "Escape" => "Esc",
+        "Backspace" => "⌫",
+        "Delete" => "Del",
+        "UpArrow" => "↑",
+        "DownArrow" => "↓",
+        "LeftArrow" => "←",
+        "RightArrow" => "→",
+        "Minus" | "KpMinus" => "-",
+        "Equal" => "=",
+        "KpPlus" => "+",
+        "KpMultiply" => "*",
+        "KpDivide" | "Slash" => "/",
+        "LeftBracket" => "[",
+        "RightBracket" => "]",
+        "SemiColon" => ";",
+        "Quote" => "'",
+        "BackSlash" | "IntlBackslash" => "\\",
+        "BackQuote" => "`",
+        "Comma" => ",",
+        "Dot" => ".",
+        // redacted logs keep the rhythm of typing but not the keys
+        "Redacted" => "•",
+        other => other,
+    };
+    label.into()
+}
+
+/// Captions from the event log as (seconds into the video, text), one per
+/// press of a non-modifier key. `speed` maps wall-clock offsets onto a
+/// sped-up video's timeline.
+fn load_captions(events_file: &Path, start_epoch: f64, speed: f64) -> Vec<(f64, String)> {
+    let mac = cfg!(target_os = "macos");
+    let mut held = [false; MODIFIERS.len()];
+    let mut captions = Vec::new();
+    for event in events::read_events(events_file) {
+        let pressed = match event["type"].as_str() {
+            Some("KeyPress") => true,
+            Some("KeyRelease") => false,
+            _ => continue,
+        };
+        let Some(key) = event["key"].as_str() else { continue };
+        if let Some(i) = MODIFIERS.iter().position(|(names, _, _)| names.contains(&key)) {
+            held[i] = pressed;
+            continue;
+        }
+        let Some(t) = events::video_time(&event, start_epoch, speed).filter(|_| pressed) else { continue };
+        let mut text: String = MODIFIERS
+            .iter()
+            .zip(held)
+            .filter(|(_, down)| *down)
+            .map(|((_, symbol, prefix), _)| if mac { *symbol } else { *prefix })
+            .collect();
+        text.push_str(&key_label(key));
+        captions.push((t, text));
+    }
+    captions
+}
+
+/// Each caption with the time it leaves the screen: when the next one
+/// replaces it or `CAPTION_SECS` later. A caption repeated while still up
+/// becomes one caption with a count.
+fn schedule(captions: Vec<(f64, String)>) -> Vec<(f64, f64, String)> {
+    let mut shown: Vec<(f64, f64, String, u32)> = Vec::new();
+    for (t, text) in captions {
+        if let Some(last) = shown.last_mut() {
+            if last.2 == text && t < last.1 {
+                last.1 = t + CAPTION_SECS;
+                last.3 += 1;
+                continue;
+            }
+            last.1 = last.1.min(t);
+        }
+        shown.push((t, t + CAPTION_SECS, text, 1));
+    }
+    shown
+        .into_iter()
+        .map(|(start, end, text, count)| match count {
+            1 => (start, end, text),
+            n => (start, end, format!("{} ×{}", text, n)),
+        })
+        .collect()
+}
+
+/// `text` as a drawtext option value inside a `sendcmd` script: quoted for
+/// the option parser, then escaped for the script's own tokenizer.
+fn script_text(text: &str) -> String {
+    let quoted = format!("text='{}'", text.replace('\'', r"'\''"));
+    quoted.chars().fold(String::new(), |mut escaped, c| {
+        if matches!(c, '\\' | '\'' | ',' | ';' | '[' | ']') {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+        escaped
+    })
+}
+
+/// An ffmpeg `sendcmd` script showing each caption on the drawtext named
+/// `drawtext@keys` while it's up and hiding it in between.
+fn caption_script(captions: &[(f64, f64, String)]) -> String {
+    let mut script = String::new();
+    for (start, end, text) in captions {
+        let _ = writeln!(
+            script,
+            "{:.3} drawtext@keys reinit {}, drawtext@keys enable 1;",
+            start,
+            script_text(text)
+        );
+        // commands at the same time run in file order, so a caption replaced
+        // at its end time hides before the next one shows
+        let _ = writeln!(script, "{:.3} drawtext@keys enable 0;", end);
+    }
+    script
+}
+
+/// Re-encode `video` with the keys pressed captioned at the times they were
+/// pressed, replacing it in place. Returns the number of captions drawn; the
+/// video is untouched when the log has no key presses.
+pub fn draw_keycaps(
+    opts: &RecordingOptions,
+    video: &Path,
+    events_file: &Path,
+    start_epoch: f64,
+    (_, h): (u32, u32),
+) -> Result<usize, RecordingError> {
+    let speed = opts.speed_factor()?.unwrap_or(1.0);
+    let captions = schedule(load_captions(events_file, start_epoch, speed));
+    if captions.is_empty() {
+        return Ok(0);
+    }
+    let font = ffmpeg::find_font()
+        .ok_or_else(|| RecordingError::FfmpegFailed("no TrueType font found to draw the keys with".into()))?;
+
+    // one drawtext retexted by a script, however many keys were pressed;
+    // ffmpeg runs in the session folder so the script's path needs no escaping
+    let dir = video.parent().unwrap_or(Path::new("."));
+    std::fs::write(dir.join("keycaps.txt"), caption_script(&captions))?;
+    let mut graph = FilterGraph::new();
+    let size = (h / 20).max(20);
+    graph.chain("", "sendcmd=f=keycaps.txt");
+    graph.chain(
+        "",
+        &format!(
+            "drawtext@keys=fontfile='{font}':text=' ':expansion=none:fontsize={size}:\
+             fontcolor=white:box=1:boxcolor=black@0.6:boxborderw={pad}:x=w-tw-{margin}:y=h-th-{margin}:enable=0",
+            font = ffmpeg::escape_filter_path(font),
+            pad = size / 2,
+            margin = size,
+        ),
+    );
+    let reencoded = ffmpeg::reencode_in_place(opts, video, &[], graph, "keycaps");
+    let _ = std::fs::remove_file(dir.join("keycaps.txt"));
+    reencoded.map(|()| captions.len())
+}
@@ -0,0 +1,65 @@
+//! Input levels for the `audio-level` event. ffmpeg measures every audio frame
+//! with `astats` and prints the figures to its stdout through `ametadata`,
+//! which passes the audio on untouched; a reader thread folds them into
+//! ~10 Hz updates, so metering never holds up the encode.
+
+use std::io::{BufRead, BufReader};
+use std::process::ChildStdout;
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::AudioLevel;
+
+/// Appended to the audio chain of a metered recording.
+pub const FILTERS: &str = "astats=metadata=1:reset=1,ametadata=mode=print:file=-:direct=1";
+
+// audio frames arrive ~45 times a second; report at most this often
+const REPORT_INTERVAL: Duration = Duration::from_millis(100);
+
+// reported for digital silence, which astats gives as -inf
+const FLOOR_DB: f64 = -100.0;
+
+const PEAK_KEY: &str = "lavfi.astats.Overall.Peak_level";
+const RMS_KEY: &str = "lavfi.astats.Overall.RMS_level";
+
+/// Where levels go; clones share the callback, so each segment's encoder
+/// reports to the same place.
+#[derive(Clone)]
+pub struct LevelMeter(Arc<dyn Fn(AudioLevel) + Send + Sync>);
+
+impl LevelMeter {
+    pub fn new(report: impl Fn(AudioLevel) + Send + Sync + 'static) -> Self {
+        LevelMeter(Arc::new(report))
+    }
+
+    /// Read the stdout of an encoder running `FILTERS` until it exits.
+    pub fn watch(&self, stdout: ChildStdout) {
+        let report = self.0.clone();
+        thread::spawn(move || {
+            let mut peak = f64::NEG_INFINITY;
+            let mut power = 0.0;
+            let mut frames = 0u32;
+            let mut last = Instant::now();
+            for line in BufReader::new(stdout).lines().map_while(Result::ok) {
+                let Some((key, value)) = line.split_once('=') else { continue };
+                // "-inf" for silence parses as negative infinity
+                let Ok(db) = value.trim().parse::<f64>() else { continue };
+                match key {
+                    PEAK_KEY => peak = peak.max(db),
+                    RMS_KEY => {
+                        power += 10f64.powf(db / 10.0);
+                        frames += 1;
+                    }
+                    _ => continue,
+                }
+                if frames > 0 && last.elapsed() >= REPORT_INTERVAL {
+                    // the window's RMS is the mean power of its frames
+                    let rms = 10.0 * (power / frames as f64).log10();
+                    report(AudioLevel { peak_db: peak.max(FLOOR_DB), rms_db: rms.max(FLOOR_DB) });
+                    (peak, power, frames, last) = (f64::NEG_INFINITY, 0.0, 0, Instant::now());
+                }
+            }
+        });
+    }
+}
@@ -3,268 +3,4383 @@
 
 //! Screen capture ➕ FFmpeg piping with isolated event helper
 //! ---------------------------------------------------------
-//! • Video capture runs in threads with a bounded channel (max 4 frames).
+//! • Video capture runs in threads with a bounded channel of `frame_buffer_size` frames; by default as
+//!   many as fit in 256 MB (`FRAME_BUFFER_BUDGET`), at most 8 (`MAX_DEFAULT_FRAME_BUFFER`).
 //! • Events captured by a separate helper process (`event_capture` example) to avoid macOS CGEventTap aborts.
+//! • This is the only recording path; the old PNG-frame recorder (`recording.rs`) is gone.
 
-use std::io::Write;
-use std::path::PathBuf;
-use std::process::{Child, Command, Stdio};
-use std::sync::atomic::{AtomicBool, Ordering};
+mod append;
+mod clicks;
+mod composite;
+mod concat;
+mod cursor;
+mod devices;
+mod error;
+mod estimate;
+mod event_stream;
+mod events;
+mod fade;
+mod ffmpeg;
+mod follow;
+mod frame_times;
+mod heatmap;
+mod integrity;
+mod keycaps;
+mod levels;
+mod library;
+mod motion;
+mod permissions;
+mod pixels;
+mod power;
+mod preset;
+mod recover;
+mod region_picker;
+mod rename;
+mod replay;
+mod segment;
+mod session_log;
+mod shutdown;
+mod source;
+mod target;
+mod tray;
+mod trim;
+mod window_activity;
+
+pub use devices::CameraInfo;
+pub use error::RecordingError;
+pub use permissions::{PermissionStatus, Permissions};
+pub use preset::RecordingPreset;
+pub use source::FrameSource;
+
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Child, ChildStdin, Command, Stdio};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
 use std::sync::{Arc, Mutex};
-use std::thread;
+use std::thread::{self, JoinHandle};
 use std::time::{Duration, Instant};
 
-use chrono::Local;
-use crossbeam_channel::bounded;
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use chrono::{DateTime, Local};
+use crossbeam_channel::{bounded, RecvTimeoutError, TrySendError};
 use serde::{Deserialize, Serialize};
-use tauri::State;
+use tauri::ipc::Channel;
+use tauri::{AppHandle, Emitter, Manager, RunEvent, Runtime, State, WebviewUrl, WebviewWindowBuilder, WindowEvent};
+use tauri_plugin_global_shortcut::{GlobalShortcutExt, ShortcutState};
+use tauri_plugin_opener::OpenerExt;
+
+use clicks::ClickMapping;
+use recover::RecoveredSession;
+use region_picker::PendingSelection;
+use ffmpeg::{FilterGraph, StderrTail};
+use levels::LevelMeter;
+use library::RecordingEntry;
+use replay::{ReplayBuffer, ReplayEncoder};
+use segment::{EncoderCommand, Segmenter};
+use session_log::SessionLog;
+use source::SourceGuard;
+use scap::{capturer::Capturer, get_all_targets, is_supported, request_permission, Target};
+use screenshots::Screen;
+
+const DEFAULT_FFMPEG: &str = "ffmpeg";
+const MAX_FPS: u32 = 120;
+const PIPELINE_JOIN_TIMEOUT: Duration = Duration::from_secs(30);
+// how long stopping waits for ffmpeg to finish the file unless stop_timeout_secs says otherwise
+const DEFAULT_STOP_TIMEOUT: Duration = Duration::from_secs(30);
+// the helper must write its ready line within this long
+const HELPER_READY_TIMEOUT: Duration = Duration::from_secs(3);
+// no frame reaching the encoder for this long while recording counts as a stall
+const STALL_TIMEOUT: Duration = Duration::from_secs(5);
+const DEFAULT_PRESET: &str = "ultrafast";
+// space reserved when no max duration bounds the recording
+const UNBOUNDED_ESTIMATE_SECS: u64 = 10 * 60;
+// recording auto-stops once free space drops below this
+const MIN_FREE_SPACE: u64 = 200 * 1024 * 1024;
+const SPACE_CHECK_INTERVAL: Duration = Duration::from_secs(5);
+// no frame for this long makes the capture loop check whether its source still exists
+const FRAME_TIMEOUT: Duration = Duration::from_secs(2);
+// in vfr mode an unchanged frame is still sent this often, keeping the
+// encoder (and the stall watchdog) fed through long static stretches
+const VFR_MAX_HOLD: Duration = Duration::from_secs(1);
+const DEFAULT_MOTION_THRESHOLD: f64 = 0.001;
+const DEFAULT_MOTION_MIN_SECS: f64 = 0.5;
+// how often the capture thread refreshes the frame kept for previews
+const PREVIEW_INTERVAL: Duration = Duration::from_millis(250);
+const PREVIEW_WIDTH: u32 = 320;
+// frames timed by fps_auto_adjust before a recording starts
+const FPS_PROBE_FRAMES: usize = 30;
+// frames timed by benchmark_capture unless told otherwise, and the most it times
+const DEFAULT_BENCHMARK_FRAMES: usize = 120;
+const MAX_BENCHMARK_FRAMES: usize = 3600;
+// how often auto_power_saver checks whether the machine is on battery
+const POWER_CHECK_INTERVAL: Duration = Duration::from_secs(10);
+// how often the capture thread notes its frame counts in session.log
+const MILESTONE_INTERVAL: Duration = Duration::from_secs(10);
+// raw frames are large; the replay buffer holds at most this much
+const REPLAY_MEMORY_LIMIT: u64 = 2 * 1024 * 1024 * 1024;
+// the default frame buffer holds as many frames as fit here, up to MAX_DEFAULT_FRAME_BUFFER
+const FRAME_BUFFER_BUDGET: u64 = 256 * 1024 * 1024;
+const MAX_DEFAULT_FRAME_BUFFER: usize = 8;
+const OUTPUT_FORMATS: &[&str] = &["mp4", "webm", "gif", "webp", "apng"];
+// image formats: no audio, no container metadata, and every frame is kept in full
+const ANIMATED_FORMATS: &[&str] = &["gif", "webp", "apng"];
+// 30s at 30 fps; beyond that an animated image is usually far larger than mp4
+const DEFAULT_MAX_ANIMATED_FRAMES: u32 = 900;
+// `color_filter` names and the single-pass filter each maps to
+const COLOR_FILTERS: &[(&str, &str)] = &[
+    ("grayscale", "format=gray"),
+    ("invert", "negate"),
+    ("sepia", "colorchannelmixer=.393:.769:.189:0:.349:.686:.168:0:.272:.534:.131"),
+];
+// flags extra_ffmpeg_args may not set: they add inputs or outputs, replace the
+// filter graph, or change what the recorder parses and pipes
+const RESERVED_FFMPEG_FLAGS: &[&str] = &[
+    "-i", "-f", "-y", "-n", "-map", "-filter_complex", "-lavfi", "-filter", "-vf", "-af",
+    "-loglevel", "-v", "-stats", "-nostats", "-progress", "-stdin", "-nostdin",
+];
+// hardware H.264 encoders `encoder` accepts; vaapi is left out as it needs a device and hwupload
+const HW_H264_ENCODERS: &[&str] = &["h264_videotoolbox", "h264_nvenc", "h264_qsv", "h264_amf"];
+const X264_PRESETS: &[&str] = &[
+    "ultrafast", "superfast", "veryfast", "faster", "fast",
+    "medium", "slow", "slower", "veryslow", "placebo",
+];
+
+// -----------------------------------------------------------------------------
+// Configuration structs
+// -----------------------------------------------------------------------------
+
+fn default_true() -> bool {
+    true
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct RecordingOptions {
+    pub fps: u32,
+    /// Rate frames are grabbed at; overrides `fps` when set.
+    pub capture_fps: Option<u32>,
+    /// Frame rate of the encoded video, at most the capture rate; defaults to it.
+    pub output_fps: Option<u32>,
+    /// Sample ~30 frames before starting and lower the capture rate to what the
+    /// machine sustained (default). When false the requested rate is trusted as
+    /// is and recording starts about a second sooner.
+    #[serde(default = "default_true")]
+    pub fps_auto_adjust: bool,
+    /// Battery-friendly recording: at most 15 fps, the fastest x264 preset
+    /// and, unless `capture_scale` is set, capture at logical resolution.
+    #[serde(default)]
+    pub power_saver: bool,
+    /// Drop to about 15 fps while the machine runs on battery and go back
+    /// when it's plugged in, checked every 10 seconds. Each change is
+    /// emitted as `power-saver-changed`.
+    #[serde(default)]
+    pub auto_power_saver: bool,
+    pub show_cursor: bool,
+    pub show_highlight: bool,
+    pub capture_keystrokes: bool,
+    /// Most mouse moves logged per second when the cursor moves less than
+    /// `mouse_min_distance`; defaults to 30.
+    pub mouse_sampling_hz: Option<f64>,
+    /// Moves at least this many points from the last logged one are always logged; defaults to 8.
+    pub mouse_min_distance: Option<f64>,
+    /// Key (rdev name, e.g. "F8") that drops a chapter marker; markers end up in `chapters.vtt`.
+    pub chapter_key: Option<String>,
+    /// Log key presses as "Redacted" instead of the key, keeping only typing activity.
+    /// `chapter_key` is never redacted.
+    #[serde(default)]
+    pub redact_keys: bool,
+    /// With `redact_keys`, still log modifier, navigation and function keys by name.
+    #[serde(default)]
+    pub redact_keep_modifiers: bool,
+    /// Also log Copy / Cut / Paste events when their Cmd/Ctrl shortcuts are
+    /// pressed. Not available with `redact_keys`.
+    #[serde(default)]
+    pub capture_clipboard: bool,
+    /// Log each switch of the foreground window (app and title) to
+    /// `window_activity.jsonl`, for indexing a recording by what was on screen.
+    #[serde(default)]
+    pub track_window_activity: bool,
+    pub monitor_index: Option<usize>,
+    /// Capture the display with this name from `list_monitors` (e.g. "DELL U2720Q"),
+    /// case-insensitively. Unlike an index, it survives displays being plugged
+    /// in or out.
+    pub monitor_name: Option<String>,
+    /// Capture every display, composited at their desktop positions into one frame.
+    #[serde(default)]
+    pub capture_all_monitors: bool,
+    /// Capture a single window (id from `list_windows`) instead of a display.
+    pub window_id: Option<u32>,
+    /// Path to the ffmpeg binary; defaults to `ffmpeg` on PATH.
+    pub ffmpeg_path: Option<String>,
+    /// x264 constant rate factor (0–51, lower is better quality).
+    pub crf: Option<u8>,
+    /// x264 preset; defaults to `ultrafast`.
+    pub preset: Option<String>,
+    /// Target video bitrate; switches from constant quality to bitrate-capped encoding.
+    pub bitrate_kbps: Option<u32>,
+    /// Maximum distance between keyframes, in frames.
+    pub keyframe_interval: Option<u32>,
+    /// H.264 encoder for mp4 output: "libx264" (default) or a hardware encoder from `list_encoders`.
+    pub encoder: Option<String>,
+    /// "high" / "medium" / "low"; used to pick a CRF when `crf` isn't given.
+    pub quality: Option<String>,
+    /// Encode without any loss, in RGB with no chroma subsampling (mp4 via
+    /// libx264rgb, lossless VP9, WebP or APNG). Files get many times larger.
+    #[serde(default)]
+    pub lossless: bool,
+    /// Arguments appended to the recording's ffmpeg command just before the
+    /// output file, for flags without a first-class option. Inputs, stream
+    /// mapping, filters, the container and logging are off limits; anything
+    /// else is passed through unchecked and can break the recording.
+    pub extra_ffmpeg_args: Option<Vec<String>>,
+    /// Capture region as (x, y, width, height) in frame pixels.
+    pub region: Option<(u32, u32, u32, u32)>,
+    /// Record a (width, height) window of the screen, in frame pixels, that
+    /// follows the mouse, kept inside the screen and eased rather than
+    /// jumping with every move. Full-screen display capture only.
+    pub follow_cursor: Option<(u32, u32)>,
+    /// "native" (default) records every physical pixel of a HiDPI display;
+    /// "logical" scales frames down to the display's size in points. Native
+    /// on a 2x display has four times the pixels, so files typically come out
+    /// 2–4x larger and encoding costs more CPU, in exchange for sharp text.
+    pub capture_scale: Option<String>,
+    /// Encoded size as (width, height) when it should differ from the captured
+    /// one, which stays full resolution. A 0 follows the other dimension's
+    /// aspect ratio; a size of another aspect ratio is letterboxed. Odd values
+    /// are rounded down, as yuv420p needs even dimensions.
+    pub output_resolution: Option<(u32, u32)>,
+    /// Fixed aspect ratio for the output as "W:H", e.g. "16:9", "9:16" for
+    /// shorts, "1:1" or "4:3". The frame is fitted to it per `aspect_fit`.
+    pub aspect_ratio: Option<String>,
+    /// "letterbox" (default) pads the frame out to `aspect_ratio` with black
+    /// bars; "crop" cuts the overhang off both sides instead.
+    pub aspect_fit: Option<String>,
+    /// "mp4" (default), "webm", "gif", "webp" or "apng"; also decides the output file extension.
+    pub output_format: Option<String>,
+    /// WebP quality (0–100); defaults to 75. Ignored with `webp_lossless`.
+    pub webp_quality: Option<u8>,
+    /// Encode WebP losslessly; larger, but exact for UI text.
+    #[serde(default)]
+    pub webp_lossless: bool,
+    /// Longest gif/webp/apng accepted, in frames; defaults to 900.
+    pub max_animated_frames: Option<u32>,
+    /// "video" (default) or "frames" to write numbered images into `frames/` instead of encoding.
+    pub output_mode: Option<String>,
+    /// Image format in frames mode: "png" (default) or "jpeg".
+    pub frame_format: Option<String>,
+    /// JPEG quality (1–100) in frames mode; defaults to 90.
+    pub jpeg_quality: Option<u8>,
+    /// Variable frame rate: frames that match the previous one are not sent,
+    /// so static stretches cost (almost) nothing; ffmpeg timestamps each frame
+    /// as it arrives instead of assuming `fps`. That's when ffmpeg reads the
+    /// frame, not when it was captured, so a backed-up frame buffer or a
+    /// busy encoder shows up as timing jitter in the video.
+    #[serde(default)]
+    pub vfr: bool,
+    /// With `vfr`, the fraction of pixels (0–1) that may change while a frame
+    /// still counts as unchanged; defaults to 0, any change is kept.
+    pub vfr_threshold: Option<f64>,
+    /// Record only while the screen changes; still stretches are cut from the
+    /// output rather than frozen. Event timestamps stay on the wall clock, and
+    /// the events aren't indexed by video frame.
+    #[serde(default)]
+    pub motion_only: bool,
+    /// With `motion_only`, the fraction of pixels (0–1) that must change to
+    /// count as activity; defaults to 0.001.
+    pub motion_threshold: Option<f64>,
+    /// With `motion_only`, how long a change must last before recording
+    /// resumes, so brief flickers are ignored; defaults to 0.5s.
+    pub motion_min_secs: Option<f64>,
+    /// Frames queued between capture and the encoder. A larger buffer absorbs
+    /// dips in encoding speed without dropping frames, at the cost of one raw
+    /// frame (width × height × 4 bytes) of memory each. Defaults to what fits
+    /// in 256 MB, at most 8.
+    pub frame_buffer_size: Option<usize>,
+    /// Seconds to count down (emitting `countdown-tick`) before capture starts.
+    pub countdown_secs: Option<u32>,
+    /// Split the recording into `output_001.mp4`, `output_002.mp4`, … so no
+    /// file grows past this many MB. Capture continues across the split. Not
+    /// available with a webcam or audio, whose devices can't be reopened
+    /// while the previous segment still holds them.
+    pub max_file_size_mb: Option<u64>,
+    /// Stop automatically (emitting `recording-auto-stopped`) after this many seconds.
+    pub max_duration_secs: Option<u64>,
+    /// How long stopping waits for ffmpeg to finish writing the file before
+    /// killing it and reporting the forced stop. Defaults to 30 seconds;
+    /// raise it for slow filters or encoders on long recordings.
+    pub stop_timeout_secs: Option<u64>,
+    /// Instant-replay mode: keep only the last N seconds in memory instead of
+    /// encoding continuously; `save_replay` writes them out, as does stopping.
+    pub ring_buffer_secs: Option<u32>,
+    /// Title embedded in the container metadata.
+    pub title: Option<String>,
+    /// Write a SHA-256 of every raw frame sent to the encoder into
+    /// `frame_hashes.jsonl`. `verify_integrity` can check a lossless recording
+    /// against them later. Costs noticeable CPU at high resolutions.
+    #[serde(default)]
+    pub frame_hashes: bool,
+    /// Write `frame_timestamps.csv`: for each frame sent to the encoder, its
+    /// index and when it was sent, in ms from the start; repeated frames
+    /// included. The video itself spaces frames evenly; this keeps the real
+    /// timing for syncing with other recorders.
+    #[serde(default)]
+    pub frame_timestamps: bool,
+    /// Recolor the video: "grayscale", "invert" or "sepia".
+    pub color_filter: Option<String>,
+    /// Burn the elapsed time (HH:MM:SS.mmm) into each frame.
+    #[serde(default)]
+    pub burn_timecode: bool,
+    /// Corner for the burned-in timecode; defaults to "top-left".
+    pub timecode_corner: Option<String>,
+    /// Playback speed-up applied while encoding (4.0 = a 4x timelapse).
+    pub speed_factor: Option<f32>,
+    /// Fade in from black over the first N seconds, in a pass after stopping.
+    pub fade_in_secs: Option<f32>,
+    /// Fade out to black over the last N seconds, in a pass after stopping.
+    /// Both fades shrink in proportion when the recording is shorter than them.
+    pub fade_out_secs: Option<f32>,
+    /// Directory the timestamped session folder is created in; defaults to `recordings` in the Videos folder.
+    pub output_dir: Option<String>,
+    /// Send the encoded video here instead of the session folder: an
+    /// absolute file or named pipe path, or an rtmp://, rtmps://, srt://,
+    /// udp:// or tcp:// URL to stream to. Nothing is post-processed, so
+    /// fades, click highlighting and the synthetic cursor are unavailable.
+    pub output_target: Option<String>,
+    /// A finished session folder to record more onto instead of starting a new
+    /// one. The take is recorded into a folder inside it and joined onto its
+    /// video at stop, re-encoded if the resolution or codec no longer match.
+    pub append_to_session: Option<String>,
+    /// Picture-in-picture webcam overlay.
+    pub webcam: Option<WebcamOptions>,
+    /// Draw an expanding ring at each mouse click in a final encode pass.
+    #[serde(default)]
+    pub highlight_clicks: bool,
+    /// Draw the pointer from logged mouse positions in a final encode pass,
+    /// for capture backends that leave it out of the frames. The backend's
+    /// own cursor is turned off so there's never two; `show_cursor` doesn't
+    /// matter with this on.
+    #[serde(default)]
+    pub synthetic_cursor: bool,
+    /// Caption each key pressed, with its modifiers ("⌘C"), in the
+    /// bottom-right corner for about 1.5 seconds, in a final encode pass.
+    /// Needs the event log, so it's off when input monitoring isn't allowed.
+    #[serde(default)]
+    pub show_keycaps: bool,
+    /// Record audio from the microphone, system output, or both mixed into one track.
+    pub audio_source: Option<AudioSource>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+pub enum AudioSource {
+    Microphone,
+    System,
+    Both,
+}
+
+impl RecordingOptions {
+    /// Frame rate of the output; `fps` is the capture rate once `start_recording` has run.
+    fn output_fps(&self) -> u32 {
+        self.output_fps.map_or(self.fps, |fps| fps.min(self.fps))
+    }
+
+    fn ffmpeg_bin(&self) -> &str {
+        self.ffmpeg_path.as_deref().unwrap_or(DEFAULT_FFMPEG)
+    }
+
+    /// Resolve the effective CRF, validating explicit values and mapping `quality`.
+    fn crf(&self) -> Result<Option<u8>, RecordingError> {
+        if let Some(crf) = self.crf {
+            if crf > 51 {
+                return Err(RecordingError::InvalidOptions(format!(
+                    "Invalid crf {}: must be between 0 and 51",
+                    crf
+                )));
+            }
+            return Ok(Some(crf));
+        }
+        match self.quality.as_deref() {
+            None => Ok(None),
+            Some("high") => Ok(Some(18)),
+            Some("medium") => Ok(Some(23)),
+            Some("low") => Ok(Some(28)),
+            Some(q) => Err(RecordingError::InvalidOptions(format!(
+                "Unknown quality '{}': expected high, medium or low",
+                q
+            ))),
+        }
+    }
+
+    fn preset(&self) -> Result<&str, RecordingError> {
+        let preset = self.preset.as_deref().unwrap_or(DEFAULT_PRESET);
+        if !X264_PRESETS.contains(&preset) {
+            return Err(RecordingError::InvalidOptions(format!(
+                "Unknown preset '{}': expected one of {}",
+                preset,
+                X264_PRESETS.join(", ")
+            )));
+        }
+        Ok(preset)
+    }
+
+    fn output_format(&self) -> Result<&str, RecordingError> {
+        let format = self.output_format.as_deref().unwrap_or("mp4");
+        if !OUTPUT_FORMATS.contains(&format) {
+            return Err(RecordingError::InvalidOptions(format!(
+                "Unsupported output format '{}': expected one of {}",
+                format,
+                OUTPUT_FORMATS.join(", ")
+            )));
+        }
+        Ok(format)
+    }
+
+    /// Frame cap for animated image output, or `None` for video formats.
+    /// Fails up front when `max_duration_secs` (or the replay window) would already exceed it.
+    fn animated_frame_limit(&self) -> Result<Option<u64>, RecordingError> {
+        let format = self.output_format()?;
+        if !ANIMATED_FORMATS.contains(&format) {
+            return Ok(None);
+        }
+        let limit = self.max_animated_frames.unwrap_or(DEFAULT_MAX_ANIMATED_FRAMES) as u64;
+        if limit == 0 {
+            return Err(RecordingError::InvalidOptions("max_animated_frames must be positive".into()));
+        }
+        if let Some(secs) = self.ring_buffer_secs.map(u64::from).or(self.max_duration_secs) {
+            let frames = secs * self.output_fps() as u64;
+            if frames > limit {
+                return Err(RecordingError::InvalidOptions(format!(
+                    "{}s at {} fps is {} frames, more than the {} allowed for {} output; record mp4 instead",
+                    secs, self.output_fps(), frames, limit, format
+                )));
+            }
+        }
+        Ok(Some(limit))
+    }
+
+    /// Image format for frames mode, or `None` when recording video.
+    fn frame_image_format(&self) -> Result<Option<FrameImageFormat>, RecordingError> {
+        match self.output_mode.as_deref().unwrap_or("video") {
+            "video" => return Ok(None),
+            "frames" => {}
+            other => {
+                return Err(RecordingError::InvalidOptions(format!(
+                    "Unknown output mode '{}': expected video or frames",
+                    other
+                )));
+            }
+        }
+        if self.webcam.is_some()
+            || self.audio_source.is_some()
+            || self.ring_buffer_secs.is_some()
+            || self.output_fps.is_some()
+            || self.color_filter.is_some()
+        {
+            return Err(RecordingError::InvalidOptions(
+                "frames output can't be combined with webcam, audio, ring buffer capture, output_fps or color_filter"
+                    .into(),
+            ));
+        }
+        match self.frame_format.as_deref().unwrap_or("png") {
+            "png" => Ok(Some(FrameImageFormat::Png)),
+            "jpeg" | "jpg" => match self.jpeg_quality.unwrap_or(90) {
+                q @ 1..=100 => Ok(Some(FrameImageFormat::Jpeg(q))),
+                q => Err(RecordingError::InvalidOptions(format!(
+                    "Invalid JPEG quality {}: must be between 1 and 100",
+                    q
+                ))),
+            },
+            other => Err(RecordingError::InvalidOptions(format!(
+                "Unknown frame format '{}': expected png or jpeg",
+                other
+            ))),
+        }
+    }
+
+    /// Whether `capture_scale` asks for logical (point-sized) output.
+    fn logical_capture(&self) -> Result<bool, RecordingError> {
+        match self.capture_scale.as_deref().unwrap_or("native") {
+            "native" => Ok(false),
+            "logical" if self.output_mode.as_deref() == Some("frames") => Err(RecordingError::InvalidOptions(
+                "capture_scale logical needs video output; frames are saved at capture size".into(),
+            )),
+            "logical" => Ok(true),
+            other => Err(RecordingError::InvalidOptions(format!(
+                "Unknown capture scale '{}': expected native or logical",
+                other
+            ))),
+        }
+    }
+
+    /// `output_resolution` rounded down to even dimensions, 0 still standing
+    /// for "follow the aspect ratio".
+    fn output_resolution(&self) -> Result<Option<(u32, u32)>, RecordingError> {
+        let Some((w, h)) = self.output_resolution else { return Ok(None) };
+        if self.output_mode.as_deref() == Some("frames") {
+            return Err(RecordingError::InvalidOptions(
+                "output_resolution needs video output; frames are saved at capture size".into(),
+            ));
+        }
+        match (w & !1, h & !1) {
+            (0, 0) => Err(RecordingError::InvalidOptions(format!(
+                "Invalid output_resolution {}x{}: at least one dimension must be 2 or more",
+                w, h
+            ))),
+            size => Ok(Some(size)),
+        }
+    }
+
+    /// `aspect_ratio` as its width and height terms, and whether `aspect_fit`
+    /// crops rather than letterboxes.
+    fn aspect(&self) -> Result<Option<(u32, u32, bool)>, RecordingError> {
+        let Some(ratio) = self.aspect_ratio.as_deref() else { return Ok(None) };
+        if self.output_mode.as_deref() == Some("frames") {
+            return Err(RecordingError::InvalidOptions(
+                "aspect_ratio needs video output; frames are saved at capture size".into(),
+            ));
+        }
+        let (num, den) = ratio
+            .split_once(':')
+            .and_then(|(w, h)| Some((w.trim().parse::<u32>().ok()?, h.trim().parse::<u32>().ok()?)))
+            .filter(|&(w, h)| w > 0 && h > 0)
+            .ok_or_else(|| {
+                RecordingError::InvalidOptions(format!("Invalid aspect_ratio '{}': expected W:H, e.g. 16:9", ratio))
+            })?;
+        let crop = match self.aspect_fit.as_deref().unwrap_or("letterbox") {
+            "letterbox" => false,
+            "crop" => true,
+            other => {
+                return Err(RecordingError::InvalidOptions(format!(
+                    "Unknown aspect fit '{}': expected letterbox or crop",
+                    other
+                )));
+            }
+        };
+        Ok(Some((num, den, crop)))
+    }
+
+    /// Mouse decimation, key redaction and clipboard flags for the event helper.
+    fn helper_args(&self) -> Result<Vec<String>, RecordingError> {
+        let mut args = Vec::new();
+        // a logged Copy or Paste gives away the C or V that redaction hid
+        if self.capture_clipboard && self.redact_keys {
+            return Err(RecordingError::InvalidOptions("capture_clipboard can't be combined with redact_keys".into()));
+        }
+        if self.capture_clipboard {
+            args.push("--clipboard".to_string());
+        }
+        if self.redact_keys {
+            args.push("--redact-keys".to_string());
+            if self.redact_keep_modifiers {
+                args.push("--keep-modifiers".to_string());
+            }
+            if let Some(key) = &self.chapter_key {
+                args.extend(["--keep-key".to_string(), key.clone()]);
+            }
+        }
+        if let Some(hz) = self.mouse_sampling_hz {
+            if !(hz > 0.0 && hz.is_finite()) {
+                return Err(RecordingError::InvalidOptions(format!("Invalid mouse_sampling_hz {}: must be positive", hz)));
+            }
+            args.extend(["--mouse-hz".to_string(), hz.to_string()]);
+        }
+        if let Some(px) = self.mouse_min_distance {
+            if !(px >= 0.0 && px.is_finite()) {
+                return Err(RecordingError::InvalidOptions(format!("Invalid mouse_min_distance {}: must not be negative", px)));
+            }
+            args.extend(["--mouse-min-distance".to_string(), px.to_string()]);
+        }
+        Ok(args)
+    }
+
+    /// Validated similarity threshold when `vfr` is on, `None` for constant frame rate.
+    fn vfr_threshold(&self) -> Result<Option<f64>, RecordingError> {
+        if !self.vfr {
+            return Ok(None);
+        }
+        if self.ring_buffer_secs.is_some() || self.output_mode.as_deref() == Some("frames") || self.output_fps() < self.fps {
+            return Err(RecordingError::InvalidOptions(
+                "vfr can't be combined with ring buffer capture, frames output or output_fps".into(),
+            ));
+        }
+        match self.vfr_threshold.unwrap_or(0.0) {
+            t if (0.0..=1.0).contains(&t) => Ok(Some(t)),
+            t => Err(RecordingError::InvalidOptions(format!(
+                "Invalid vfr_threshold {}: must be between 0 and 1",
+                t
+            ))),
+        }
+    }
+
+    /// Whether the encoded frames can match the hashed raw ones bit for bit:
+    /// lossless, and nothing between capture and encoder that changes pixels
+    /// or frame count.
+    fn hashes_verifiable(&self) -> bool {
+        self.lossless
+            && self.webcam.is_none()
+            && !self.burn_timecode
+            && self.color_filter.is_none()
+            && self.speed_factor.is_none_or(|f| f == 1.0)
+            && self.output_fps() == self.fps
+            && !self.highlight_clicks
+            && !self.synthetic_cursor
+            && !self.show_keycaps
+            && self.max_file_size_mb.is_none()
+            && self.output_mode.as_deref() != Some("frames")
+            && self.extra_ffmpeg_args.as_ref().is_none_or(|a| a.is_empty())
+            && self.fades().is_ok_and(|fades| fades == (0.0, 0.0))
+            && !self.logical_capture().unwrap_or(false)
+            && self.output_resolution.is_none()
+            && self.aspect_ratio.is_none()
+            && self.follow_cursor.is_none()
+            && self.output_target.is_none()
+    }
+
+    /// The frame gate for `motion_only`, `None` when every frame is recorded.
+    fn motion_gate(&self) -> Result<Option<motion::MotionGate>, RecordingError> {
+        if !self.motion_only {
+            return Ok(None);
+        }
+        // each of these places wall-clock events on the video's timeline,
+        // which cutting the still stretches throws off
+        if self.vfr
+            || self.highlight_clicks
+            || self.synthetic_cursor
+            || self.show_keycaps
+            || self.chapter_key.is_some()
+            || self.track_window_activity
+        {
+            return Err(RecordingError::InvalidOptions(
+                "motion_only can't be combined with vfr, highlight_clicks, synthetic_cursor, show_keycaps, \
+                 chapter_key or track_window_activity"
+                    .into(),
+            ));
+        }
+        let threshold = self.motion_threshold.unwrap_or(DEFAULT_MOTION_THRESHOLD);
+        if !(0.0..=1.0).contains(&threshold) {
+            return Err(RecordingError::InvalidOptions(format!(
+                "Invalid motion_threshold {}: must be between 0 and 1",
+                threshold
+            )));
+        }
+        let min_secs = self.motion_min_secs.unwrap_or(DEFAULT_MOTION_MIN_SECS);
+        if !(min_secs >= 0.0 && min_secs.is_finite()) {
+            return Err(RecordingError::InvalidOptions(format!(
+                "Invalid motion_min_secs {}: must not be negative",
+                min_secs
+            )));
+        }
+        Ok(Some(motion::MotionGate::new(threshold, Duration::from_secs_f64(min_secs))))
+    }
+
+    /// Per-segment byte cap from `max_file_size_mb`, `None` for a single output file.
+    fn segment_limit(&self) -> Result<Option<u64>, RecordingError> {
+        let Some(mb) = self.max_file_size_mb else { return Ok(None) };
+        if mb == 0 {
+            return Err(RecordingError::InvalidOptions("max_file_size_mb must be positive".into()));
+        }
+        // each segment's ffmpeg opens the webcam and audio devices afresh, and
+        // the previous one still holds them while it finishes its file
+        if self.ring_buffer_secs.is_some()
+            || self.output_mode.as_deref() == Some("frames")
+            || self.highlight_clicks
+            || self.synthetic_cursor
+            || self.show_keycaps
+            || self.webcam.is_some()
+            || self.audio_source.is_some()
+        {
+            return Err(RecordingError::InvalidOptions(
+                "max_file_size_mb can't be combined with ring buffer capture, frames output, highlight_clicks, \
+                 synthetic_cursor, show_keycaps, webcam or audio_source"
+                    .into(),
+            ));
+        }
+        Ok(Some(mb.saturating_mul(1024 * 1024)))
+    }
+
+    /// The `follow_cursor` window size rounded down to even for the encoder,
+    /// `None` for a fixed frame.
+    fn follow_cursor(&self) -> Result<Option<(u32, u32)>, RecordingError> {
+        let Some((w, h)) = self.follow_cursor else { return Ok(None) };
+        if w < 2 || h < 2 {
+            return Err(RecordingError::InvalidOptions(format!(
+                "Invalid follow_cursor size {}x{}: must be at least 2x2",
+                w, h
+            )));
+        }
+        if self.region.is_some() || self.window_id.is_some() || self.highlight_clicks || self.synthetic_cursor {
+            return Err(RecordingError::InvalidOptions(
+                "follow_cursor can't be combined with region, window_id, highlight_clicks or synthetic_cursor".into(),
+            ));
+        }
+        Ok(Some((w & !1, h & !1)))
+    }
+
+    /// Validated `output_target`, `None` for the session folder's output file.
+    fn output_target(&self) -> Result<Option<target::OutputTarget>, RecordingError> {
+        let Some(location) = &self.output_target else { return Ok(None) };
+        if self.output_mode.as_deref() == Some("frames")
+            || self.ring_buffer_secs.is_some()
+            || self.max_file_size_mb.is_some()
+            || self.append_to_session.is_some()
+            || self.highlight_clicks
+            || self.synthetic_cursor
+            || self.show_keycaps
+            || self.fades()? != (0.0, 0.0)
+        {
+            return Err(RecordingError::InvalidOptions(
+                "output_target can't be combined with frames output, ring buffer capture, max_file_size_mb, \
+                 append_to_session, fades, highlight_clicks, synthetic_cursor or show_keycaps"
+                    .into(),
+            ));
+        }
+        target::OutputTarget::parse(location, self.output_format()?).map(Some)
+    }
+
+    /// Fold `capture_fps` and `power_saver` into the rates and encoder
+    /// settings a recording actually uses.
+    fn apply_rate_overrides(&mut self) {
+        if let Some(fps) = self.capture_fps {
+            self.fps = fps;
+        }
+        if self.power_saver {
+            self.fps = self.fps.min(power::POWER_SAVER_FPS);
+            self.output_fps = self.output_fps.map(|fps| fps.min(self.fps));
+            // the fastest x264 preset, whatever slower one was asked for
+            self.preset = Some(DEFAULT_PRESET.into());
+            if self.capture_scale.is_none() && self.output_mode.as_deref() != Some("frames") {
+                self.capture_scale = Some("logical".into());
+            }
+        }
+    }
+
+    /// How long `stop_recording` waits for ffmpeg to exit.
+    fn stop_timeout(&self) -> Result<Duration, RecordingError> {
+        match self.stop_timeout_secs {
+            Some(0) => Err(RecordingError::InvalidOptions("stop_timeout_secs must be positive".into())),
+            Some(secs) => Ok(Duration::from_secs(secs)),
+            None => Ok(DEFAULT_STOP_TIMEOUT),
+        }
+    }
+
+    /// Capacity of the capture → encoder channel for frames of `frame_bytes`.
+    fn frame_buffer_size(&self, frame_bytes: u64) -> Result<usize, RecordingError> {
+        match self.frame_buffer_size {
+            Some(0) => Err(RecordingError::InvalidOptions("frame_buffer_size must be at least 1".into())),
+            Some(size) => Ok(size),
+            None => Ok((FRAME_BUFFER_BUDGET / frame_bytes.max(1)).clamp(1, MAX_DEFAULT_FRAME_BUFFER as u64) as usize),
+        }
+    }
+
+    /// Validated `speed_factor`; `None` means real time.
+    fn speed_factor(&self) -> Result<Option<f64>, RecordingError> {
+        match self.speed_factor {
+            Some(f) if !(0.25..=100.0).contains(&f) => Err(RecordingError::InvalidOptions(format!(
+                "Invalid speed factor {}: must be between 0.25 and 100",
+                f
+            ))),
+            Some(f) if f != 1.0 => Ok(Some(f as f64)),
+            _ => Ok(None),
+        }
+    }
+
+    /// Validated `(fade_in_secs, fade_out_secs)`, zero where unset.
+    fn fades(&self) -> Result<(f64, f64), RecordingError> {
+        let mut fades = [0.0; 2];
+        let requested = [("fade_in_secs", self.fade_in_secs), ("fade_out_secs", self.fade_out_secs)];
+        for (fade, (name, secs)) in fades.iter_mut().zip(requested) {
+            let Some(secs) = secs else { continue };
+            if !secs.is_finite() || secs < 0.0 {
+                return Err(RecordingError::InvalidOptions(format!(
+                    "Invalid {} {}: must be zero or more",
+                    name, secs
+                )));
+            }
+            *fade = secs as f64;
+        }
+        if fades != [0.0; 2] && (self.output_mode.as_deref() == Some("frames") || self.max_file_size_mb.is_some()) {
+            return Err(RecordingError::InvalidOptions(
+                "fades can't be combined with frames output or max_file_size_mb".into(),
+            ));
+        }
+        Ok((fades[0], fades[1]))
+    }
+
+    /// `-metadata` arguments describing the recording (not supported for animated images).
+    fn metadata_args(&self, w: u32, h: u32) -> Vec<String> {
+        if ANIMATED_FORMATS.contains(&self.output_format.as_deref().unwrap_or_default()) {
+            return Vec::new();
+        }
+        let mut tags = vec![
+            ("creation_time", chrono::Utc::now().to_rfc3339()),
+            ("comment", format!("fps={} resolution={}x{}", self.output_fps(), w, h)),
+        ];
+        if let Some(title) = &self.title {
+            tags.push(("title", ffmpeg::sanitize_metadata(title)));
+        }
+        tags.into_iter()
+            .flat_map(|(k, v)| ["-metadata".to_string(), format!("{k}={v}")])
+            .collect()
+    }
+
+    /// `extra_ffmpeg_args`, refused when they'd fight the command the recorder builds.
+    fn extra_ffmpeg_args(&self) -> Result<Vec<String>, RecordingError> {
+        let Some(args) = self.extra_ffmpeg_args.clone().filter(|a| !a.is_empty()) else {
+            return Ok(Vec::new());
+        };
+        if self.output_mode.as_deref() == Some("frames") {
+            return Err(RecordingError::InvalidOptions("extra_ffmpeg_args needs video output".into()));
+        }
+        // a leading bare word would be taken for a second output file
+        if !args[0].starts_with('-') {
+            return Err(RecordingError::InvalidOptions(format!(
+                "extra_ffmpeg_args must start with a flag, not '{}'",
+                args[0]
+            )));
+        }
+        // compared without a stream specifier, so -filter:v counts as -filter
+        let reserved = args
+            .iter()
+            .find(|a| RESERVED_FFMPEG_FLAGS.contains(&a.split(':').next().unwrap_or_default()));
+        if let Some(flag) = reserved {
+            return Err(RecordingError::InvalidOptions(format!(
+                "extra_ffmpeg_args can't contain {}; the recorder sets it itself",
+                flag
+            )));
+        }
+        Ok(args)
+    }
+
+    /// Encoder arguments placed between the input and the output file.
+    fn video_codec_args(&self) -> Result<Vec<String>, RecordingError> {
+        // bitrate and CRF are alternative rate-control modes
+        let crf = match self.bitrate_kbps {
+            Some(_) if self.crf.is_some() => {
+                return Err(RecordingError::InvalidOptions(
+                    "crf and bitrate_kbps are mutually exclusive".into(),
+                ));
+            }
+            Some(0) => return Err(RecordingError::InvalidOptions("bitrate_kbps must be positive".into())),
+            Some(_) => None,
+            None => self.crf()?,
+        };
+        if self.keyframe_interval == Some(0) {
+            return Err(RecordingError::InvalidOptions("keyframe_interval must be positive".into()));
+        }
+        let mut args: Vec<String> = Vec::new();
+        let format = self.output_format()?;
+        match format {
+            _ if self.lossless => args.extend(self.lossless_codec_args(format)?),
+            "webm" => {
+                args.extend(["-c:v", "libvpx-vp9", "-deadline", "realtime", "-cpu-used", "8"].map(String::from));
+                // VP9 constant-quality mode requires an unconstrained bitrate
+                if let Some(crf) = crf {
+                    args.extend(["-crf".into(), crf.to_string(), "-b:v".into(), "0".into()]);
+                }
+                args.extend(["-pix_fmt".into(), "yuv420p".into()]);
+            }
+            // gif needs no encoder flags; its palette is built in the filter graph
+            "gif" => {}
+            "webp" => {
+                args.extend(["-c:v".into(), "libwebp_anim".into()]);
+                if self.webp_lossless {
+                    args.extend(["-lossless".into(), "1".into()]);
+                } else {
+                    match self.webp_quality.unwrap_or(75) {
+                        q @ 0..=100 => args.extend(["-quality".into(), q.to_string()]),
+                        q => {
+                            return Err(RecordingError::InvalidOptions(format!(
+                                "Invalid webp_quality {}: must be between 0 and 100",
+                                q
+                            )));
+                        }
+                    }
+                }
+                args.extend(["-loop".into(), "0".into()]);
+            }
+            "apng" => args.extend(["-c:v", "apng", "-plays", "0"].map(String::from)),
+            _ if self.encoder.as_deref().is_some_and(|e| e != "libx264") => {
+                let encoder = self.encoder.as_deref().unwrap_or_default();
+                if !HW_H264_ENCODERS.contains(&encoder) {
+                    return Err(RecordingError::InvalidOptions(format!(
+                        "Unknown encoder '{}': expected libx264 or one of {}",
+                        encoder,
+                        HW_H264_ENCODERS.join(", ")
+                    )));
+                }
+                args.extend(["-c:v".into(), encoder.into()]);
+                // x264 presets don't apply; each encoder has its own quality knob
+                if let Some(crf) = crf {
+                    match encoder {
+                        // videotoolbox quality runs 1–100, higher is better
+                        "h264_videotoolbox" => {
+                            let q = (51 - crf.min(51)) as u32 * 99 / 51 + 1;
+                            args.extend(["-q:v".into(), q.to_string()]);
+                        }
+                        "h264_nvenc" => {
+                            args.extend(["-preset", "p1", "-rc", "vbr", "-b:v", "0", "-cq"].map(String::from));
+                            args.push(crf.to_string());
+                        }
+                        "h264_qsv" => args.extend(["-global_quality".into(), crf.to_string()]),
+                        _ => {
+                            args.extend(["-rc".into(), "cqp".into()]);
+                            args.extend(["-qp_i".into(), crf.to_string(), "-qp_p".into(), crf.to_string()]);
+                        }
+                    }
+                }
+                let pix_fmt = if encoder == "h264_qsv" { "nv12" } else { "yuv420p" };
+                args.extend(["-pix_fmt".into(), pix_fmt.into()]);
+            }
+            _ => {
+                args.extend(["-c:v".into(), "libx264".into(), "-preset".into(), self.preset()?.into()]);
+                if let Some(crf) = crf {
+                    args.extend(["-crf".into(), crf.to_string()]);
+                }
+                args.extend(["-pix_fmt".into(), "yuv420p".into()]);
+            }
+        }
+        if !ANIMATED_FORMATS.contains(&format) {
+            if let Some(kbps) = self.bitrate_kbps {
+                // cap the rate with a two-second VBV buffer for predictable sizes
+                args.extend([
+                    "-b:v".into(), format!("{}k", kbps),
+                    "-maxrate".into(), format!("{}k", kbps),
+                    "-bufsize".into(), format!("{}k", kbps.saturating_mul(2)),
+                ]);
+            }
+            if let Some(gop) = self.keyframe_interval {
+                args.extend(["-g".into(), gop.to_string()]);
+            }
+        }
+        // keep the arrival timestamps instead of resampling to a constant rate
+        if self.vfr {
+            args.extend(["-fps_mode".into(), "vfr".into()]);
+        }
+        Ok(args)
+    }
+
+    /// Encoder arguments for `lossless`. Every pixel format here holds the
+    /// captured BGRA exactly (minus the unused alpha).
+    fn lossless_codec_args(&self, format: &str) -> Result<Vec<String>, RecordingError> {
+        if self.crf.is_some()
+            || self.quality.is_some()
+            || self.bitrate_kbps.is_some()
+            || self.encoder.as_deref().is_some_and(|e| e != "libx264")
+        {
+            return Err(RecordingError::InvalidOptions(
+                "lossless can't be combined with crf, quality, bitrate_kbps or a hardware encoder".into(),
+            ));
+        }
+        let args = match format {
+            "mp4" => vec!["-c:v", "libx264rgb", "-qp", "0", "-preset", self.preset()?, "-pix_fmt", "bgr0"],
+            "webm" => vec!["-c:v", "libvpx-vp9", "-lossless", "1", "-deadline", "realtime", "-cpu-used", "8", "-pix_fmt", "gbrp"],
+            "webp" => vec!["-c:v", "libwebp_anim", "-lossless", "1", "-loop", "0"],
+            "apng" => vec!["-c:v", "apng", "-plays", "0"],
+            _ => {
+                return Err(RecordingError::InvalidOptions(format!(
+                    "{} output can't be lossless; use mp4, webm, webp or apng",
+                    format
+                )));
+            }
+        };
+        Ok(args.into_iter().map(String::from).collect())
+    }
+
+    /// Audio encoder arguments; empty when no audio is recorded.
+    fn audio_codec_args(&self) -> Result<Vec<String>, RecordingError> {
+        if self.audio_source.is_none() {
+            return Ok(Vec::new());
+        }
+        let args: &[&str] = match self.output_format()? {
+            "webm" => &["-c:a", "libopus", "-b:a", "128k"],
+            format @ ("gif" | "webp" | "apng") => {
+                return Err(RecordingError::InvalidOptions(format!("{} output cannot carry audio", format)));
+            }
+            _ => &["-c:a", "aac", "-b:a", "160k"],
+        };
+        // live audio inputs never end, so stop with the piped video
+        Ok(args.iter().copied().chain(["-shortest"]).map(String::from).collect())
+    }
+
+    /// The ffmpeg filter for `color_filter`, if one is set.
+    fn color_filter(&self) -> Result<Option<&'static str>, RecordingError> {
+        let Some(name) = self.color_filter.as_deref() else { return Ok(None) };
+        COLOR_FILTERS
+            .iter()
+            .find(|(n, _)| *n == name)
+            .map(|(_, filter)| Some(*filter))
+            .ok_or_else(|| {
+                let names: Vec<&str> = COLOR_FILTERS.iter().map(|(n, _)| *n).collect();
+                RecordingError::InvalidOptions(format!(
+                    "Unknown color filter '{}': expected one of {}",
+                    name,
+                    names.join(", ")
+                ))
+            })
+    }
+
+    /// `drawtext` filter burning in the elapsed time, rendered with `font`.
+    fn timecode_filter(&self, font: &str) -> Result<String, RecordingError> {
+        let (right, bottom) = corner(self.timecode_corner.as_deref(), "top-left", "timecode")?;
+        Ok(format!(
+            "drawtext=fontfile='{}':text='%{{pts\\:hms}}':fontsize=24:fontcolor=white:\
+             box=1:boxcolor=black@0.6:boxborderw=6:x={}:y={}",
+            ffmpeg::escape_filter_path(font),
+            if right { "w-tw-16" } else { "16" },
+            if bottom { "h-th-16" } else { "16" },
+        ))
+    }
+
+    /// Filter graph over the screen input; input 1 is the webcam when `webcam` is set,
+    /// `audio_inputs` are the input indices of the audio devices. `geometry` are the
+    /// crop/pad/scale steps from capture size to output size, `w` the output width.
+    fn filter_graph(
+        &self,
+        w: u32,
+        geometry: &[String],
+        webcam: bool,
+        audio_inputs: &[usize],
+        timecode: Option<&str>,
+    ) -> Result<FilterGraph, RecordingError> {
+        let mut graph = FilterGraph::new();
+        // drop frames first so nothing downstream processes them
+        if self.output_fps() < self.fps {
+            graph.chain("", &format!("fps={}", self.output_fps()));
+        }
+        // before any overlay, so overlays are sized against the output
+        for step in geometry {
+            graph.chain("", step);
+        }
+        if let (true, Some(cam)) = (webcam, &self.webcam) {
+            let cam_w = (w * cam.size_percent() / 100).max(2) & !1;
+            graph.side(format!("[1:v]scale={}:-2[cam]", cam_w));
+            // shortest=1: the camera never ends on its own, the piped screen stream does
+            graph.chain("[cam]", &format!("overlay={}:shortest=1", cam.overlay_position()?));
+        }
+        // after the overlay so the webcam matches, before the timecode so it stays legible
+        if let Some(filter) = self.color_filter()? {
+            graph.chain("", filter);
+        }
+        // before setpts, so a timelapse still shows wall-clock time
+        if let Some(drawtext) = timecode {
+            graph.chain("", drawtext);
+        }
+        let speed = self.speed_factor()?;
+        if let Some(factor) = speed {
+            graph.chain("", &format!("setpts=PTS/{}", factor));
+        }
+        if self.output_format()? == "gif" {
+            // palettegen consumes the whole stream before paletteuse runs,
+            // giving a two-pass palette in a single ffmpeg invocation
+            graph.chain("", "split[a][b];[a]palettegen[p];[b][p]paletteuse");
+        }
+        if audio_inputs.is_empty() {
+            return Ok(graph);
+        }
+        let mut audio_filters = Vec::new();
+        if audio_inputs.len() > 1 {
+            audio_filters.push(format!("amix=inputs={}:duration=longest", audio_inputs.len()));
+        }
+        // metered before atempo, so the levels follow what was said in real time
+        audio_filters.push(levels::FILTERS.to_string());
+        if let Some(factor) = speed {
+            audio_filters.extend(atempo_chain(factor));
+        }
+        let labels: String = audio_inputs.iter().map(|i| format!("[{}:a]", i)).collect();
+        graph.side(format!("{}{}[aout]", labels, audio_filters.join(",")));
+        graph.map_audio("[aout]".into());
+        Ok(graph)
+    }
+}
+
+/// Parse a corner name into (right, bottom), used by the webcam and timecode placement.
+fn corner(value: Option<&str>, default: &str, what: &str) -> Result<(bool, bool), RecordingError> {
+    match value.unwrap_or(default) {
+        "top-left" => Ok((false, false)),
+        "top-right" => Ok((true, false)),
+        "bottom-left" => Ok((false, true)),
+        "bottom-right" => Ok((true, true)),
+        other => Err(RecordingError::InvalidOptions(format!(
+            "Unknown {} corner '{}': expected top-left, top-right, bottom-left or bottom-right",
+            what, other
+        ))),
+    }
+}
+
+/// `atempo` only accepts 0.5–2.0 per instance, so larger changes are chained.
+fn atempo_chain(mut factor: f64) -> Vec<String> {
+    let mut filters = Vec::new();
+    while factor > 2.0 {
+        filters.push("atempo=2.0".to_string());
+        factor /= 2.0;
+    }
+    while factor < 0.5 {
+        filters.push("atempo=0.5".to_string());
+        factor /= 0.5;
+    }
+    filters.push(format!("atempo={}", factor));
+    filters
+}
+
+#[derive(Debug, Clone, Copy)]
+enum FrameImageFormat {
+    Png,
+    Jpeg(u8), // quality
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct WebcamOptions {
+    /// Index from `list_cameras`.
+    pub device: usize,
+    /// "top-left", "top-right", "bottom-left" or "bottom-right" (default).
+    pub corner: Option<String>,
+    /// Overlay width as a percentage of the recording width (default 20).
+    pub size_percent: Option<u32>,
+}
+
+impl WebcamOptions {
+    fn size_percent(&self) -> u32 {
+        self.size_percent.unwrap_or(20)
+    }
+
+    /// Validate the options and return the `overlay` x:y expression.
+    fn overlay_position(&self) -> Result<String, RecordingError> {
+        if !(5..=50).contains(&self.size_percent()) {
+            return Err(RecordingError::InvalidOptions(format!(
+                "Invalid webcam size {}%: must be between 5 and 50",
+                self.size_percent()
+            )));
+        }
+        let (right, bottom) = corner(self.corner.as_deref(), "bottom-right", "webcam")?;
+        Ok(format!(
+            "{}:{}",
+            if right { "W-w-16" } else { "16" },
+            if bottom { "H-h-16" } else { "16" }
+        ))
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RecordingState {
+    pub is_recording: bool,
+    pub duration: u64,
+    /// Seconds since capture started, by the clock.
+    pub wall_duration: f64,
+    /// Seconds of video encoded so far: frames delivered at the capture rate,
+    /// after any speed-up. Falls behind `wall_duration` when frames are dropped.
+    pub media_duration: f64,
+    pub error: Option<String>,
+    pub dropped_frames: u64,
+}
+
+/// Returned by `stop_recording` once the output is finalized.
+#[derive(Debug, Serialize, Clone)]
+pub struct RecordingResult {
+    pub output_path: String,
+    pub manifest_path: String,
+    pub dropped_frames: u64,
+    pub stats: RecordingStats,
+    pub thumbnail_path: Option<String>,
+    /// Every segment file in order when `max_file_size_mb` split the recording;
+    /// `output_path` is the first. Empty for a single file.
+    pub segments: Vec<String>,
+}
+
+/// Returned by `start_recording`: where the session is being written.
+#[derive(Debug, Serialize, Clone)]
+pub struct RecordingSession {
+    pub session_dir: String,
+    /// Planned output; it only becomes playable once the recording stops.
+    pub output_path: String,
+    /// Event log, when keystroke or click capture is on.
+    pub events_file: Option<String>,
+}
+
+/// Post-recording summary of how well capture kept up.
+#[derive(Debug, Serialize, Clone)]
+pub struct RecordingStats {
+    pub duration_secs: f64,
+    pub frames_captured: u64,
+    /// Frames actually delivered per second of wall-clock time.
+    pub average_fps: f64,
+    pub dropped_frames: u64,
+    /// Frames dropped because the encoder was behind and the channel was full.
+    pub channel_full: u64,
+    pub file_size: u64,
+}
+
+/// Contents of the `manifest.json` written into each finished session directory.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RecordingManifest {
+    pub output_path: String,
+    pub width: u32,
+    pub height: u32,
+    pub fps: u32,
+    pub duration_secs: f64,
+    pub keystrokes_captured: bool,
+    pub mouse_captured: bool,
+    pub events_file: Option<String>,
+    pub events_started_at: Option<String>, // when the helper began logging
+    pub events_indexed_file: Option<String>,
+    pub chapters_file: Option<String>, // WebVTT chapters from `chapter_key` presses
+    /// Foreground window changes from `track_window_activity`.
+    #[serde(default)]
+    pub window_activity_file: Option<String>,
+    /// How event-log screen coordinates map onto the video, when known.
+    pub screen_mapping: Option<ClickMapping>,
+    pub thumbnail_file: Option<String>,
+    /// Recording start as UNIX epoch seconds, the base event timestamps share.
+    #[serde(default)]
+    pub started_epoch: f64,
+    pub started_at: String, // RFC 3339, local time
+    pub ended_at: String,
+    pub platform: String,
+    /// Finalized by `recover_sessions` after the app stopped mid-recording.
+    #[serde(default)]
+    pub recovered: bool,
+    /// Segment files when `max_file_size_mb` split the recording.
+    #[serde(default)]
+    pub segments: Vec<String>,
+    /// Raw-frame hashes from `frame_hashes`.
+    #[serde(default)]
+    pub frame_hashes_file: Option<String>,
+    /// Whether `verify_integrity` can check the output against those hashes.
+    #[serde(default)]
+    pub hashes_verifiable: bool,
+    /// When each frame was sent to the encoder, from `frame_timestamps`.
+    #[serde(default)]
+    pub frame_timestamps_file: Option<String>,
+    /// Take folders joined onto the output by `append_to_session`, oldest first.
+    #[serde(default)]
+    pub appended: Vec<String>,
+}
+
+/// Returned by `get_last_recording`.
+#[derive(Debug, Serialize)]
+pub struct LastRecording {
+    pub output_path: String,
+    pub manifest: Option<RecordingManifest>,
+}
+
+/// Payload of the `recording-progress` event, emitted once per second while recording.
+#[derive(Debug, Serialize, Clone)]
+pub struct RecordingProgress {
+    pub elapsed_secs: f64,
+    pub frames_captured: u64,
+    pub file_size: u64,
+}
+
+/// Payload of the `audio-level` event, emitted about ten times a second while
+/// recording audio. Levels are in dBFS, -100 for silence.
+#[derive(Debug, Serialize, Clone)]
+pub struct AudioLevel {
+    pub peak_db: f64,
+    pub rms_db: f64,
+}
+
+/// Payload of the `recording-auto-stopped` event.
+#[derive(Debug, Serialize, Clone)]
+pub struct AutoStopped {
+    pub reason: String,
+    pub result: Option<RecordingResult>,
+    pub error: Option<String>,
+}
+
+/// Payload of the `recording-error` event, emitted the moment a background
+/// thread fails. The recording then stops on its own, keeping what was
+/// encoded so far.
+#[derive(Debug, Serialize, Clone)]
+pub struct RecordingFailed {
+    /// "capture" or "writer".
+    pub stage: String,
+    pub reason: String,
+}
+
+/// Payload of the `power-saver-changed` event, emitted when `auto_power_saver`
+/// changes the capture rate mid-recording.
+#[derive(Debug, Serialize, Clone)]
+pub struct PowerSaverChanged {
+    pub on_battery: bool,
+    /// Frames now captured per second; the video keeps its frame rate.
+    pub capture_fps: f64,
+}
+
+/// Payload of the `recording-stalled` event, emitted when frames stop reaching the encoder.
+#[derive(Debug, Serialize, Clone)]
+pub struct RecordingStalled {
+    pub stalled_secs: u64,
+    pub frames_written: u64,
+    /// ffmpeg's most recent stderr output, if it printed anything.
+    pub ffmpeg_output: Option<String>,
+}
+
+/// Payload of the `recording-scheduled` event and return value of `schedule_recording`.
+#[derive(Debug, Serialize, Clone)]
+pub struct ScheduledRecording {
+    pub id: u64,
+    pub start_at_epoch: f64,
+}
+
+/// Payload of the `recording-schedule-fired` event.
+#[derive(Debug, Serialize, Clone)]
+pub struct ScheduleFired {
+    pub id: u64,
+    pub session: Option<RecordingSession>,
+    pub error: Option<RecordingError>,
+}
+
+/// Payload of the `keystroke-capture-changed` event.
+#[derive(Debug, Serialize, Clone)]
+pub struct KeystrokeCaptureChanged {
+    pub enabled: bool,
+}
+
+/// Payload of the `preset-expanded` event: the options a preset start used.
+#[derive(Debug, Serialize, Clone)]
+pub struct PresetExpanded {
+    pub preset: RecordingPreset,
+    pub options: RecordingOptions,
+}
 
-use scap::{capturer::Capturer, frame::Frame, is_supported, request_permission};
+/// Payload of the `recording-toggled` event, emitted when the hotkey or the
+/// tray starts or stops a recording.
+#[derive(Debug, Serialize, Clone)]
+pub struct RecordingToggled {
+    pub is_recording: bool,
+    pub result: Option<RecordingResult>,
+    pub error: Option<RecordingError>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct FfmpegStatus {
+    pub found: bool,
+    pub version: Option<String>,
+    pub error: Option<String>,
+}
+
+/// One step of `self_test`.
+#[derive(Debug, Serialize)]
+pub struct SelfTestCheck {
+    pub name: String,
+    pub passed: bool,
+    pub detail: Option<String>,
+}
+
+/// Returned by `self_test`; `passed` is true only if every check passed.
+#[derive(Debug, Serialize)]
+pub struct SelfTestReport {
+    pub passed: bool,
+    pub checks: Vec<SelfTestCheck>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct WindowInfo {
+    pub id: u32,
+    pub title: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct MonitorInfo {
+    pub index: usize,
+    /// The name the OS gives the display, usually its model; `None` when unknown.
+    pub name: Option<String>,
+    pub id: u32,
+    pub width: u32,
+    pub height: u32,
+    pub x: i32,
+    pub y: i32,
+    pub scale_factor: f32,
+    /// Refresh rate in Hz; 0 when the platform doesn't report it.
+    pub refresh_rate: f32,
+    pub is_primary: bool,
+}
+
+/// Returned by `get_capture_info`: what scap will capture with on this machine.
+#[derive(Debug, Serialize)]
+pub struct CaptureInfo {
+    /// The platform API scap captures through.
+    pub backend: String,
+    pub supported: bool,
+    pub screen_recording: PermissionStatus,
+    /// Frame layouts the backend can deliver; all are converted to BGRA.
+    pub frame_types: Vec<String>,
+    /// The layout recordings ask the backend for.
+    pub requested_frame_type: String,
+    pub displays: Vec<MonitorInfo>,
+}
+
+/// Returned by `estimate_size_per_minute`.
+#[derive(Debug, Serialize, Clone)]
+pub struct SizeEstimate {
+    /// Approximate bytes written per minute of recording.
+    pub bytes_per_minute: u64,
+    /// The output size and frame rate the estimate is for.
+    pub width: u32,
+    pub height: u32,
+    pub fps: u32,
+}
+
+/// Returned by `benchmark_capture`. Times are per frame, in milliseconds.
+#[derive(Debug, Serialize)]
+pub struct CaptureBenchmark {
+    pub width: u32,
+    pub height: u32,
+    /// The rate the capturer was asked for; delivery can't exceed it.
+    pub requested_fps: u32,
+    /// Sustained rate over the whole sample.
+    pub fps: f64,
+    pub frames: usize,
+    pub min_ms: f64,
+    pub avg_ms: f64,
+    pub max_ms: f64,
+    pub p95_ms: f64,
+}
+
+// -----------------------------------------------------------------------------
+// Shared application state
+// -----------------------------------------------------------------------------
+
+/// (width, height, BGRA data) of the frame kept for `get_preview_frame`.
+type PreviewFrame = (u32, u32, Vec<u8>);
+
+/// Facts about the running session that end up in its manifest.
+struct SessionInfo {
+    opts: RecordingOptions,
+    width: u32,
+    height: u32,
+    fps: u32,
+    started: DateTime<Local>,
+    events_file: Option<PathBuf>,
+    helper_ready: Option<DateTime<Local>>,
+    clicks: Option<ClickMapping>, // set when click highlighting is on
+    cursor: Option<ClickMapping>, // set when the synthetic cursor is on
+    screen_mapping: Option<ClickMapping>,
+    frame_hashes: Option<PathBuf>,
+    frame_timestamps: Option<PathBuf>,
+    window_activity: Option<PathBuf>,
+}
+
+/// Where the writer thread puts captured frames.
+enum FrameSink {
+    Ffmpeg { stdin: ChildStdin, last: Vec<u8> },
+    Segmented { segmenter: Segmenter, last: Vec<u8> },
+    Replay(Arc<Mutex<Option<ReplayBuffer>>>),
+    Images { dir: PathBuf, format: FrameImageFormat, width: u32, height: u32, next: u64 },
+}
+
+impl FrameSink {
+    /// Hand one BGRA frame to the sink; `false` means it can't take any more.
+    /// An empty frame stands for the previous one again; `auto_power_saver`
+    /// fills the slots it doesn't capture with those.
+    fn write(&mut self, buf: Vec<u8>) -> bool {
+        match self {
+            FrameSink::Ffmpeg { stdin, last } => {
+                if !buf.is_empty() {
+                    *last = buf;
+                }
+                stdin.write_all(last).is_ok()
+            }
+            FrameSink::Segmented { segmenter, last } => {
+                if !buf.is_empty() {
+                    *last = buf;
+                }
+                segmenter.write(last)
+            }
+            FrameSink::Replay(replay) => match replay.lock().unwrap().as_mut() {
+                Some(replay) => {
+                    if buf.is_empty() {
+                        replay.repeat();
+                    } else {
+                        replay.push(buf);
+                    }
+                    true
+                }
+                None => false,
+            },
+            FrameSink::Images { dir, format, width, height, next } => {
+                let ext = match format {
+                    FrameImageFormat::Png => "png",
+                    FrameImageFormat::Jpeg(_) => "jpg",
+                };
+                // numbered from 0 like frame_hashes and frame_timestamps rows
+                let path = dir.join(format!("frame_{:06}.{}", next, ext));
+                *next += 1;
+                match save_frame_image(&path, buf, *width, *height, *format) {
+                    Ok(()) => true,
+                    Err(e) => {
+                        eprintln!("Failed to write {}: {}", path.display(), e);
+                        false
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Everything the commands share. Managed by the app in `run`; embedders
+/// driving `start_recording_with_source` manage a default one themselves.
+#[derive(Default)]
+pub struct AppState {
+    is_recording: Arc<AtomicBool>,
+    counting_down: Arc<AtomicBool>,
+    started_at:   Arc<Mutex<Option<Instant>>>,
+    dropped_frames: Arc<AtomicU64>,
+    frames_captured: Arc<AtomicU64>,
+    channel_full: Arc<AtomicU64>, // subset of dropped_frames caused by backpressure
+    output_dir:   Arc<Mutex<Option<PathBuf>>>,
+    output_file:  Arc<Mutex<Option<PathBuf>>>,
+    session:      Arc<Mutex<Option<SessionInfo>>>,
+    ffmpeg:       Arc<Mutex<Option<Child>>>,
+    ffmpeg_stderr: Arc<Mutex<Option<StderrTail>>>,
+    helper:       Arc<Mutex<Option<Child>>>, // helper process for event capture
+    capture_thread: Arc<Mutex<Option<JoinHandle<()>>>>,
+    writer_thread:  Arc<Mutex<Option<JoinHandle<()>>>>, // drains the channel into ffmpeg stdin
+    error:        Arc<Mutex<Option<String>>>, // last warning/error surfaced via get_recording_state
+    latest_frame: Arc<Mutex<Option<PreviewFrame>>>,
+    replay:       Arc<Mutex<Option<ReplayBuffer>>>, // set in instant-replay mode instead of `ffmpeg`
+    hotkey:       Arc<Mutex<Option<String>>>, // currently registered toggle combo
+    last_options: Arc<Mutex<Option<RecordingOptions>>>, // as passed to the last successful start
+    restarting:   Arc<AtomicBool>, // restart_recording is between its cancel and start
+    scheduled:    Arc<Mutex<Option<ScheduledRecording>>>, // pending schedule_recording request
+    segments:     Arc<Mutex<Vec<PathBuf>>>, // files written so far when max_file_size_mb splits the output
+    session_log:  Arc<Mutex<Option<SessionLog>>>, // session.log of the running recording
+    region_picker: Arc<Mutex<Option<PendingSelection>>>, // set while the region picker overlay is open
+    event_subscribers: event_stream::Subscribers, // subscribe_events channels, kept across recordings
+    window_tracker: Arc<Mutex<Option<JoinHandle<()>>>>, // polls the foreground window for track_window_activity
+    shutting_down: Arc<AtomicBool>, // an app exit is held until the recording is finalized
+    finishing:    Arc<AtomicBool>, // finish_recording is saving a session that has no manifest yet
+    battery_probe: Option<power::BatteryProbe>, // replaces power::on_battery for auto_power_saver
+}
+
+impl AppState {
+    /// Have `auto_power_saver` ask `probe` whether the machine is on battery
+    /// instead of reading the battery, e.g. to test a switch mid-recording.
+    pub fn with_battery_probe(mut self, probe: impl Fn() -> Option<bool> + Send + Sync + 'static) -> Self {
+        self.battery_probe = Some(Arc::new(probe));
+        self
+    }
+
+    fn battery_probe(&self) -> power::BatteryProbe {
+        self.battery_probe.clone().unwrap_or_else(|| Arc::new(power::on_battery))
+    }
+}
+
+// -----------------------------------------------------------------------------
+// Session helpers
+// -----------------------------------------------------------------------------
+
+/// Where session directories are created: `output_dir` if given, else a
+/// `recordings` folder in the user's Videos folder, then Documents, then home.
+/// An existing `~/recordings` from before that order is kept, so its
+/// sessions stay listed and recoverable. Fails rather than guessing (the working directory of a bundled app may be
+/// the read-only bundle itself) when none of them exists.
+fn recordings_root<R: Runtime>(app: &AppHandle<R>, output_dir: Option<&str>) -> Result<PathBuf, RecordingError> {
+    if let Some(dir) = output_dir {
+        return Ok(PathBuf::from(dir));
+    }
+    let paths = app.path();
+    let home: Vec<PathBuf> =
+        ["HOME", "USERPROFILE"].iter().filter_map(std::env::var_os).map(PathBuf::from).collect();
+    let legacy = home.iter().map(|dir| dir.join("recordings")).find(|dir| dir.is_absolute() && dir.is_dir());
+    if let Some(legacy) = legacy {
+        return Ok(legacy);
+    }
+    [paths.video_dir().ok(), paths.document_dir().ok()]
+        .into_iter()
+        .flatten()
+        .chain(home)
+        .find(|dir| dir.is_absolute() && dir.is_dir())
+        .map(|dir| dir.join("recordings"))
+        .ok_or_else(|| {
+            RecordingError::Io("No folder for recordings could be found; choose one with output_dir".into())
+        })
+}
+
+/// Create `root` if needed and make sure files can be written into it.
+fn ensure_writable(root: &Path) -> Result<(), RecordingError> {
+    let unwritable = |e: std::io::Error| {
+        RecordingError::Io(match e.kind() {
+            std::io::ErrorKind::PermissionDenied => {
+                format!("Permission denied: cannot write to {}", root.display())
+            }
+            _ => format!("Output directory {} is not usable: {}", root.display(), e),
+        })
+    };
+    std::fs::create_dir_all(root).map_err(unwritable)?;
+    let probe = root.join(".write_test");
+    std::fs::write(&probe, b"").map_err(unwritable)?;
+    let _ = std::fs::remove_file(probe);
+    Ok(())
+}
+
+/// Create a fresh timestamped session directory under the recordings root.
+fn create_session_dir<R: Runtime>(app: &AppHandle<R>, output_dir: Option<&str>) -> Result<PathBuf, RecordingError> {
+    let root = recordings_root(app, output_dir)?;
+    ensure_writable(&root)?;
+    let session = root.join(Local::now().format("%Y%m%d_%H%M%S").to_string());
+    std::fs::create_dir_all(&session)?;
+    Ok(session)
+}
+
+/// The session's finished output file (its first segment, if it was split),
+/// if it exists and isn't empty.
+fn session_output(dir: &Path) -> Option<PathBuf> {
+    // a renamed recording can only be found through its manifest
+    let named = std::fs::read_to_string(dir.join("manifest.json"))
+        .ok()
+        .and_then(|json| serde_json::from_str::<RecordingManifest>(&json).ok())
+        .map(|m| PathBuf::from(m.output_path));
+    let default_names = OUTPUT_FORMATS
+        .iter()
+        .flat_map(|ext| [dir.join(format!("output.{}", ext)), segment::segment_path(dir, ext, 1)]);
+    named
+        .into_iter()
+        .chain(default_names)
+        .find(|p| std::fs::metadata(p).is_ok_and(|m| m.is_file() && m.len() > 0))
+}
+
+/// Fail if the volume holding `dir` can't fit `required` bytes plus the safety margin.
+fn ensure_free_space(dir: &Path, required: u64) -> Result<(), RecordingError> {
+    let available = fs2::available_space(dir)?;
+    let required = required + MIN_FREE_SPACE;
+    if available < required {
+        return Err(RecordingError::Io(format!(
+            "Insufficient disk space: need about {} bytes, only {} bytes available",
+            required, available
+        )));
+    }
+    Ok(())
+}
+
+/// Write `manifest.json` next to the output and return its path.
+fn write_manifest(dir: &Path, manifest: &RecordingManifest) -> Result<PathBuf, RecordingError> {
+    let path = dir.join("manifest.json");
+    let json = serde_json::to_string_pretty(manifest)
+        .map_err(|e| RecordingError::Io(format!("Failed to serialize manifest: {}", e)))?;
+    std::fs::write(&path, json)?;
+    Ok(path)
+}
+
+// -----------------------------------------------------------------------------
+// Monitor helpers
+// -----------------------------------------------------------------------------
+
+/// Resolve a monitor index (as reported by `list_monitors`) to a scap display target.
+fn display_target(index: usize) -> Result<Target, RecordingError> {
+    let screens = Screen::all().map_err(|e| RecordingError::Capture(e.to_string()))?;
+    if screens.is_empty() {
+        return Err(RecordingError::Capture("No monitors detected".into()));
+    }
+    let screen = screens.get(index).ok_or_else(|| {
+        RecordingError::InvalidOptions(format!(
+            "Monitor index {} out of range (valid: 0..={})",
+            index,
+            screens.len() - 1
+        ))
+    })?;
+
+    let mut displays: Vec<Target> = get_all_targets()
+        .into_iter()
+        .filter(|t| matches!(t, Target::Display(_)))
+        .collect();
+
+    // match by display id, falling back to enumeration order
+    let pos = displays
+        .iter()
+        .position(|t| matches!(t, Target::Display(d) if d.id == screen.display_info.id))
+        .unwrap_or(index);
+    if pos >= displays.len() {
+        return Err(RecordingError::Capture(format!("Monitor {} is not available for capture", index)));
+    }
+    Ok(displays.swap_remove(pos))
+}
+
+/// Find the scap target for a window id as reported by `list_windows`.
+fn window_target(id: u32) -> Result<Target, RecordingError> {
+    get_all_targets()
+        .into_iter()
+        .find(|t| matches!(t, Target::Window(w) if w.id == id))
+        .ok_or_else(|| RecordingError::InvalidOptions(format!("No open window with id {}", id)))
+}
+
+fn window_exists(id: u32) -> bool {
+    get_all_targets()
+        .iter()
+        .any(|t| matches!(t, Target::Window(w) if w.id == id))
+}
+
+/// How event-log coordinates map onto the video, or `None` when the capture
+/// origin is unknown (window capture).
+fn click_mapping(opts: &RecordingOptions, region: Option<(u32, u32, u32, u32)>) -> Option<ClickMapping> {
+    if opts.window_id.is_some() {
+        return None;
+    }
+    let screens = Screen::all().ok()?;
+    let (crop_x, crop_y) = region.map(|(x, y, _, _)| (x as f64, y as f64)).unwrap_or((0.0, 0.0));
+    if opts.capture_all_monitors {
+        let (origins, scale) = desktop_layout(&screens);
+        return Some(ClickMapping {
+            origin: (
+                origins.iter().map(|o| o.0).min()? as f64,
+                origins.iter().map(|o| o.1).min()? as f64,
+            ),
+            scale,
+            crop: (crop_x, crop_y),
+        });
+    }
+    let screen = match opts.monitor_index {
+        Some(index) => screens.get(index)?,
+        None => screens.iter().find(|s| s.display_info.is_primary).or(screens.first())?,
+    };
+    let info = &screen.display_info;
+    Some(ClickMapping {
+        origin: (info.x as f64, info.y as f64),
+        scale: info.scale_factor as f64,
+        crop: (crop_x, crop_y),
+    })
+}
+
+/// Backing scale factor (physical pixels per point) of the display `opts`
+/// captures, 1.0 when it can't be detected. Window capture uses the chosen
+/// or primary display.
+fn display_scale_factor(opts: &RecordingOptions) -> f64 {
+    let Ok(screens) = Screen::all() else { return 1.0 };
+    if opts.capture_all_monitors {
+        return desktop_layout(&screens).1;
+    }
+    let screen = match opts.monitor_index {
+        Some(index) => screens.get(index),
+        None => screens.iter().find(|s| s.display_info.is_primary).or(screens.first()),
+    };
+    screen.map_or(1.0, |s| s.display_info.scale_factor as f64).max(1.0)
+}
+
+/// The frame size capturing with `opts` should deliver, from the display
+/// layout. A window can't be measured before capture starts, so it counts
+/// as the whole display it's on.
+fn expected_frame_size(opts: &RecordingOptions) -> Result<(u32, u32), RecordingError> {
+    let screens = Screen::all().map_err(|e| RecordingError::Capture(e.to_string()))?;
+    // display sizes are in points
+    let physical = |s: &Screen| {
+        let d = s.display_info;
+        ((d.width as f32 * d.scale_factor).round() as u32, (d.height as f32 * d.scale_factor).round() as u32)
+    };
+    if opts.capture_all_monitors {
+        let sizes: Vec<(u32, u32)> = screens.iter().map(physical).collect();
+        let (origins, scale) = desktop_layout(&screens);
+        let layout = composite::layout(&origins, scale, &sizes);
+        return Ok((layout.width, layout.height));
+    }
+    let screen = match opts.monitor_index {
+        Some(index) => screens.get(index).ok_or_else(|| {
+            RecordingError::InvalidOptions(format!("Monitor index {} out of range", index))
+        })?,
+        None => screens
+            .iter()
+            .find(|s| s.display_info.is_primary)
+            .or(screens.first())
+            .ok_or_else(|| RecordingError::Capture("No monitors detected".into()))?,
+    };
+    Ok(physical(screen))
+}
+
+/// Desktop origins of `screens` and the scale used to place them on a composite canvas
+/// (the largest scale factor, so HiDPI displays keep their resolution).
+fn desktop_layout(screens: &[Screen]) -> (Vec<(i32, i32)>, f64) {
+    let origins = screens.iter().map(|s| (s.display_info.x, s.display_info.y)).collect();
+    let scale = screens
+        .iter()
+        .map(|s| s.display_info.scale_factor as f64)
+        .fold(1.0, f64::max);
+    (origins, scale)
+}
+
+/// The scap targets to record: one per display with `capture_all_monitors`,
+/// otherwise the single selection from `capture_target`.
+fn capture_targets(opts: &RecordingOptions) -> Result<Vec<Option<Target>>, RecordingError> {
+    if !opts.capture_all_monitors {
+        return Ok(vec![capture_target(opts)?]);
+    }
+    if opts.monitor_index.is_some() || opts.window_id.is_some() {
+        return Err(RecordingError::InvalidOptions(
+            "capture_all_monitors can't be combined with monitor_index or window_id".into(),
+        ));
+    }
+    let count = Screen::all().map_err(|e| RecordingError::Capture(e.to_string()))?.len();
+    (0..count).map(|index| display_target(index).map(Some)).collect()
+}
+
+/// The OS's name for each of `screens`. `screenshots` has no names, so each is
+/// matched to Tauri's monitor at the same origin, which some platforms report
+/// in physical pixels and others in points.
+fn monitor_names<R: Runtime>(app: &AppHandle<R>, screens: &[Screen]) -> Vec<Option<String>> {
+    let monitors = app.available_monitors().unwrap_or_default();
+    screens
+        .iter()
+        .map(|s| {
+            let d = &s.display_info;
+            monitors
+                .iter()
+                .find(|m| {
+                    let (pos, scale) = (m.position(), m.scale_factor());
+                    let logical = |v: i32| (v as f64 / scale).round() as i32;
+                    (pos.x, pos.y) == (d.x, d.y) || (logical(pos.x), logical(pos.y)) == (d.x, d.y)
+                })
+                .and_then(|m| m.name().cloned())
+        })
+        .collect()
+}
+
+/// Turn `monitor_name` into the matching `monitor_index`, listing the
+/// available names when none matches.
+fn resolve_monitor_name<R: Runtime>(app: &AppHandle<R>, opts: &mut RecordingOptions) -> Result<(), RecordingError> {
+    let Some(name) = opts.monitor_name.as_deref() else { return Ok(()) };
+    if opts.monitor_index.is_some() {
+        return Err(RecordingError::InvalidOptions(
+            "monitor_name and monitor_index are mutually exclusive".into(),
+        ));
+    }
+    let screens = Screen::all().map_err(|e| RecordingError::Capture(e.to_string()))?;
+    let names = monitor_names(app, &screens);
+    let index = names
+        .iter()
+        .position(|n| n.as_deref().is_some_and(|n| n.eq_ignore_ascii_case(name.trim())))
+        .ok_or_else(|| {
+            let available: Vec<&str> = names.iter().flatten().map(String::as_str).collect();
+            RecordingError::InvalidOptions(format!(
+                "No monitor named '{}' (available: {})",
+                name,
+                if available.is_empty() { "none reported".to_string() } else { available.join(", ") }
+            ))
+        })?;
+    opts.monitor_index = Some(index);
+    Ok(())
+}
+
+/// Resolve the options' monitor/window selection to a scap target (`None` = primary display).
+fn capture_target(opts: &RecordingOptions) -> Result<Option<Target>, RecordingError> {
+    match (opts.monitor_index, opts.window_id) {
+        (Some(_), Some(_)) => Err(RecordingError::InvalidOptions(
+            "monitor_index and window_id are mutually exclusive".into(),
+        )),
+        (_, Some(id)) => window_target(id).map(Some),
+        (Some(index), None) => display_target(index).map(Some),
+        (None, None) => Ok(None),
+    }
+}
+
+// -----------------------------------------------------------------------------
+// Frame helpers
+// -----------------------------------------------------------------------------
+
+/// Save a BGRA frame as a PNG or JPEG image.
+fn save_frame_image(path: &Path, mut bgra: Vec<u8>, w: u32, h: u32, format: FrameImageFormat) -> Result<(), RecordingError> {
+    match format {
+        FrameImageFormat::Png => {
+            // BGRA -> RGBA
+            for px in bgra.chunks_exact_mut(4) {
+                px.swap(0, 2);
+            }
+            let img = image::RgbaImage::from_raw(w, h, bgra)
+                .ok_or_else(|| RecordingError::Capture("frame buffer does not match its reported size".into()))?;
+            img.save(path)?;
+        }
+        FrameImageFormat::Jpeg(quality) => {
+            let rgb: Vec<u8> = bgra.chunks_exact(4).flat_map(|px| [px[2], px[1], px[0]]).collect();
+            let img = image::RgbImage::from_raw(w, h, rgb)
+                .ok_or_else(|| RecordingError::Capture("frame buffer does not match its reported size".into()))?;
+            let file = std::io::BufWriter::new(std::fs::File::create(path)?);
+            image::codecs::jpeg::JpegEncoder::new_with_quality(file, quality).encode_image(&img)?;
+        }
+    }
+    Ok(())
+}
+
+/// Size of a recording output: the file itself, or the sum of the images in a frames directory.
+fn output_size(path: &Path) -> u64 {
+    if path.is_dir() {
+        std::fs::read_dir(path)
+            .map(|dir| dir.filter_map(|e| e.ok()?.metadata().ok()).map(|m| m.len()).sum())
+            .unwrap_or(0)
+    } else {
+        std::fs::metadata(path).map(|m| m.len()).unwrap_or(0)
+    }
+}
+
+/// How long stopping the running session waits for ffmpeg to exit.
+fn session_stop_timeout(state: &AppState) -> Duration {
+    state
+        .session
+        .lock()
+        .unwrap()
+        .as_ref()
+        .and_then(|s| s.opts.stop_timeout().ok())
+        .unwrap_or(DEFAULT_STOP_TIMEOUT)
+}
+
+/// Bytes recorded so far: the output, or every segment when it was split.
+fn recorded_size(out: &Path, segments: &[PathBuf]) -> u64 {
+    if segments.is_empty() {
+        output_size(out)
+    } else {
+        segments.iter().map(|p| output_size(p)).sum()
+    }
+}
+
+/// How captured (and cropped) frames become output frames.
+struct OutputGeometry {
+    filters: Vec<String>, // crop/pad/scale steps for ffmpeg
+    aspect_size: (u32, u32), // after `aspect_ratio`, before scaling
+    size: (u32, u32),
+}
+
+/// The geometry taking `w`x`h` captured frames to the output.
+fn output_geometry(
+    opts: &RecordingOptions,
+    (w, h): (u32, u32),
+    display_scale: f64,
+) -> Result<OutputGeometry, RecordingError> {
+    // aspect_ratio first, cropping or padding at full resolution
+    let mut geometry = Vec::new();
+    let aspect = opts.aspect()?;
+    let (aw, ah) = aspect.map_or((w, h), |(num, den, crop)| fit_aspect(w, h, (num, den), crop));
+    if (aw, ah) != (w, h) {
+        geometry.push(match aspect {
+            Some((_, _, true)) => format!("crop={}:{}", aw, ah),
+            _ => format!("pad={}:{}:(ow-iw)/2:(oh-ih)/2", aw, ah),
+        });
+    }
+    // then an explicit output_resolution, else "logical" bringing HiDPI
+    // frames down to the display's size in points
+    let even = |d: f64| (d.round() as u32).max(2) & !1;
+    let scale_to = match opts.output_resolution()? {
+        Some((0, th)) => Some((even(aw as f64 * th as f64 / ah as f64), th)),
+        Some((tw, 0)) => Some((tw, even(ah as f64 * tw as f64 / aw as f64))),
+        Some(size) => Some(size),
+        None if opts.logical_capture()? && display_scale > 1.0 => {
+            Some((even(aw as f64 / display_scale), even(ah as f64 / display_scale)))
+        }
+        None => None,
+    }
+    .filter(|&size| size != (aw, ah));
+    // a different aspect ratio is fitted inside and letterboxed
+    if let Some((sw, sh)) = scale_to {
+        geometry.push(format!(
+            "scale={sw}:{sh}:force_original_aspect_ratio=decrease:flags=area,pad={sw}:{sh}:(ow-iw)/2:(oh-ih)/2"
+        ));
+    }
+    Ok(OutputGeometry { filters: geometry, aspect_size: (aw, ah), size: scale_to.unwrap_or((aw, ah)) })
+}
+
+/// The largest `terms`-shaped size inside `w`x`h` when cropping, else the
+/// smallest one around it, with even dimensions either way.
+fn fit_aspect(w: u32, h: u32, (num, den): (u32, u32), crop: bool) -> (u32, u32) {
+    let (w64, h64) = (w as u64, h as u64);
+    // wider than the target when w/h > num/den
+    let wider = w64 * den as u64 > h64 * num as u64;
+    let (fw, fh) = match (wider, crop) {
+        (true, true) | (false, false) => (h64 * num as u64 / den as u64, h64),
+        (true, false) | (false, true) => (w64, w64 * den as u64 / num as u64),
+    };
+    let even = |d: u64| match crop {
+        true => (d as u32).max(2) & !1,
+        false => (d as u32).next_multiple_of(2),
+    };
+    (even(fw), even(fh))
+}
+
+/// Clamp a requested region to the frame bounds, rounding the size down to even
+/// numbers as yuv420p requires. Returns `None` if nothing of the region remains.
+fn clamp_region(region: (u32, u32, u32, u32), frame_w: u32, frame_h: u32) -> Option<(u32, u32, u32, u32)> {
+    let (x, y, w, h) = region;
+    if x >= frame_w || y >= frame_h {
+        return None;
+    }
+    let w = w.min(frame_w - x) & !1;
+    let h = h.min(frame_h - y) & !1;
+    if w == 0 || h == 0 {
+        return None;
+    }
+    Some((x, y, w, h))
+}
+
+/// Copy the rows of `region` out of a tightly packed BGRA buffer.
+/// Returns `None` if the buffer is too small for the region (e.g. the display changed size).
+fn crop_bgra(data: &[u8], frame_w: u32, region: (u32, u32, u32, u32)) -> Option<Vec<u8>> {
+    let (x, y, w, h) = region;
+    let stride = frame_w as usize * 4;
+    let row_len = w as usize * 4;
+    let mut out = Vec::with_capacity(row_len * h as usize);
+    for row in y as usize..(y + h) as usize {
+        let start = row * stride + x as usize * 4;
+        out.extend_from_slice(data.get(start..start + row_len)?);
+    }
+    Some(out)
+}
+
+// -----------------------------------------------------------------------------
+// Thread helpers
+// -----------------------------------------------------------------------------
+
+/// Join `handle` if it finishes before `deadline`. Returns false on timeout
+/// (the thread is left detached) and true if there was nothing to join.
+fn join_until(handle: Option<JoinHandle<()>>, deadline: Instant) -> bool {
+    let Some(handle) = handle else { return true };
+    while !handle.is_finished() {
+        if Instant::now() >= deadline {
+            return false;
+        }
+        thread::sleep(Duration::from_millis(10));
+    }
+    let _ = handle.join();
+    true
+}
+
+// -----------------------------------------------------------------------------
+// Recording lifecycle
+// -----------------------------------------------------------------------------
+
+/// Kill the event helper (if any) and wait for it to exit.
+fn stop_helper(state: &AppState) -> Result<(), RecordingError> {
+    if let Some(mut h) = state.helper.lock().unwrap().take() {
+        h.kill().map_err(|e| RecordingError::Helper(format!("failed to kill event capture: {}", e)))?;
+        match h.wait() {
+            Ok(status) => {
+                if !status.success() {
+                    eprintln!("Event capture exited with status: {}", status);
+                }
+            }
+            Err(e) => eprintln!("Failed to wait for event capture: {}", e),
+        }
+    }
+    Ok(())
+}
+
+/// Locate the prebuilt `event_capture` helper next to the main executable
+/// (bundled apps), or under `examples/` (cargo target dirs).
+fn helper_binary() -> Result<PathBuf, RecordingError> {
+    let exe = std::env::current_exe().map_err(|e| RecordingError::Helper(e.to_string()))?;
+    let dir = exe.parent().unwrap_or(Path::new("."));
+    let name = format!("event_capture{}", std::env::consts::EXE_SUFFIX);
+    [dir.join(&name), dir.join("examples").join(&name)]
+        .into_iter()
+        .find(|p| p.is_file())
+        .ok_or_else(|| {
+            RecordingError::Helper(format!(
+                "{} not found next to {}; build it with `cargo build --example event_capture`",
+                name,
+                exe.display()
+            ))
+        })
+}
+
+/// What the helper reported on its first stdout line.
+enum HelperStart {
+    Ready(DateTime<Local>),
+    PermissionDenied,
+}
+
+/// Wait for the helper's status line (`READY` or `PERMISSION_DENIED`), failing
+/// if it reports anything else or stays silent past `HELPER_READY_TIMEOUT`.
+fn wait_for_helper(helper: &mut Child) -> Result<HelperStart, RecordingError> {
+    let stdout = helper
+        .stdout
+        .take()
+        .ok_or_else(|| RecordingError::Helper("stdout unavailable".into()))?;
+    let (tx, rx) = bounded::<String>(1);
+    // the reader ends by itself once the helper exits and its stdout closes
+    thread::spawn(move || {
+        let mut line = String::new();
+        if BufReader::new(stdout).read_line(&mut line).is_ok() {
+            let _ = tx.send(line);
+        }
+    });
+    match rx.recv_timeout(HELPER_READY_TIMEOUT) {
+        Ok(line) => match line.trim() {
+            "READY" => Ok(HelperStart::Ready(Local::now())),
+            "PERMISSION_DENIED" => Ok(HelperStart::PermissionDenied),
+            "" => Err(RecordingError::Helper("exited during startup".into())),
+            other => Err(RecordingError::Helper(other.to_string())),
+        },
+        Err(_) => Err(RecordingError::Helper(format!(
+            "not ready after {}s",
+            HELPER_READY_TIMEOUT.as_secs()
+        ))),
+    }
+}
+
+/// Rolls back a half-started recording when `start_recording` returns early:
+/// kills ffmpeg and the helper and removes the session directory.
+struct StartGuard<'a> {
+    state: &'a AppState,
+}
+
+impl StartGuard<'_> {
+    /// The recording is running; keep everything.
+    fn disarm(self) {
+        std::mem::forget(self);
+    }
+}
+
+impl Drop for StartGuard<'_> {
+    fn drop(&mut self) {
+        if let Some(mut c) = self.state.ffmpeg.lock().unwrap().take() {
+            let _ = c.kill();
+            let _ = c.wait();
+        }
+        self.state.ffmpeg_stderr.lock().unwrap().take();
+        if let Err(e) = stop_helper(self.state) {
+            eprintln!("Failed to stop event capture: {}", e);
+        }
+        self.state.output_file.lock().unwrap().take();
+        self.state.session.lock().unwrap().take();
+        self.state.replay.lock().unwrap().take();
+        self.state.session_log.lock().unwrap().take();
+        if let Some(dir) = self.state.output_dir.lock().unwrap().take() {
+            let _ = std::fs::remove_dir_all(dir);
+        }
+    }
+}
+
+/// Stop the pipeline, reap ffmpeg and the helper, and verify the output.
+/// Shared by `stop_recording` and the capture thread's auto-stop path.
+fn finish_recording(state: &AppState) -> Result<RecordingResult, RecordingError> {
+    // set before is_recording clears, so the session is never unclaimed while
+    // its passes rewrite the output and before its manifest exists
+    state.finishing.store(true, Ordering::Relaxed);
+    let result = save_recording(state);
+    state.finishing.store(false, Ordering::Relaxed);
+    result
+}
+
+/// The body of `finish_recording`.
+fn save_recording(state: &AppState) -> Result<RecordingResult, RecordingError> {
+    // First, signal threads to stop
+    state.is_recording.store(false, Ordering::Relaxed);
+    let ended = Local::now();
+    let log = state.session_log.lock().unwrap().take().unwrap_or_default();
+    log.write("Stopping");
+
+    // Wait for the capture thread to exit (closing the channel), then for the
+    // writer to flush every buffered frame and close ffmpeg's stdin
+    let deadline = Instant::now() + PIPELINE_JOIN_TIMEOUT;
+    let capture_done = join_until(state.capture_thread.lock().unwrap().take(), deadline);
+    let writer_done = join_until(state.writer_thread.lock().unwrap().take(), deadline);
+    join_until(state.window_tracker.lock().unwrap().take(), deadline);
+    if !capture_done || !writer_done {
+        eprintln!("Recording pipeline did not finish within {:?}", PIPELINE_JOIN_TIMEOUT);
+        log.write(format_args!(
+            "Pipeline did not finish within {:?} (capture {}, writer {})",
+            PIPELINE_JOIN_TIMEOUT,
+            if capture_done { "done" } else { "stuck" },
+            if writer_done { "done" } else { "stuck" },
+        ));
+    }
+
+    stop_helper(state)?;
+
+    // Wait for ffmpeg to finish processing
+    let stderr = state.ffmpeg_stderr.lock().unwrap().take();
+    if let Some(mut c) = state.ffmpeg.lock().unwrap().take() {
+        // stdin is still open if the writer hung, so waiting would block forever
+        if !writer_done {
+            let _ = c.kill();
+        }
+        let timeout = session_stop_timeout(state);
+        match ffmpeg::wait_within(&mut c, timeout) {
+            // stuck, e.g. on a filter; the file may be unplayable without its trailer
+            Ok(None) => {
+                let _ = c.kill();
+                let _ = c.wait();
+                let output = stderr.map(|s| s.text()).unwrap_or_default();
+                log.write(format_args!("ffmpeg killed after not exiting within {:?}:\n{}", timeout, output));
+                return Err(RecordingError::FfmpegFailed(format!(
+                    "ffmpeg did not finish within {}s of stopping and was killed; the output may be incomplete",
+                    timeout.as_secs()
+                )));
+            }
+            Ok(Some(status)) => {
+                log.write(format_args!("ffmpeg exited with {}", status));
+                // a kill above is ours; anything else is ffmpeg failing on its own
+                if !status.success() && writer_done {
+                    let output = stderr.map(|s| s.text()).unwrap_or_default();
+                    eprintln!("FFmpeg exited with status: {}\n{}", status, output);
+                    log.write(format_args!("ffmpeg output:\n{}", output));
+                    return Err(RecordingError::FfmpegFailed(if output.is_empty() {
+                        format!("ffmpeg exited with {}", status)
+                    } else {
+                        format!("ffmpeg exited with {}:\n{}", status, output)
+                    }));
+                }
+            }
+            Err(e) => {
+                eprintln!("Failed to wait for ffmpeg: {}", e);
+                log.write(format_args!("Failed to wait for ffmpeg: {}", e));
+                // If waiting fails, then kill it
+                let _ = c.kill();
+            }
+        }
+    }
+
+    // return path
+    let out = state.output_file.lock().unwrap().clone().ok_or(RecordingError::NotRecording)?;
+    // an output_target leaves nothing in the session folder to check or post-process
+    let streamed = state.session.lock().unwrap().as_ref().is_some_and(|s| s.opts.output_target.is_some());
+
+    // instant-replay sessions produce their output only now, from the buffer
+    if let Some(replay) = state.replay.lock().unwrap().take() {
+        replay.snapshot().encode(&out)?;
+    }
+    
+    // Verify the output exists and has size > 0
+    if !streamed {
+        if let Err(e) = std::fs::metadata(&out) {
+            log.write(format_args!("Output {} unavailable: {}", out.display(), e));
+            return Err(RecordingError::FfmpegFailed(format!("output file unavailable: {}", e)));
+        }
+    }
+    let segments = std::mem::take(&mut *state.segments.lock().unwrap());
+    let file_size = recorded_size(&out, &segments);
+    if file_size == 0 && !streamed {
+        log.write("Output file is empty");
+        return Err(RecordingError::FfmpegFailed("output file is empty".into()));
+    }
+
+    let session = state.session.lock().unwrap().take().ok_or(RecordingError::NotRecording)?;
+
+    // the recording itself is fine at this point, so failed post-processing only warns
+    let start_epoch = session.started.timestamp_millis() as f64 / 1000.0;
+    // the pointer goes under the click rings
+    if let (Some(mapping), Some(events)) = (session.cursor, &session.events_file) {
+        let size = (session.width, session.height);
+        if let Err(e) = cursor::draw_cursor(&session.opts, &out, events, start_epoch, mapping, size) {
+            *state.error.lock().unwrap() = Some(format!("Synthetic cursor skipped: {}", e));
+        }
+    }
+    if let (Some(mapping), Some(events)) = (session.clicks, &session.events_file) {
+        let size = (session.width, session.height);
+        if let Err(e) = clicks::highlight_clicks(&session.opts, &out, events, start_epoch, mapping, size) {
+            *state.error.lock().unwrap() = Some(format!("Click highlighting skipped: {}", e));
+        }
+    }
+    // captions go on top of everything
+    if let (true, Some(events)) = (session.opts.show_keycaps, &session.events_file) {
+        let size = (session.width, session.height);
+        if let Err(e) = keycaps::draw_keycaps(&session.opts, &out, events, start_epoch, size) {
+            *state.error.lock().unwrap() = Some(format!("Keycaps skipped: {}", e));
+        }
+    }
+
+    let mut events_indexed = None;
+    // with motion_only, frames no longer follow the wall clock the events are on
+    if let (false, Some(events_file)) = (session.opts.motion_only, &session.events_file) {
+        let speed = session.opts.speed_factor().ok().flatten().unwrap_or(1.0);
+        match events::write_indexed(events_file, start_epoch, session.fps, speed) {
+            Ok(path) => events_indexed = Some(path),
+            Err(e) => *state.error.lock().unwrap() = Some(format!("Event indexing skipped: {}", e)),
+        }
+    }
+
+    let duration_secs = (ended - session.started).num_milliseconds().max(0) as f64 / 1000.0;
+
+    let mut chapters = None;
+    if let (Some(events_file), Some(key)) = (&session.events_file, &session.opts.chapter_key) {
+        let speed = session.opts.speed_factor().ok().flatten().unwrap_or(1.0);
+        match events::write_chapters(events_file, start_epoch, speed, key, duration_secs / speed) {
+            Ok(path) => chapters = path,
+            Err(e) => *state.error.lock().unwrap() = Some(format!("Chapter export skipped: {}", e)),
+        }
+    }
+    let speed = session.opts.speed_factor().ok().flatten().unwrap_or(1.0);
+    let fades = session.opts.fades().unwrap_or_default();
+    if fades != (0.0, 0.0) {
+        // measured, since dropped frames and speed-up make the clock a poor guide
+        let media_secs = ffmpeg::duration(&ffmpeg::ffprobe_bin(session.opts.ffmpeg_bin()), &out)
+            .unwrap_or(duration_secs / speed);
+        match fade::apply(&session.opts, &out, fades, media_secs) {
+            Ok(used) if used != fades => {
+                log.write(format_args!("Fades shortened to {:.2}s in, {:.2}s out", used.0, used.1))
+            }
+            Ok(_) => {}
+            Err(e) => *state.error.lock().unwrap() = Some(format!("Fade skipped: {}", e)),
+        }
+    }
+    // a split recording's thumbnail comes from the middle of its first segment
+    let thumb_secs = match segments.len() {
+        0 | 1 => duration_secs / speed,
+        _ => ffmpeg::duration(&ffmpeg::ffprobe_bin(session.opts.ffmpeg_bin()), &out).unwrap_or(0.0),
+    };
+    let thumbnail = match (!streamed).then(|| write_thumbnail(session.opts.ffmpeg_bin(), &out, thumb_secs)) {
+        Some(Ok(path)) => Some(path),
+        Some(Err(e)) => {
+            *state.error.lock().unwrap() = Some(format!("Thumbnail skipped: {}", e));
+            None
+        }
+        None => None,
+    };
+
+    let frames_captured = state.frames_captured.load(Ordering::Relaxed);
+    let dropped_frames = state.dropped_frames.load(Ordering::Relaxed);
+    let stats = RecordingStats {
+        duration_secs,
+        frames_captured,
+        average_fps: if duration_secs > 0.0 { frames_captured as f64 / duration_secs } else { 0.0 },
+        dropped_frames,
+        channel_full: state.channel_full.load(Ordering::Relaxed),
+        // the click pass may have re-encoded the file
+        file_size: recorded_size(&out, &segments).max(file_size),
+    };
+
+    let manifest = RecordingManifest {
+        output_path: out.to_string_lossy().into(),
+        width: session.width,
+        height: session.height,
+        fps: session.fps,
+        duration_secs,
+        keystrokes_captured: session.events_file.is_some(),
+        mouse_captured: session.events_file.is_some(),
+        events_file: session.events_file.map(|p| p.to_string_lossy().into()),
+        events_started_at: session.helper_ready.map(|t| t.to_rfc3339()),
+        events_indexed_file: events_indexed.map(|p| p.to_string_lossy().into()),
+        chapters_file: chapters.map(|p| p.to_string_lossy().into()),
+        window_activity_file: session.window_activity.map(|p| p.to_string_lossy().into()),
+        screen_mapping: session.screen_mapping,
+        thumbnail_file: thumbnail.map(|p| p.to_string_lossy().into()),
+        started_epoch: start_epoch,
+        started_at: session.started.to_rfc3339(),
+        ended_at: ended.to_rfc3339(),
+        platform: std::env::consts::OS.into(),
+        recovered: false,
+        segments: segments.iter().map(|p| p.to_string_lossy().into()).collect(),
+        hashes_verifiable: session.frame_hashes.is_some() && session.opts.hashes_verifiable(),
+        frame_hashes_file: session.frame_hashes.map(|p| p.to_string_lossy().into()),
+        frame_timestamps_file: session.frame_timestamps.map(|p| p.to_string_lossy().into()),
+        appended: Vec::new(),
+    };
+    let session_dir = if streamed {
+        state.output_dir.lock().unwrap().clone()
+    } else {
+        out.parent().map(Path::to_path_buf)
+    };
+    let mut manifest_path = write_manifest(session_dir.as_deref().unwrap_or(Path::new(".")), &manifest)?;
+    let mut output_path = manifest.output_path.clone();
+    // the take is complete on its own; joining it on is post-processing too
+    if let Some(dir) = &session.opts.append_to_session {
+        match append::join(session.opts.ffmpeg_bin(), Path::new(dir), &manifest) {
+            Ok((joined, joined_manifest, warning)) => {
+                log.write(format_args!("Appended to {}", joined.display()));
+                if warning.is_some() {
+                    *state.error.lock().unwrap() = warning;
+                }
+                output_path = joined.to_string_lossy().into();
+                manifest_path = joined_manifest;
+            }
+            Err(e) => {
+                *state.error.lock().unwrap() =
+                    Some(format!("Appending skipped, the take is kept in {}: {}", out.parent().unwrap_or(&out).display(), e));
+            }
+        }
+    }
+    if let Some(warning) = state.error.lock().unwrap().as_ref() {
+        log.write(format_args!("Warning: {}", warning));
+    }
+    log.write(format_args!(
+        "Finished: {:.2}s, {} frames captured, {} dropped ({} with the encoder behind), {} bytes",
+        stats.duration_secs, stats.frames_captured, stats.dropped_frames, stats.channel_full, stats.file_size
+    ));
+
+    Ok(RecordingResult {
+        output_path,
+        manifest_path: manifest_path.to_string_lossy().into(),
+        dropped_frames,
+        stats,
+        thumbnail_path: manifest.thumbnail_file,
+        segments: manifest.segments,
+    })
+}
+
+/// Write `thumbnail.jpg` next to `out` from the middle of the recording, or its
+/// first frame when it's under a second long. For frames output, `out` is the
+/// image directory and the middle image is used.
+fn write_thumbnail(bin: &str, out: &Path, video_secs: f64) -> Result<PathBuf, String> {
+    let dir = out.parent().unwrap_or(Path::new("."));
+    let thumb = dir.join("thumbnail.jpg");
+    if out.is_dir() {
+        let mut frames: Vec<PathBuf> = std::fs::read_dir(out)
+            .map_err(|e| e.to_string())?
+            .filter_map(|e| Some(e.ok()?.path()))
+            .collect();
+        frames.sort();
+        let middle = frames.get(frames.len() / 2).ok_or("no frames were written")?;
+        ffmpeg::thumbnail(bin, middle, 0.0, &thumb)?;
+    } else {
+        let at = if video_secs < 1.0 { 0.0 } else { video_secs / 2.0 };
+        ffmpeg::thumbnail(bin, out, at, &thumb)?;
+    }
+    Ok(thumb)
+}
+
+
+/// Finalize the recording from a background thread (the capture thread can't
+/// join itself) and tell the frontend the stop wasn't user-initiated.
+fn spawn_auto_stop<R: Runtime>(app: AppHandle<R>, reason: String) {
+    thread::spawn(move || {
+        let state = app.state::<AppState>();
+        *state.error.lock().unwrap() = Some(reason.clone());
+        let outcome = finish_recording(&state);
+        let _ = app.emit("recording-auto-stopped", AutoStopped {
+            reason,
+            error: outcome.as_ref().err().map(|e| e.to_string()),
+            result: outcome.ok(),
+        });
+    });
+}
+
+/// Tell the frontend about a failed capture or writer thread straight away,
+/// rather than leaving a truncated file to be found at stop time.
+fn emit_failure<R: Runtime>(app: &AppHandle<R>, stage: &str, reason: String) {
+    eprintln!("Recording {} failed: {}", stage, reason);
+    let _ = app.emit("recording-error", RecordingFailed { stage: stage.into(), reason });
+}
+
+/// Flag (once per stall) when no frame has reached the encoder for
+/// `STALL_TIMEOUT` while recording, e.g. because ffmpeg hung or died.
+fn spawn_watchdog<R: Runtime>(
+    app: AppHandle<R>,
+    alive: Arc<AtomicBool>,
+    idle: Arc<AtomicBool>,
+    written: Arc<AtomicU64>,
+    stderr: Arc<Mutex<Option<StderrTail>>>,
+) {
+    thread::spawn(move || {
+        let mut last_count = 0;
+        let mut last_change = Instant::now();
+        let mut flagged = false;
+        while alive.load(Ordering::Relaxed) {
+            thread::sleep(Duration::from_secs(1));
+            let count = written.load(Ordering::Relaxed);
+            // no frames are due while motion_only is waiting for activity
+            if count != last_count || idle.load(Ordering::Relaxed) {
+                last_count = count;
+                last_change = Instant::now();
+                flagged = false;
+                continue;
+            }
+            if flagged || last_change.elapsed() < STALL_TIMEOUT || !alive.load(Ordering::Relaxed) {
+                continue;
+            }
+            flagged = true;
+            // the current segment's, when the output has rolled over
+            let ffmpeg_output = stderr.lock().unwrap().as_ref().map(StderrTail::text).filter(|t| !t.is_empty());
+            let stalled_secs = last_change.elapsed().as_secs();
+            let state = app.state::<AppState>();
+            let message = match &ffmpeg_output {
+                Some(output) => format!("Encoder stalled for {}s:\n{}", stalled_secs, output),
+                None => format!("Encoder stalled for {}s", stalled_secs),
+            };
+            if let Some(log) = state.session_log.lock().unwrap().as_ref() {
+                log.write(&message);
+            }
+            *state.error.lock().unwrap() = Some(message);
+            let _ = app.emit("recording-stalled", RecordingStalled {
+                stalled_secs,
+                frames_written: count,
+                ffmpeg_output,
+            });
+        }
+    });
+}
+
+/// Start a recording with `opts`, or stop the running one, and report the
+/// outcome as a `recording-toggled` event.
+fn toggle_recording(app: &AppHandle, opts: RecordingOptions) {
+    let state = app.state::<AppState>();
+    if state.is_recording.load(Ordering::Relaxed) || state.counting_down.load(Ordering::Relaxed) {
+        report_stop(app);
+    } else {
+        report_start(app, opts);
+    }
+}
+
+/// Start a recording with `opts` and report it as `recording-toggled`.
+fn report_start(app: &AppHandle, opts: RecordingOptions) {
+    let outcome = start_recording(app.clone(), app.state::<AppState>(), opts);
+    let _ = app.emit("recording-toggled", RecordingToggled {
+        is_recording: outcome.is_ok(),
+        result: None,
+        error: outcome.err(),
+    });
+}
+
+/// Stop the running recording and report it as `recording-toggled`.
+fn report_stop(app: &AppHandle) {
+    let outcome = stop_recording(app.state::<AppState>());
+    let _ = app.emit("recording-toggled", RecordingToggled {
+        is_recording: false,
+        error: outcome.as_ref().err().cloned(),
+        result: outcome.ok(),
+    });
+}
+
+// -----------------------------------------------------------------------------
+// Tauri commands
+// -----------------------------------------------------------------------------
+
+#[tauri::command(async)]
+fn start_recording(app: AppHandle, state: State<AppState>, opts: RecordingOptions) -> Result<RecordingSession, RecordingError> {
+    ensure_idle(&state)?;
+    begin_recording(app, state, opts, None)
+}
+
+/// `start_recording` with frames from `source` instead of a display, e.g. a
+/// synthetic one in tests. `app` must manage an `AppState`; the recording is
+/// stopped with `stop_active_recording` or any of the usual commands.
+pub fn start_recording_with_source<R: Runtime>(
+    app: &AppHandle<R>,
+    opts: RecordingOptions,
+    source: Box<dyn FrameSource>,
+) -> Result<RecordingSession, RecordingError> {
+    let state = app.state::<AppState>();
+    ensure_idle(&state)?;
+    begin_recording(app.clone(), state, opts, Some(source))
+}
+
+/// `stop_recording` for the recording running in `app`.
+pub fn stop_active_recording<R: Runtime>(app: &AppHandle<R>) -> Result<RecordingResult, RecordingError> {
+    stop_recording(app.state::<AppState>())
+}
+
+fn ensure_idle(state: &AppState) -> Result<(), RecordingError> {
+    if state.is_recording.load(Ordering::Relaxed)
+        || state.counting_down.load(Ordering::Relaxed)
+        || state.restarting.load(Ordering::Relaxed)
+    {
+        return Err(RecordingError::AlreadyRecording);
+    }
+    Ok(())
+}
+
+/// Validate `opts`, set up the session and start the pipeline. Frames come
+/// from `source` when given, otherwise from the displays `opts` selects.
+fn begin_recording<R: Runtime>(
+    app: AppHandle<R>,
+    state: State<AppState>,
+    mut opts: RecordingOptions,
+    source: Option<Box<dyn FrameSource>>,
+) -> Result<RecordingSession, RecordingError> {
+    let requested = opts.clone();
+    // an injected source needs neither the screen nor permission to read it
+    if source.is_none() {
+        if !is_supported() {
+            return Err(RecordingError::Unsupported);
+        }
+        if !request_permission() {
+            return Err(RecordingError::PermissionDenied);
+        }
+    }
+    // resolved per start, so a restart finds the display wherever it is now
+    resolve_monitor_name(&app, &mut opts)?;
+    opts.apply_rate_overrides();
+    if opts.fps == 0 || opts.fps > MAX_FPS {
+        return Err(RecordingError::InvalidOptions(format!(
+            "Invalid fps {}: must be between 1 and {}",
+            opts.fps, MAX_FPS
+        )));
+    }
+    // duplicating frames to reach a higher rate is almost never what's wanted
+    if let Some(out) = opts.output_fps {
+        if out == 0 || out > opts.fps {
+            return Err(RecordingError::InvalidOptions(format!(
+                "Invalid output_fps {}: must be between 1 and the capture rate of {}",
+                out, opts.fps
+            )));
+        }
+    }
+    *state.error.lock().unwrap() = None;
+
+    // an unusable hardware encoder degrades to libx264 instead of failing
+    if let Some(encoder) = opts.encoder.clone() {
+        if HW_H264_ENCODERS.contains(&encoder.as_str()) && !ffmpeg::encoder_works(opts.ffmpeg_bin(), &encoder) {
+            *state.error.lock().unwrap() = Some(format!("Encoder {} is unavailable, using libx264", encoder));
+            opts.encoder = None;
+        }
+    }
+    let codec_args = opts.video_codec_args()?;
+    opts.stop_timeout()?;
+    let follow_size = opts.follow_cursor()?;
+    let output_target = opts.output_target()?;
+    let frame_format = opts.frame_image_format()?;
+    let vfr_threshold = opts.vfr_threshold()?;
+    let mut motion = opts.motion_gate()?;
+    // a replay buffer is bounded by its window instead
+    let frame_limit = opts.animated_frame_limit()?.filter(|_| opts.ring_buffer_secs.is_none());
+    if frame_format.is_some() && opts.highlight_clicks {
+        return Err(RecordingError::InvalidOptions("highlight_clicks needs video output".into()));
+    }
+    if opts.synthetic_cursor && (frame_format.is_some() || opts.ring_buffer_secs.is_some()) {
+        return Err(RecordingError::InvalidOptions(
+            "synthetic_cursor needs video output and can't be combined with ring_buffer_secs".into(),
+        ));
+    }
+    if opts.show_keycaps && (frame_format.is_some() || opts.ring_buffer_secs.is_some()) {
+        return Err(RecordingError::InvalidOptions(
+            "show_keycaps needs video output and can't be combined with ring_buffer_secs".into(),
+        ));
+    }
+    if opts.ring_buffer_secs.is_some() && (opts.webcam.is_some() || opts.audio_source.is_some()) {
+        return Err(RecordingError::InvalidOptions(
+            "ring_buffer_secs can't be combined with webcam or audio capture".into(),
+        ));
+    }
+    if opts.ring_buffer_secs.is_some() && opts.frame_hashes {
+        return Err(RecordingError::InvalidOptions("frame_hashes can't be combined with ring_buffer_secs".into()));
+    }
+    if opts.ring_buffer_secs.is_some() && opts.frame_timestamps {
+        return Err(RecordingError::InvalidOptions(
+            "frame_timestamps can't be combined with ring_buffer_secs".into(),
+        ));
+    }
+    let timecode = if opts.burn_timecode {
+        let font = ffmpeg::find_font().ok_or_else(|| {
+            RecordingError::FfmpegFailed("burn_timecode needs a TrueType font, but none of the usual system fonts exist".into())
+        })?;
+        let drawtext = opts.timecode_filter(font)?;
+        ffmpeg::check_filter(opts.ffmpeg_bin(), &drawtext).map_err(|e| {
+            RecordingError::FfmpegFailed(format!(
+                "the drawtext filter failed to initialize (ffmpeg needs libfreetype): {}",
+                e
+            ))
+        })?;
+        Some(drawtext)
+    } else {
+        None
+    };
+    let audio_codec_args = opts.audio_codec_args()?;
+    opts.speed_factor()?;
+    opts.fades()?;
+    opts.logical_capture()?;
+    opts.output_resolution()?;
+    opts.aspect()?;
+    let helper_args = opts.helper_args()?;
+    let extra_args = opts.extra_ffmpeg_args()?;
+    if let Some(dir) = &opts.append_to_session {
+        if frame_format.is_some() || opts.ring_buffer_secs.is_some() || opts.max_file_size_mb.is_some() {
+            return Err(RecordingError::InvalidOptions(
+                "append_to_session can't be combined with frames output, ring buffer capture or max_file_size_mb"
+                    .into(),
+            ));
+        }
+        append::validate(Path::new(dir), opts.output_format()?)?;
+    }
+    if let Some(cam) = &opts.webcam {
+        cam.overlay_position()?;
+    }
+
+    // count down before touching the capturer, so a cancel leaves nothing behind
+    if let Some(secs) = opts.countdown_secs {
+        state.counting_down.store(true, Ordering::Relaxed);
+        for remaining in (1..=secs).rev() {
+            let _ = app.emit("countdown-tick", remaining);
+            let tick_end = Instant::now() + Duration::from_secs(1);
+            while Instant::now() < tick_end {
+                if !state.counting_down.load(Ordering::Relaxed) {
+                    return Err(RecordingError::Cancelled);
+                }
+                thread::sleep(Duration::from_millis(50));
+            }
+        }
+        if !state.counting_down.swap(false, Ordering::Relaxed) {
+            return Err(RecordingError::Cancelled);
+        }
+    }
+
+    // open the audio devices up front so a missing one fails before anything is spawned
+    let mut audio_args = Vec::new();
+    let wanted: &[bool] = match opts.audio_source {
+        None => &[],
+        Some(AudioSource::Microphone) => &[false],
+        Some(AudioSource::System) => &[true],
+        Some(AudioSource::Both) => &[false, true],
+    };
+    for &system in wanted {
+        let label = if system { "system audio" } else { "microphone" };
+        let args = devices::audio_input_args(opts.ffmpeg_bin(), system).ok_or_else(|| {
+            RecordingError::Audio(if system {
+                "no system audio source found (install a loopback device such as BlackHole on macOS, \
+                 or enable Stereo Mix on Windows)"
+                    .into()
+            } else {
+                "no microphone found".into()
+            })
+        })?;
+        if !ffmpeg::probe_input(opts.ffmpeg_bin(), &args, Duration::from_secs(5)) {
+            return Err(RecordingError::Audio(format!("the {} device could not be opened", label)));
+        }
+        audio_args.extend(args);
+    }
+    let audio_sources = wanted.len();
+
+    // resolve the capture target(s) before spawning anything
+    let targets = match source {
+        Some(_) => Vec::new(),
+        None => capture_targets(&opts)?,
+    };
+
+    // create session directory, or a take folder inside the one appended to
+    let session = match &opts.append_to_session {
+        Some(dir) => append::take_dir(Path::new(dir))?,
+        None => create_session_dir(&app, opts.output_dir.as_deref())?,
+    };
+    *state.output_dir.lock().unwrap() = Some(session.clone());
+    // from here on, any early return undoes what was started
+    let guard = StartGuard { state: &state };
+    let log = SessionLog::create(&session)?;
+    log.write(format_args!("{} {} on {}", env!("CARGO_PKG_NAME"), env!("CARGO_PKG_VERSION"), std::env::consts::OS));
+    log.write(format_args!("Options: {}", serde_json::to_string(&opts).unwrap_or_default()));
+    *state.session_log.lock().unwrap() = Some(log.clone());
+
+    // spawn helper process for keystrokes/mouse events
+    let mut events_file = (opts.capture_keystrokes
+        || opts.highlight_clicks
+        || opts.synthetic_cursor
+        || opts.show_keycaps
+        || opts.chapter_key.is_some()
+        || opts.capture_clipboard)
+        .then(|| session.join("events.log"));
+    let mut helper_ready = None;
+    if let Some(path) = &events_file {
+        let mut helper = Command::new(helper_binary()?)
+            .arg(path)
+            .args(&helper_args)
+            // the start epoch follows on stdin once capture begins
+            .arg("--wait-for-start")
+            .stdin(Stdio::piped()) // PAUSE / RESUME, see toggle_keystroke_capture
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()
+            .map_err(|e| RecordingError::Helper(format!("failed to spawn: {}", e)))?;
+        match wait_for_helper(&mut helper) {
+            Ok(HelperStart::Ready(ready)) => {
+                log.write("Event capture helper ready");
+                helper_ready = Some(ready);
+                *state.helper.lock().unwrap() = Some(helper);
+            }
+            // without input monitoring the recording is still useful, just video-only
+            Ok(HelperStart::PermissionDenied) => {
+                log.write("Event capture helper lacks input monitoring permission");
+                let _ = helper.kill();
+                let _ = helper.wait();
+                *state.error.lock().unwrap() = Some(
+                    "Input monitoring permission is missing; recording video without keystrokes or clicks".into(),
+                );
+                opts.capture_keystrokes = false;
+                opts.highlight_clicks = false;
+                opts.synthetic_cursor = false;
+                opts.show_keycaps = false;
+                events_file = None;
+            }
+            Err(e) => {
+                log.write(format_args!("Event capture helper failed: {}", e));
+                let _ = helper.kill();
+                let _ = helper.wait();
+                return Err(e);
+            }
+        }
+    }
+
+    let display_scale = if source.is_some() { 1.0 } else { display_scale_factor(&opts) };
+    log.write(format_args!("Display scale factor {}", display_scale));
+
+    // initialize capturers (one per display when compositing)
+    let mut capturers: Vec<SourceGuard> = source.map(SourceGuard::new).into_iter().collect();
+    for target in targets {
+        let mut capturer = Capturer::build(scap::capturer::Options {
+            fps: opts.fps,
+            target,
+            show_cursor: opts.show_cursor && !opts.synthetic_cursor,
+            show_highlight: opts.show_highlight,
+            output_type: scap::frame::FrameType::BGRAFrame,
+            // physical pixels; "logical" output is scaled down in ffmpeg
+            output_resolution: scap::capturer::Resolution::Captured,
+            ..Default::default()
+        }).map_err(|e| RecordingError::Capture(e.to_string()))?;
+        capturer.start_capture();
+        capturers.push(SourceGuard::new(Box::new(capturer)));
+    }
+    if opts.fps_auto_adjust {
+        let (_, times) = time_frames(&mut capturers[0], FPS_PROBE_FRAMES)?;
+        let measured_fps = times.len() as f64 / times.iter().sum::<f64>();
+        println!("Measured max FPS: {:.2}", measured_fps);
+        log.write(format_args!("Measured {:.2} fps against {} requested", measured_fps, opts.fps));
+
+        // Clamp to what the machine can deliver, unless the measurement is unusable
+        if measured_fps.is_finite() && measured_fps >= 1.0 {
+            opts.fps = opts.fps.min(measured_fps.floor() as u32);
+        } else {
+            eprintln!("Ignoring unusable fps measurement, keeping requested {}", opts.fps);
+        }
+    }
+    println!("Final recording FPS set to {} (output {})", opts.fps, opts.output_fps());
+    log.write(format_args!("Capturing at {} fps, output {} fps", opts.fps, opts.output_fps()));
+
+    // grab first frame(s) for geometry
+    let mut sizes = Vec::new();
+    for capturer in &mut capturers {
+        let (fw, fh, _) = capturer.require_frame()?;
+        sizes.push((fw, fh));
+    }
+    let layout = if capturers.len() > 1 {
+        let screens = Screen::all().map_err(|e| RecordingError::Capture(e.to_string()))?;
+        let (origins, scale) = desktop_layout(&screens);
+        Some(composite::layout(&origins, scale, &sizes))
+    } else {
+        None
+    };
+    let (frame_w, frame_h) = layout.as_ref().map(|l| (l.width, l.height)).unwrap_or(sizes[0]);
+
+    // validate the crop region against the real frame geometry
+    let region = match opts.region {
+        Some(requested) => {
+            let clamped = clamp_region(requested, frame_w, frame_h).ok_or_else(|| {
+                RecordingError::InvalidOptions(format!(
+                    "Region {:?} lies outside the {}x{} frame",
+                    requested, frame_w, frame_h
+                ))
+            })?;
+            if clamped != requested {
+                *state.error.lock().unwrap() = Some(format!(
+                    "Region {:?} adjusted to {:?} to fit the {}x{} frame",
+                    requested, clamped, frame_w, frame_h
+                ));
+            }
+            Some(clamped)
+        }
+        None => None,
+    };
+    // a follow window no bigger than the screen, moving inside it
+    let follow_size = follow_size.map(|(fw, fh)| (fw.min(frame_w) & !1, fh.min(frame_h) & !1));
+    let mut follower = match follow_size {
+        Some(size) => {
+            let mapping = click_mapping(&opts, None).ok_or_else(|| {
+                RecordingError::Capture("Can't locate the captured display to follow the cursor".into())
+            })?;
+            Some(follow::CursorFollower::new(size, (frame_w, frame_h), mapping))
+        }
+        None => None,
+    };
+    let (w, h) = region.map(|(_, _, w, h)| (w, h)).or(follow_size).unwrap_or((frame_w, frame_h));
+    let OutputGeometry { filters: geometry, aspect_size: (aw, ah), size: (out_w, out_h) } =
+        output_geometry(&opts, (w, h), display_scale)?;
+    log.write(format_args!("Frames are {}x{}, recording {}x{}", frame_w, frame_h, out_w, out_h));
+
+    // the event log can't be placed on a moving window
+    let screen_mapping = events_file
+        .as_ref()
+        .filter(|_| follower.is_none())
+        .and_then(|_| click_mapping(&opts, region))
+        .map(|mapping| {
+            // the aspect crop or pad shifts the frame by half the difference,
+            // then the scale filter fits it into the output, centred
+            let shift = ((aw as f64 - w as f64) / 2.0, (ah as f64 - h as f64) / 2.0);
+            let ratio = (out_w as f64 / aw as f64).min(out_h as f64 / ah as f64);
+            let pad = ((out_w as f64 - aw as f64 * ratio) / 2.0, (out_h as f64 - ah as f64 * ratio) / 2.0);
+            ClickMapping {
+                scale: mapping.scale * ratio,
+                crop: ((mapping.crop.0 - shift.0) * ratio - pad.0, (mapping.crop.1 - shift.1) * ratio - pad.1),
+                ..mapping
+            }
+        });
+    let clicks = if opts.highlight_clicks {
+        if screen_mapping.is_none() {
+            *state.error.lock().unwrap() = Some("Click highlighting is unavailable for this capture target".into());
+        }
+        screen_mapping
+    } else {
+        None
+    };
+    let cursor = if opts.synthetic_cursor {
+        if screen_mapping.is_none() {
+            *state.error.lock().unwrap() = Some("The synthetic cursor is unavailable for this capture target".into());
+        }
+        screen_mapping
+    } else {
+        None
+    };
+
+    // make sure the volume can hold the recording before launching ffmpeg;
+    // a stream's room is the receiver's business
+    let planned_secs = opts.max_duration_secs.unwrap_or(UNBOUNDED_ESTIMATE_SECS);
+    let bytes_per_sec = estimate::bytes_per_sec(&opts, (out_w, out_h))?;
+    if output_target.is_none() {
+        ensure_free_space(&session, (bytes_per_sec * planned_secs as f64) as u64)?;
+    }
+    if opts.lossless {
+        *state.error.lock().unwrap() = Some(format!(
+            "Lossless capture writes very large files, roughly {} MB per minute at {}x{}",
+            (bytes_per_sec * 60.0) as u64 / 1_000_000,
+            out_w,
+            out_h
+        ));
+    }
+    let buffer_size = opts.frame_buffer_size(w as u64 * h as u64 * 4)?;
+
+    // open the webcam if requested; a broken camera degrades to screen-only
+    let mut webcam_args = Vec::new();
+    if let Some(cam) = &opts.webcam {
+        let camera = devices::list_cameras(opts.ffmpeg_bin()).into_iter().nth(cam.device);
+        match camera {
+            Some(camera) => {
+                let args = devices::camera_input_args(&camera);
+                if ffmpeg::probe_input(opts.ffmpeg_bin(), &args, Duration::from_secs(5)) {
+                    webcam_args = args;
+                } else {
+                    *state.error.lock().unwrap() =
+                        Some(format!("Webcam '{}' could not be opened, recording screen only", camera.name));
+                }
+            }
+            None => {
+                *state.error.lock().unwrap() =
+                    Some(format!("Webcam {} not found, recording screen only", cam.device));
+            }
+        }
+    }
+
+    // audio inputs follow the screen and webcam inputs
+    let first_audio = 1 + usize::from(!webcam_args.is_empty());
+    let audio_inputs: Vec<usize> = (first_audio..first_audio + audio_sources).collect();
+    let filter_args = opts
+        .filter_graph(out_w, &geometry, !webcam_args.is_empty(), &audio_inputs, timecode.as_deref())?
+        .into_args();
+
+    let segment_limit = opts.segment_limit()?;
+    let out_file = match (frame_format, segment_limit, &output_target) {
+        (Some(_), _, _) => session.join("frames"),
+        (None, Some(_), _) => segment::segment_path(&session, opts.output_format()?, 1),
+        (None, None, Some(target)) => PathBuf::from(&target.location),
+        (None, None, None) => session.join(format!("output.{}", opts.output_format()?)),
+    };
+    *state.output_file.lock().unwrap() = Some(out_file.clone());
+    *state.segments.lock().unwrap() = segment_limit.map(|_| out_file.clone()).into_iter().collect();
+    let frame_hashes = opts.frame_hashes.then(|| session.join("frame_hashes.jsonl"));
+    let mut hasher = frame_hashes.as_deref().map(integrity::FrameHasher::create).transpose()?;
+    let frame_timestamps = opts.frame_timestamps.then(|| session.join("frame_timestamps.csv"));
+    let mut timestamps = frame_timestamps.as_deref().map(frame_times::FrameTimestamps::create).transpose()?;
+    let mut sink = if let Some(format) = frame_format {
+        // frames mode: no ffmpeg, the writer thread saves each frame as an image
+        std::fs::create_dir_all(&out_file)?;
+        log.write(format_args!("Writing {:?} images to {}", format, out_file.display()));
+        FrameSink::Images { dir: out_file.clone(), format, width: w, height: h, next: 0 }
+    } else if let Some(secs) = opts.ring_buffer_secs {
+        // instant replay: frames go to memory, ffmpeg only runs when a replay is saved
+        let encoder = ReplayEncoder {
+            ffmpeg: opts.ffmpeg_bin().into(),
+            width: w,
+            height: h,
+            fps: opts.fps,
+            output_args: [filter_args, codec_args, opts.metadata_args(out_w, out_h), extra_args].concat(),
+        };
+        log.write(format_args!("Replay buffer of {}s; ffmpeg runs when a replay is saved", secs));
+        let (replay, held_secs) = ReplayBuffer::new(secs, REPLAY_MEMORY_LIMIT, encoder);
+        if held_secs < secs as f64 {
+            *state.error.lock().unwrap() = Some(format!(
+                "Replay buffer capped at {:.1}s to fit in memory at {}x{}",
+                held_secs, w, h
+            ));
+        }
+        *state.replay.lock().unwrap() = Some(replay);
+        FrameSink::Replay(state.replay.clone())
+    } else {
+        // launch ffmpeg
+        // vfr frames are stamped when ffmpeg reads them, so gaps stay gaps;
+        // frames queued in the channel get the read time, not the capture time
+        let rate_args = match vfr_threshold {
+            Some(_) => ["-use_wallclock_as_timestamps".to_string(), "1".to_string()],
+            None => ["-r".to_string(), opts.fps.to_string()],
+        };
+        let input_args: Vec<String> = ["-y", "-f", "rawvideo", "-pix_fmt", "bgra", "-s", &format!("{w}x{h}")]
+            .iter()
+            .map(|a| a.to_string())
+            .chain(rate_args)
+            .chain(["-i".to_string(), "-".to_string()])
+            .collect();
+        let encoder = EncoderCommand {
+            bin: opts.ffmpeg_bin().into(),
+            args: [
+                input_args,
+                webcam_args,
+                audio_args,
+                filter_args,
+                codec_args,
+                audio_codec_args,
+                opts.metadata_args(out_w, out_h),
+                extra_args,
+                output_target.as_ref().map(target::OutputTarget::output_args).unwrap_or_default(),
+            ]
+            .concat(),
+            // the filter graph meters audio whenever any is recorded
+            meter: (audio_sources > 0).then(|| {
+                let app = app.clone();
+                LevelMeter::new(move |level| {
+                    let _ = app.emit("audio-level", level);
+                })
+            }),
+        };
+        log.write(format_args!("ffmpeg: {}", encoder.command_line(&out_file)));
+        let (ffmpeg, ff_stdin, stderr) = encoder.spawn(&out_file)?;
+        *state.ffmpeg_stderr.lock().unwrap() = stderr;
+        *state.ffmpeg.lock().unwrap() = Some(ffmpeg);
+        match segment_limit {
+            Some(limit_bytes) => FrameSink::Segmented {
+                segmenter: Segmenter {
+                    encoder,
+                    stdin: ff_stdin,
+                    dir: session.clone(),
+                    ext: opts.output_format()?.into(),
+                    limit_bytes,
+                    index: 1,
+                    segments: state.segments.clone(),
+                    ffmpeg: state.ffmpeg.clone(),
+                    stderr: state.ffmpeg_stderr.clone(),
+                    closing: Vec::new(),
+                    log: log.clone(),
+                    checked: Instant::now(),
+                },
+                last: Vec::new(),
+            },
+            None => FrameSink::Ffmpeg { stdin: ff_stdin, last: Vec::new() },
+        }
+    };
+    let recording_session = RecordingSession {
+        session_dir: session.to_string_lossy().into(),
+        output_path: out_file.to_string_lossy().into(),
+        events_file: events_file.as_ref().map(|p| p.to_string_lossy().into()),
+    };
+    let started = Local::now();
+    // events are logged relative to this from here on
+    if let Some(stdin) = state.helper.lock().unwrap().as_mut().and_then(|h| h.stdin.as_mut()) {
+        let epoch = started.timestamp_micros() as f64 / 1e6;
+        writeln!(stdin, "START {}", epoch).and_then(|()| stdin.flush())?;
+    }
+    if let Some(warning) = state.error.lock().unwrap().as_ref() {
+        log.write(format_args!("Warning: {}", warning));
+    }
+    log.write("Recording started");
+    let tail_events = events_file.clone();
+    let window_activity = opts.track_window_activity.then(|| session.join("window_activity.jsonl"));
+    *state.session.lock().unwrap() = Some(SessionInfo {
+        opts: opts.clone(),
+        width: out_w,
+        height: out_h,
+        fps: opts.output_fps(),
+        started,
+        events_file,
+        helper_ready,
+        clicks,
+        cursor,
+        screen_mapping,
+        frame_hashes,
+        frame_timestamps,
+        window_activity: window_activity.clone(),
+    });
+    guard.disarm();
+
+    // set up pipeline
+    let (tx, rx) = bounded::<Vec<u8>>(buffer_size);
+    let alive = state.is_recording.clone();
+    alive.store(true, Ordering::Relaxed);
+    if let Some(path) = tail_events {
+        event_stream::spawn_tail(path, alive.clone(), state.event_subscribers.clone());
+    }
+    if let Some(path) = &window_activity {
+        // the recording goes on without it
+        match window_activity::spawn_tracker(path, alive.clone(), started) {
+            Ok(tracker) => *state.window_tracker.lock().unwrap() = Some(tracker),
+            Err(e) => *state.error.lock().unwrap() = Some(format!("Window activity not tracked: {}", e)),
+        }
+    }
+
+    // FFmpeg input thread (or replay buffer / image writer)
+    let written = Arc::new(AtomicU64::new(0));
+    let frames_written = written.clone();
+    let writer_log = log.clone();
+    let writer_app = app.clone();
+    let writer_alive = alive.clone();
+    let writer_stderr = state.ffmpeg_stderr.clone();
+    let writer = thread::spawn(move || {
+        // Process all frames in the channel, even after stop signal
+        while let Ok(buf) = rx.recv() {
+            // hashed in delivery order, so line n is the nth frame of the video
+            if let Some(h) = hasher.as_mut() {
+                let hashed = if buf.is_empty() { h.repeat() } else { h.record(&buf) };
+                if let Err(e) = hashed {
+                    eprintln!("Frame hashing stopped: {}", e);
+                    writer_log.write(format_args!("Frame hashing stopped: {}", e));
+                    hasher = None;
+                }
+            }
+            if !sink.write(buf) {
+                let message = format!(
+                    "The encoder stopped accepting frames after {}",
+                    frames_written.load(Ordering::Relaxed)
+                );
+                writer_log.write(&message);
+                // a cancel kills ffmpeg on purpose; that's no failure
+                if writer_alive.load(Ordering::Relaxed) {
+                    let output = writer_stderr.lock().unwrap().as_ref().map(StderrTail::text).filter(|t| !t.is_empty());
+                    let reason = match output {
+                        Some(output) => format!("{}:\n{}", message, output),
+                        None => message,
+                    };
+                    emit_failure(&writer_app, "writer", reason);
+                }
+                break;
+            }
+            frames_written.fetch_add(1, Ordering::Relaxed);
+        }
+        // Ensure stdin is properly closed when we're done
+        drop(sink);
+    });
+
+    // Frame grabber thread: owns the capturer so the pacing loop below can
+    // time out instead of blocking forever when the source disappears.
+    // Sends BGRA data, or why a frame couldn't be grabbed.
+    let (frame_tx, frame_rx) = bounded::<Result<Vec<u8>, String>>(1);
+    let mut capturers: Vec<Box<dyn FrameSource>> = capturers.into_iter().map(SourceGuard::into_inner).collect();
+    let grab_alive = alive.clone();
+    if let Some(layout) = layout {
+        composite::spawn(capturers, layout, grab_alive, frame_tx);
+    } else {
+        let mut capturer = capturers.remove(0);
+        let size = sizes[0];
+        let window = opts.window_id.is_some();
+        thread::spawn(move || {
+            while grab_alive.load(Ordering::Relaxed) {
+                let Some(frame) = source::next_frame_checked(capturer.as_mut(), size, window).transpose() else {
+                    break;
+                };
+                let failed = frame.is_err();
+                if frame_tx.send(frame).is_err() || failed {
+                    break;
+                }
+            }
+            capturer.stop();
+        });
+    }
+
+    // Frame capture thread
+    let watchdog_app = app.clone();
+    let capture_alive = alive.clone();
+    let window_id = opts.window_id;
+    let dropped = state.dropped_frames.clone();
+    let captured = state.frames_captured.clone();
+    let channel_full = state.channel_full.clone();
+    for counter in [&dropped, &captured, &channel_full] {
+        counter.store(0, Ordering::Relaxed);
+    }
+    let latest_frame = state.latest_frame.clone();
+    let segments = state.segments.clone();
+    let motion_idle = Arc::new(AtomicBool::new(false));
+    let capture_idle = motion_idle.clone();
+    let capture_log = log;
+    // image sequences just get fewer images, and vfr output stretches the
+    // last frame anyway; other video needs its slots filled
+    let repeat_skipped = frame_format.is_none() && vfr_threshold.is_none();
+    let on_battery = opts
+        .auto_power_saver
+        .then(|| power::watch(state.battery_probe(), POWER_CHECK_INTERVAL, alive.clone()));
+    *latest_frame.lock().unwrap() = None;
+    let capture = thread::spawn(move || {
+        let dt = Duration::from_secs_f64(1.0 / opts.fps as f64);
+        // the limit counts output frames; scale it to captured ones
+        let capture_limit = frame_limit.map(|limit| limit * opts.fps as u64 / opts.output_fps() as u64);
+        let recording_start = Instant::now();
+        let mut frame_idx = 0u32;
+        let mut last_progress = recording_start;
+        let mut last_space_check = recording_start;
+        let mut last_preview: Option<Instant> = None;
+        let max_duration = opts.max_duration_secs.map(Duration::from_secs);
+        let mut stop_reason = None;
+        // vfr: the last frame sent and when, and whether newer identical ones were skipped
+        let mut last_sent: Option<(Vec<u8>, Instant)> = None;
+        let mut held = false;
+        // drop counts as of the last progress tick, to log only what changed
+        let mut logged_drops = (0, 0);
+        let mut last_milestone = recording_start;
+        // auto_power_saver: on battery only every `stride`th frame slot is
+        // captured; the others repeat the frame last sent, so pacing is unchanged
+        let mut stride = 1;
+        let mut repeatable = false;
+        
+        while capture_alive.load(Ordering::Relaxed) {
+            if max_duration.is_some_and(|max| recording_start.elapsed() >= max) {
+                stop_reason = Some(format!(
+                    "Maximum recording duration of {}s reached",
+                    opts.max_duration_secs.unwrap_or_default()
+                ));
+                capture_alive.store(false, Ordering::Relaxed);
+                break;
+            }
+            if capture_limit.is_some_and(|limit| captured.load(Ordering::Relaxed) >= limit) {
+                stop_reason = Some(format!(
+                    "Reached the {}-frame limit for animated output; record mp4 for longer clips",
+                    frame_limit.unwrap_or_default()
+                ));
+                capture_alive.store(false, Ordering::Relaxed);
+                break;
+            }
+            if last_space_check.elapsed() >= SPACE_CHECK_INTERVAL {
+                last_space_check = Instant::now();
+                if let Ok(available) = fs2::available_space(&session) {
+                    if available < MIN_FREE_SPACE {
+                        stop_reason = Some(format!(
+                            "Disk space low ({} bytes free), recording stopped",
+                            available
+                        ));
+                        capture_alive.store(false, Ordering::Relaxed);
+                        break;
+                    }
+                }
+            }
+
+            if let Some(on_battery) = on_battery.as_ref().map(|flag| flag.load(Ordering::Relaxed)) {
+                let wanted = if on_battery { power::frame_stride(opts.fps) } else { 1 };
+                if wanted != stride {
+                    stride = wanted;
+                    let capture_fps = opts.fps as f64 / stride as f64;
+                    capture_log.write(format_args!(
+                        "{} power: capturing at {:.1} fps",
+                        if on_battery { "Battery" } else { "External" },
+                        capture_fps
+                    ));
+                    let _ = app.emit("power-saver-changed", PowerSaverChanged { on_battery, capture_fps });
+                }
+            }
+
+            let expected_time = recording_start + dt * frame_idx;
+        
+            let now = Instant::now();
+            if now >= expected_time {
+                if !frame_idx.is_multiple_of(stride) {
+                    // the source keeps delivering at the full rate; the slot's frame
+                    // is dropped here so no backlog builds up behind the grabber
+                    if let Ok(Err(e)) = frame_rx.try_recv() {
+                        emit_failure(&app, "capture", e.clone());
+                        stop_reason = Some(e);
+                        capture_alive.store(false, Ordering::Relaxed);
+                        break;
+                    }
+                    // an empty frame tells the writer to repeat the last one, so
+                    // no pixels are copied for it
+                    if repeat_skipped && repeatable {
+                        match tx.try_send(Vec::new()) {
+                            Ok(()) => {
+                                let index = captured.fetch_add(1, Ordering::Relaxed);
+                                if let Some(t) = timestamps.as_mut() {
+                                    let _ = t.record(index, recording_start.elapsed());
+                                }
+                            }
+                            Err(TrySendError::Disconnected(_)) => {
+                                stop_reason = Some("The encoder stopped accepting frames".to_string());
+                                capture_alive.store(false, Ordering::Relaxed);
+                                break;
+                            }
+                            Err(TrySendError::Full(_)) => {
+                                channel_full.fetch_add(1, Ordering::Relaxed);
+                                dropped.fetch_add(1, Ordering::Relaxed);
+                            }
+                        }
+                    }
+                    frame_idx += 1;
+                    continue;
+                }
+                let buf = match frame_rx.recv_timeout(FRAME_TIMEOUT) {
+                    Ok(Ok(data)) => {
+                        match follower.as_mut().map(follow::CursorFollower::region).or(region) {
+                            Some(r) => crop_bgra(&data, frame_w, r),
+                            None => Some(data),
+                        }
+                    }
+                    Ok(Err(e)) => {
+                        emit_failure(&app, "capture", e.clone());
+                        stop_reason = Some(e);
+                        capture_alive.store(false, Ordering::Relaxed);
+                        break;
+                    }
+                    Err(RecvTimeoutError::Timeout) => {
+                        if window_id.is_some_and(|id| !window_exists(id)) {
+                            stop_reason = Some("The captured window was closed".to_string());
+                            capture_alive.store(false, Ordering::Relaxed);
+                            break;
+                        }
+                        None
+                    }
+                    Err(RecvTimeoutError::Disconnected) => {
+                        let reason = match window_id {
+                            Some(_) => "The captured window is no longer available".to_string(),
+                            None => {
+                                let reason = "The capture source stopped delivering frames".to_string();
+                                emit_failure(&app, "capture", reason.clone());
+                                reason
+                            }
+                        };
+                        stop_reason = Some(reason);
+                        capture_alive.store(false, Ordering::Relaxed);
+                        break;
+                    }
+                };
+                // keep an occasional copy for previews; encoding happens on demand
+                if let Some(b) = &buf {
+                    if last_preview.is_none_or(|t| t.elapsed() >= PREVIEW_INTERVAL) {
+                        last_preview = Some(Instant::now());
+                        *latest_frame.lock().unwrap() = Some((w, h, b.clone()));
+                    }
+                }
+                // motion_only: still frames are left out altogether
+                let gated = match (&mut motion, &buf) {
+                    (Some(gate), Some(b)) => {
+                        let admit = gate.admit(b, Instant::now());
+                        capture_idle.store(gate.is_idle(), Ordering::Relaxed);
+                        !admit
+                    }
+                    _ => false,
+                };
+                // an unchanged frame is skipped, extending the previous one's duration
+                let unchanged = gated || match (vfr_threshold, &buf, &last_sent) {
+                    (Some(threshold), Some(b), Some((prev, at))) => {
+                        at.elapsed() < VFR_MAX_HOLD && !pixels::differs(prev, b, threshold)
+                    }
+                    _ => false,
+                };
+                let compare_copy = buf.as_ref().filter(|_| vfr_threshold.is_some() && !unchanged).cloned();
+                // the frames motion_only held back while confirming the change go first
+                let confirmed = match (&mut motion, unchanged) {
+                    (Some(gate), false) => gate.take_held(),
+                    _ => Vec::new(),
+                };
+                for (seen, frame) in confirmed {
+                    if tx.send_timeout(frame, FRAME_TIMEOUT).is_err() {
+                        dropped.fetch_add(1, Ordering::Relaxed);
+                        continue;
+                    }
+                    let index = captured.fetch_add(1, Ordering::Relaxed);
+                    if let Some(t) = timestamps.as_mut() {
+                        let _ = t.record(index, seen.duration_since(recording_start));
+                    }
+                }
+                if unchanged {
+                    held = true;
+                    // a still stretch motion_only leaves out isn't filled in either
+                    repeatable = false;
+                } else {
+                    match buf.map(|b| tx.try_send(b)) {
+                        Some(Ok(())) => {
+                            let index = captured.fetch_add(1, Ordering::Relaxed);
+                            if let Some(t) = timestamps.as_mut() {
+                                if let Err(e) = t.record(index, recording_start.elapsed()) {
+                                    capture_log.write(format_args!("Frame timestamps stopped: {}", e));
+                                    timestamps = None;
+                                }
+                            }
+                            if index == 0 {
+                                capture_log.write(format_args!(
+                                    "First frame after {:.3}s",
+                                    recording_start.elapsed().as_secs_f64()
+                                ));
+                            }
+                            held = false;
+                            repeatable = true;
+                            last_sent = compare_copy.map(|b| (b, Instant::now()));
+                        }
+                        // the writer has failed and said why; stop and keep what's encoded
+                        Some(Err(TrySendError::Disconnected(_))) => {
+                            stop_reason = Some("The encoder stopped accepting frames".to_string());
+                            capture_alive.store(false, Ordering::Relaxed);
+                            break;
+                        }
+                        // the encoder is behind and the buffer is full
+                        Some(Err(TrySendError::Full(_))) => {
+                            channel_full.fetch_add(1, Ordering::Relaxed);
+                            dropped.fetch_add(1, Ordering::Relaxed);
+                        }
+                        // capture failed
+                        None => {
+                            dropped.fetch_add(1, Ordering::Relaxed);
+                        }
+                    }
+                }
+                frame_idx += 1;
+
+                // push progress to the frontend once per second
+                if last_progress.elapsed() >= Duration::from_secs(1) && capture_alive.load(Ordering::Relaxed) {
+                    last_progress = Instant::now();
+                    let drops = (dropped.load(Ordering::Relaxed), channel_full.load(Ordering::Relaxed));
+                    if drops.0 > logged_drops.0 {
+                        capture_log.write(format_args!(
+                            "Dropped {} frames ({} with the encoder behind)",
+                            drops.0 - logged_drops.0,
+                            drops.1 - logged_drops.1
+                        ));
+                    }
+                    logged_drops = drops;
+                    if last_milestone.elapsed() >= MILESTONE_INTERVAL {
+                        last_milestone = Instant::now();
+                        capture_log.write(format_args!(
+                            "{:.0}s: {} frames captured, {} dropped",
+                            recording_start.elapsed().as_secs_f64(),
+                            captured.load(Ordering::Relaxed),
+                            drops.0
+                        ));
+                    }
+                    let _ = app.emit("recording-progress", RecordingProgress {
+                        elapsed_secs: recording_start.elapsed().as_secs_f64(),
+                        frames_captured: captured.load(Ordering::Relaxed),
+                        file_size: recorded_size(&out_file, &segments.lock().unwrap()),
+                    });
+                }
+            } else {
+                let sleep_time = expected_time - now;
+                thread::sleep(sleep_time);
+            }
+        }
+        // repeat a skipped tail, or the video would end at the last change
+        if let (true, Some((prev, _))) = (held, last_sent) {
+            if tx.send_timeout(prev, FRAME_TIMEOUT).is_ok() {
+                if let Some(t) = timestamps.as_mut() {
+                    let _ = t.record(captured.load(Ordering::Relaxed), recording_start.elapsed());
+                }
+            }
+        }
+        if let Some(Err(e)) = timestamps.map(frame_times::FrameTimestamps::finish) {
+            capture_log.write(format_args!("Frame timestamps incomplete: {}", e));
+        }
+        // dropping tx closes the channel so the writer can drain and exit
+        drop(tx);
+        capture_log.write(format_args!(
+            "Capture ended: {} frames captured, {} dropped",
+            captured.load(Ordering::Relaxed),
+            dropped.load(Ordering::Relaxed)
+        ));
+
+        if let Some(reason) = stop_reason {
+            capture_log.write(format_args!("Stopping: {}", reason));
+            spawn_auto_stop(app, reason);
+        }
+    });
+    *state.capture_thread.lock().unwrap() = Some(capture);
+    *state.writer_thread.lock().unwrap() = Some(writer);
+    spawn_watchdog(watchdog_app, alive, motion_idle, written, state.ffmpeg_stderr.clone());
+
+    *state.started_at.lock().unwrap() = Some(Instant::now());
+    *state.last_options.lock().unwrap() = Some(requested);
+    Ok(recording_session)
+}
+
+#[tauri::command(async)]
+fn stop_recording(state: State<AppState>) -> Result<RecordingResult, RecordingError> {
+    // a pending countdown is cancelled; start_recording notices and bails out
+    if state.counting_down.swap(false, Ordering::Relaxed) {
+        return Err(RecordingError::Cancelled);
+    }
+
+    finish_recording(&state)
+}
+
+#[tauri::command(async)]
+fn cancel_recording(state: State<AppState>) -> Result<(), RecordingError> {
+    // nothing has been created yet while counting down
+    if state.counting_down.swap(false, Ordering::Relaxed) {
+        return Ok(());
+    }
+    let was_recording = state.is_recording.swap(false, Ordering::Relaxed);
+    if !was_recording && state.ffmpeg.lock().unwrap().is_none() {
+        return Err(RecordingError::NotRecording);
+    }
+
+    // kill ffmpeg first so a writer blocked on its stdin fails fast; no clean mux needed
+    if let Some(mut c) = state.ffmpeg.lock().unwrap().take() {
+        let _ = c.kill();
+        let _ = c.wait();
+    }
+    state.ffmpeg_stderr.lock().unwrap().take();
+    stop_helper(&state)?;
+
+    let deadline = Instant::now() + PIPELINE_JOIN_TIMEOUT;
+    join_until(state.capture_thread.lock().unwrap().take(), deadline);
+    join_until(state.writer_thread.lock().unwrap().take(), deadline);
+    join_until(state.window_tracker.lock().unwrap().take(), deadline);
+
+    // discard everything the session produced
+    state.output_file.lock().unwrap().take();
+    state.segments.lock().unwrap().clear();
+    state.session.lock().unwrap().take();
+    state.replay.lock().unwrap().take();
+    state.session_log.lock().unwrap().take();
+    if let Some(dir) = state.output_dir.lock().unwrap().take() {
+        std::fs::remove_dir_all(&dir).map_err(|e| {
+            RecordingError::Io(format!("Failed to delete {}: {}", dir.display(), e))
+        })?;
+    }
+    Ok(())
+}
+
+/// Discard the current take and start again with the same options. The
+/// recording is reported as running throughout, so the frontend never sees a gap.
+#[tauri::command(async)]
+fn restart_recording(app: AppHandle, state: State<AppState>) -> Result<RecordingSession, RecordingError> {
+    let opts = state.last_options.lock().unwrap().clone().ok_or(RecordingError::NotRecording)?;
+    if state.restarting.swap(true, Ordering::Relaxed) {
+        return Err(RecordingError::AlreadyRecording);
+    }
+    let outcome = cancel_recording(state.clone()).and_then(|()| begin_recording(app, state.clone(), opts, None));
+    state.restarting.store(false, Ordering::Relaxed);
+    outcome
+}
+
+/// `start_recording` with the options of `preset`, after applying `overrides`
+/// (any `RecordingOptions` fields). The expanded options are emitted as
+/// `preset-expanded` before capture starts.
+#[tauri::command(async)]
+fn start_recording_with_preset(
+    app: AppHandle,
+    state: State<AppState>,
+    preset: RecordingPreset,
+    overrides: Option<serde_json::Value>,
+) -> Result<RecordingSession, RecordingError> {
+    let opts = preset.expand(overrides)?;
+    let _ = app.emit("preset-expanded", PresetExpanded { preset, options: opts.clone() });
+    start_recording(app, state, opts)
+}
+
+/// Start a recording with `opts` at `start_at_epoch` (UNIX seconds). Only one
+/// start can be pending; the outcome arrives as `recording-schedule-fired`.
+#[tauri::command]
+fn schedule_recording(
+    app: AppHandle,
+    state: State<AppState>,
+    start_at_epoch: f64,
+    opts: RecordingOptions,
+) -> Result<ScheduledRecording, RecordingError> {
+    if state.is_recording.load(Ordering::Relaxed) || state.counting_down.load(Ordering::Relaxed) {
+        return Err(RecordingError::AlreadyRecording);
+    }
+    let now = chrono::Utc::now().timestamp_millis() as f64 / 1000.0;
+    if !start_at_epoch.is_finite() || start_at_epoch < now {
+        return Err(RecordingError::InvalidOptions(format!(
+            "Scheduled start {} is in the past",
+            start_at_epoch
+        )));
+    }
+    let mut scheduled = state.scheduled.lock().unwrap();
+    if scheduled.is_some() {
+        return Err(RecordingError::InvalidOptions("a recording is already scheduled".into()));
+    }
+    static NEXT_ID: AtomicU64 = AtomicU64::new(1);
+    let schedule = ScheduledRecording { id: NEXT_ID.fetch_add(1, Ordering::Relaxed), start_at_epoch };
+    *scheduled = Some(schedule.clone());
+    drop(scheduled);
+    let _ = app.emit("recording-scheduled", schedule.clone());
 
-// -----------------------------------------------------------------------------
-// Configuration structs
-// -----------------------------------------------------------------------------
+    let id = schedule.id;
+    thread::spawn(move || {
+        let state = app.state::<AppState>();
+        loop {
+            // cancelled, possibly followed by a newer schedule
+            if state.scheduled.lock().unwrap().as_ref().is_none_or(|s| s.id != id) {
+                return;
+            }
+            let remaining = start_at_epoch - chrono::Utc::now().timestamp_millis() as f64 / 1000.0;
+            if remaining <= 0.0 {
+                break;
+            }
+            thread::sleep(Duration::from_secs_f64(remaining.min(0.25)));
+        }
+        // taking it under the lock settles a race with cancel_scheduled_recording
+        if state.scheduled.lock().unwrap().take_if(|s| s.id == id).is_none() {
+            return;
+        }
+        let outcome = start_recording(app.clone(), state, opts);
+        let _ = app.emit("recording-schedule-fired", ScheduleFired {
+            id,
+            error: outcome.as_ref().err().cloned(),
+            session: outcome.ok(),
+        });
+    });
+    Ok(schedule)
+}
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
-pub struct RecordingOptions {
-    pub fps: u32,
-    pub show_cursor: bool,
-    pub show_highlight: bool,
-    pub capture_keystrokes: bool,
+/// Drop the pending `schedule_recording` request before it fires.
+#[tauri::command]
+fn cancel_scheduled_recording(state: State<AppState>) -> Result<(), RecordingError> {
+    match state.scheduled.lock().unwrap().take() {
+        Some(_) => Ok(()),
+        None => Err(RecordingError::InvalidOptions("no recording is scheduled".into())),
+    }
 }
 
-#[derive(Debug, Serialize, Deserialize)]
-pub struct RecordingState {
-    pub is_recording: bool,
-    pub duration: u64,
-    pub error: Option<String>,
+/// Pause or resume event logging (e.g. while typing a password) without
+/// stopping the helper or the video.
+#[tauri::command]
+fn toggle_keystroke_capture(app: AppHandle, state: State<AppState>, enabled: bool) -> Result<(), RecordingError> {
+    let mut helper = state.helper.lock().unwrap();
+    let stdin = helper
+        .as_mut()
+        .and_then(|h| h.stdin.as_mut())
+        .ok_or(RecordingError::NotRecording)?;
+    writeln!(stdin, "{}", if enabled { "RESUME" } else { "PAUSE" })
+        .and_then(|()| stdin.flush())
+        .map_err(|e| RecordingError::Helper(format!("failed to signal event capture: {}", e)))?;
+    let _ = app.emit("keystroke-capture-changed", KeystrokeCaptureChanged { enabled });
+    Ok(())
 }
 
-// -----------------------------------------------------------------------------
-// Shared application state
-// -----------------------------------------------------------------------------
+/// Stream input events to `channel` as they're logged, one JSON record per
+/// message, for this and every later recording. Redacted keys and paused
+/// stretches are left out, as in the log. Returns the id for `unsubscribe_events`.
+#[tauri::command]
+fn subscribe_events(state: State<AppState>, channel: Channel<serde_json::Value>) -> u32 {
+    let id = channel.id();
+    state.event_subscribers.lock().unwrap().push(channel);
+    id
+}
 
-struct AppState {
-    is_recording: Arc<AtomicBool>,
-    started_at:   Arc<Mutex<Option<Instant>>>,
-    output_dir:   Arc<Mutex<Option<PathBuf>>>,
-    ffmpeg:       Arc<Mutex<Option<Child>>>,
-    helper:       Arc<Mutex<Option<Child>>>, // helper process for event capture
+#[tauri::command]
+fn unsubscribe_events(state: State<AppState>, id: u32) {
+    state.event_subscribers.lock().unwrap().retain(|channel| channel.id() != id);
 }
 
-// -----------------------------------------------------------------------------
-// Tauri commands
-// -----------------------------------------------------------------------------
+/// Write the current instant-replay buffer to a new file in the session directory.
+#[tauri::command(async)]
+fn save_replay(state: State<AppState>) -> Result<String, RecordingError> {
+    // snapshot under the lock, encode without it so capture keeps running
+    let snapshot = match state.replay.lock().unwrap().as_ref() {
+        Some(replay) => replay.snapshot(),
+        None => return Err(RecordingError::NotRecording),
+    };
+    let dir = state.output_dir.lock().unwrap().clone().ok_or(RecordingError::NotRecording)?;
+    let ext = state
+        .output_file
+        .lock()
+        .unwrap()
+        .as_ref()
+        .and_then(|p| p.extension().map(|e| e.to_string_lossy().into_owned()))
+        .unwrap_or_else(|| "mp4".into());
+    let out = dir.join(format!("replay_{}.{}", Local::now().format("%Y%m%d_%H%M%S"), ext));
+    snapshot.encode(&out)?;
+    Ok(out.to_string_lossy().into())
+}
 
 #[tauri::command]
-fn start_recording(state: State<AppState>, mut opts: RecordingOptions) -> Result<(), String> {
-    if state.is_recording.load(Ordering::Relaxed) {
-        return Err("Recording already running".into());
-    }
+fn take_screenshot(app: AppHandle, mut opts: RecordingOptions) -> Result<String, RecordingError> {
     if !is_supported() {
-        return Err("Screen capture unsupported on this platform".into());
+        return Err(RecordingError::Unsupported);
     }
     if !request_permission() {
-        return Err("Screen-record permission denied".into());
-    }
-
-    // create session directory
-    // let session = PathBuf::from(std::env::var("HOME").unwrap_or(".".into()))
-    //     .join("recordings")
-    //     .join(Local::now().format("%Y%m%d_%H%M%S").to_string());
-    let home_dir = std::env::var("HOME")
-        .or_else(|_| std::env::var("USERPROFILE"))
-        .unwrap_or_else(|_| ".".into());
-    let session = PathBuf::from(home_dir)
-        .join("recordings")
-        .join(Local::now().format("%Y%m%d_%H%M%S").to_string());
-    std::fs::create_dir_all(&session).map_err(|e| e.to_string())?;
-    *state.output_dir.lock().unwrap() = Some(session.clone());
-
-    // spawn helper process for keystrokes/mouse events
-    if opts.capture_keystrokes {
-        let events_file = session.join("events.log");
-        let helper = Command::new("cargo")
-            .current_dir(env!("CARGO_MANIFEST_DIR"))
-            .args(["run", "--example", "event_capture", "--", events_file.to_str().unwrap()])
-            .stdout(Stdio::null())
-            .stderr(Stdio::null())
-            .spawn()
-            .map_err(|e| format!("Failed to spawn event helper: {}", e))?;
-        *state.helper.lock().unwrap() = Some(helper);
+        return Err(RecordingError::PermissionDenied);
     }
 
-    fn measure_max_fps(capturer: &mut Capturer) -> Result<f64, String> {
-        const SAMPLE_FRAMES: usize = 30;
-        let mut times = Vec::new();
-    
-        // warm up
-        capturer.get_next_frame().map_err(|e| e.to_string())?;
-    
-        for _ in 0..SAMPLE_FRAMES {
-            let start = Instant::now();
-            capturer.get_next_frame().map_err(|e| e.to_string())?;
-            let elapsed = start.elapsed().as_secs_f64();
-            times.push(elapsed);
-        }
-    
-        let avg_frame_time = times.iter().sum::<f64>() / times.len() as f64;
-        let fps = 1.0 / avg_frame_time;
-        Ok(fps)
-    }
-    
+    resolve_monitor_name(&app, &mut opts)?;
+    let target = capture_target(&opts)?;
+    let session = create_session_dir(&app, opts.output_dir.as_deref())?;
 
-    // initialize capturer
     let mut capturer = Capturer::build(scap::capturer::Options {
-        fps: opts.fps,
-        target: None,
+        fps: 30,
+        target,
         show_cursor: opts.show_cursor,
-        show_highlight: opts.show_highlight,
+        show_highlight: false,
         output_type: scap::frame::FrameType::BGRAFrame,
         ..Default::default()
-    }).map_err(|e| e.to_string())?;
+    }).map_err(|e| RecordingError::Capture(e.to_string()))?;
     capturer.start_capture();
-    let measured_fps = measure_max_fps(&mut capturer)?;
-    println!("Measured max FPS: {:.2}", measured_fps);
+    let frame = capturer.get_next_frame();
+    capturer.stop_capture();
+    // don't leave an empty session behind if the capture failed
+    if frame.is_err() {
+        let _ = std::fs::remove_dir(&session);
+    }
 
+    let (w, h, mut data) = pixels::to_bgra(frame.map_err(|e| RecordingError::Capture(e.to_string()))?)?;
+    // BGRA -> RGBA
+    for px in data.chunks_exact_mut(4) {
+        px.swap(0, 2);
+    }
+    let img = image::RgbaImage::from_raw(w, h, data)
+        .ok_or_else(|| RecordingError::Capture("frame buffer does not match its reported size".into()))?;
 
-        // Force final fps to minimum of measured_fps and 20
-    opts.fps = opts.fps.min(measured_fps.floor() as u32);
-    println!("Final recording FPS set to {}", opts.fps);
+    let out = session.join(format!("screenshot_{}.png", Local::now().format("%Y%m%d_%H%M%S")));
+    img.save(&out)?;
+    Ok(out.to_string_lossy().into())
+}
 
-    // grab first frame for geometry
-    let first = capturer.get_next_frame().map_err(|e| e.to_string())?;
-    let (w, h) = match &first {
-        Frame::BGRA(f) => (f.width, f.height),
-        _ => return Err("Unexpected frame type".into()),
+#[tauri::command]
+fn get_preview_frame(state: State<AppState>) -> Result<Option<String>, RecordingError> {
+    let Some((w, h, bgra)) = state.latest_frame.lock().unwrap().clone() else {
+        return Ok(None);
     };
+    let rgb: Vec<u8> = bgra.chunks_exact(4).flat_map(|px| [px[2], px[1], px[0]]).collect();
+    let img = image::RgbImage::from_raw(w, h, rgb)
+        .ok_or_else(|| RecordingError::Capture("preview frame has an unexpected size".into()))?;
+    let preview_h = (h as u64 * PREVIEW_WIDTH as u64 / w.max(1) as u64).max(1) as u32;
+    let small = image::imageops::thumbnail(&img, PREVIEW_WIDTH, preview_h);
 
-    // launch ffmpeg
-    let out_file = session.join("output.mp4");
-    let mut ffmpeg = Command::new("ffmpeg")
-        .args(["-y","-f","rawvideo","-pix_fmt","bgra",
-               "-s", &format!("{w}x{h}"),
-               "-r", &opts.fps.to_string(),
-               "-i","-","-c:v","libx264","-preset","ultrafast",
-               "-pix_fmt","yuv420p", out_file.to_str().unwrap()])
-        .stdin(Stdio::piped())
-        .stdout(Stdio::null())
-        .stderr(Stdio::null())
-        .spawn()
-        .map_err(|e| e.to_string())?;
-    let mut ff_stdin = ffmpeg.stdin.take().ok_or("ffmpeg stdin unavailable")?;
-    *state.ffmpeg.lock().unwrap() = Some(ffmpeg);
+    let mut jpeg = Vec::new();
+    image::codecs::jpeg::JpegEncoder::new_with_quality(&mut jpeg, 70)
+        .encode_image(&small)?;
+    Ok(Some(BASE64.encode(jpeg)))
+}
 
-    // set up pipeline
-    let (tx, rx) = bounded::<Vec<u8>>(4);
-    let alive = state.is_recording.clone();
-    alive.store(true, Ordering::Relaxed);
+#[tauri::command]
+fn get_last_recording(app: AppHandle, output_dir: Option<String>) -> Result<Option<LastRecording>, RecordingError> {
+    let root = recordings_root(&app, output_dir.as_deref())?;
+    if !root.is_dir() {
+        return Ok(None);
+    }
+    let mut sessions: Vec<PathBuf> = std::fs::read_dir(&root)?
+        .filter_map(|e| e.ok())
+        .map(|e| e.path())
+        .filter(|p| p.is_dir())
+        .collect();
+    // session names are %Y%m%d_%H%M%S, so the newest sorts last
+    sessions.sort();
 
-    // FFmpeg input thread
-    let ffmpeg_alive = alive.clone();
-    thread::spawn(move || {
-        // Process all frames in the channel, even after stop signal
-        while let Ok(buf) = rx.recv() {
-            if ff_stdin.write_all(&buf).is_err() {
-                break;
-            }
+    let last = sessions.iter().rev().find_map(|dir| Some((dir, session_output(dir)?)));
+    Ok(last.map(|(dir, output)| LastRecording {
+        output_path: output.to_string_lossy().into(),
+        manifest: std::fs::read_to_string(dir.join("manifest.json"))
+            .ok()
+            .and_then(|json| serde_json::from_str(&json).ok()),
+    }))
+}
+
+/// Every finished recording in the recordings folder (`output_dir` or the
+/// default), newest first, for a recordings manager.
+#[tauri::command(async)]
+fn list_recordings(app: AppHandle, output_dir: Option<String>) -> Result<Vec<RecordingEntry>, RecordingError> {
+    library::list(&recordings_root(&app, output_dir.as_deref())?)
+}
+
+/// Delete a recording's session folder and everything in it. Only session
+/// folders (with a manifest or session log) directly inside the recordings
+/// folder can be deleted, and not the one being recorded into.
+#[tauri::command(async)]
+fn delete_recording(
+    app: AppHandle,
+    state: State<AppState>,
+    session_dir: String,
+    output_dir: Option<String>,
+) -> Result<(), RecordingError> {
+    let root = recordings_root(&app, output_dir.as_deref())?;
+    let dir = library::session_in_root(&root, Path::new(&session_dir))?;
+    let running = state.output_dir.lock().unwrap().as_ref().and_then(|d| d.canonicalize().ok());
+    // still being saved counts as recording: ffmpeg may be rewriting the output
+    let busy = state.is_recording.load(Ordering::Relaxed) || state.finishing.load(Ordering::Relaxed);
+    if busy && running.is_some_and(|running| running.starts_with(&dir)) {
+        return Err(RecordingError::AlreadyRecording);
+    }
+    library::delete(&root, &dir)
+}
+
+/// Finalize sessions an earlier run left without a manifest. Also runs once at
+/// startup for the default recordings folder, reporting via `sessions-recovered`.
+#[tauri::command(async)]
+fn recover_sessions(
+    app: AppHandle,
+    state: State<AppState>,
+    output_dir: Option<String>,
+    ffmpeg_path: Option<String>,
+) -> Result<Vec<RecoveredSession>, RecordingError> {
+    // the live session has no manifest yet either, nor one still being saved
+    if state.is_recording.load(Ordering::Relaxed)
+        || state.counting_down.load(Ordering::Relaxed)
+        || state.finishing.load(Ordering::Relaxed)
+    {
+        return Err(RecordingError::AlreadyRecording);
+    }
+    let root = recordings_root(&app, output_dir.as_deref())?;
+    recover::recover(ffmpeg_path.as_deref().unwrap_or(DEFAULT_FFMPEG), &root)
+}
+
+fn no_file_manager(path: &Path, e: tauri_plugin_opener::Error) -> RecordingError {
+    RecordingError::Io(format!("No file manager could open {}: {}", path.display(), e))
+}
+
+/// Open the recordings folder (`output_dir` or the default), creating it first
+/// if nothing has been recorded yet. Returns the folder's path.
+#[tauri::command]
+fn open_recordings_folder(app: AppHandle, output_dir: Option<String>) -> Result<String, RecordingError> {
+    let root = recordings_root(&app, output_dir.as_deref())?;
+    std::fs::create_dir_all(&root)?;
+    app.opener()
+        .open_path(root.to_string_lossy(), None::<&str>)
+        .map_err(|e| no_file_manager(&root, e))?;
+    Ok(root.to_string_lossy().into())
+}
+
+/// Show `path` selected in the file manager. A path that doesn't exist yet
+/// (a recording still in progress, say) opens its parent folder instead.
+#[tauri::command]
+fn reveal_in_folder(app: AppHandle, path: String) -> Result<(), RecordingError> {
+    let path = PathBuf::from(path);
+    if path.exists() {
+        return app.opener().reveal_item_in_dir(&path).map_err(|e| no_file_manager(&path, e));
+    }
+    let parent = path
+        .parent()
+        .filter(|p| !p.as_os_str().is_empty())
+        .ok_or_else(|| RecordingError::InvalidOptions(format!("{} has no parent folder", path.display())))?;
+    std::fs::create_dir_all(parent)?;
+    app.opener()
+        .open_path(parent.to_string_lossy(), None::<&str>)
+        .map_err(|e| no_file_manager(parent, e))
+}
+
+/// Check a `frame_hashes` recording's video against the hashes taken at capture.
+#[tauri::command(async)]
+fn verify_integrity(
+    session_dir: String,
+    ffmpeg_path: Option<String>,
+) -> Result<integrity::IntegrityReport, RecordingError> {
+    integrity::verify(ffmpeg_path.as_deref().unwrap_or(DEFAULT_FFMPEG), Path::new(&session_dir))
+}
+
+/// Give a finished recording a name of the user's choosing, and its session
+/// folder too with `rename_folder`. A taken name gets a " (2)"-style suffix.
+/// Returns the new output path.
+#[tauri::command]
+fn rename_recording(
+    state: State<AppState>,
+    session_dir: String,
+    new_name: String,
+    rename_folder: Option<bool>,
+) -> Result<String, RecordingError> {
+    // the running session's files are still being written, and a take being
+    // appended will be joined onto this one's
+    let dir = PathBuf::from(&session_dir);
+    if state.is_recording.load(Ordering::Relaxed)
+        && state.output_dir.lock().unwrap().as_ref().is_some_and(|running| running.starts_with(&dir))
+    {
+        return Err(RecordingError::AlreadyRecording);
+    }
+    let output = rename::rename(&dir, &new_name, rename_folder.unwrap_or(false))?;
+    Ok(output.to_string_lossy().into())
+}
+
+/// Let the user drag out a capture region on a transparent overlay over display
+/// `monitor_index` (the primary by default), or over the whole desktop with
+/// `all_monitors`. Returns the region in frame pixels, ready for `region`, or
+/// `None` when the user presses Escape or closes the overlay.
+#[tauri::command(async)]
+fn begin_region_selection(
+    app: AppHandle,
+    state: State<AppState>,
+    monitor_index: Option<usize>,
+    all_monitors: Option<bool>,
+) -> Result<Option<(u32, u32, u32, u32)>, RecordingError> {
+    let overlay = region_picker::overlay(monitor_index, all_monitors.unwrap_or(false))?;
+    let (reply, answer) = bounded(1);
+    {
+        let mut pending = state.region_picker.lock().unwrap();
+        if pending.is_some() {
+            return Err(RecordingError::InvalidOptions("A region selection is already open".into()));
         }
-        // Ensure stdin is properly closed when we're done
-        drop(ff_stdin);
-    });
+        *pending = Some(PendingSelection { reply, scale: overlay.scale });
+    }
 
-    // Frame capture thread
-    let capture_alive = alive.clone();
-    thread::spawn(move || {
-        let dt = Duration::from_secs_f64(1.0 / opts.fps as f64);
-        let recording_start = Instant::now();
-        let mut frame_idx = 0u32;
-        
-        while capture_alive.load(Ordering::Relaxed) {
-            let expected_time = recording_start + dt * frame_idx;
-        
-            let now = Instant::now();
-            if now >= expected_time {
-                if let Ok(Frame::BGRA(f)) = capturer.get_next_frame() {
-                    if tx.send(f.data).is_err() {
-                        break;
-                    }
-                }
-                frame_idx += 1;
-            } else {
-                let sleep_time = expected_time - now;
-                thread::sleep(sleep_time);
+    let url = WebviewUrl::App("region-picker.html".into());
+    let window = WebviewWindowBuilder::new(&app, region_picker::WINDOW_LABEL, url)
+        .position(overlay.x, overlay.y)
+        .inner_size(overlay.width, overlay.height)
+        .decorations(false)
+        .transparent(true)
+        .shadow(false)
+        .always_on_top(true)
+        .skip_taskbar(true)
+        .resizable(false)
+        .focused(true)
+        .build();
+    let window = match window {
+        Ok(window) => window,
+        Err(e) => {
+            state.region_picker.lock().unwrap().take();
+            return Err(RecordingError::Io(format!("Could not open the region picker: {}", e)));
+        }
+    };
+    // closing the overlay any other way counts as cancelling
+    let pending = state.region_picker.clone();
+    window.on_window_event(move |event| {
+        if let WindowEvent::Destroyed = event {
+            if let Some(selection) = pending.lock().unwrap().take() {
+                let _ = selection.reply.send(None);
             }
         }
-        // Channel will be closed when tx is dropped
     });
 
-    *state.started_at.lock().unwrap() = Some(Instant::now());
+    let region = answer.recv().unwrap_or(None);
+    let _ = window.close();
+    Ok(region)
+}
+
+/// Called by the region picker overlay with the dragged rectangle, in the
+/// overlay's points, or `None` when the user cancelled.
+#[tauri::command]
+fn finish_region_selection(
+    state: State<AppState>,
+    selection: Option<(f64, f64, f64, f64)>,
+) -> Result<(), RecordingError> {
+    let pending = state
+        .region_picker
+        .lock()
+        .unwrap()
+        .take()
+        .ok_or_else(|| RecordingError::InvalidOptions("No region selection is open".into()))?;
+    let _ = pending.reply.send(selection.and_then(|s| region_picker::to_region(s, pending.scale)));
     Ok(())
 }
 
+/// Delete a session `recover_sessions` reported as unrecoverable.
 #[tauri::command]
-fn stop_recording(state: State<AppState>) -> Result<String, String> {
-    // First, signal threads to stop
-    state.is_recording.store(false, Ordering::Relaxed);
-    
-    // Give time for the pipeline to finish (3 seconds should be enough)
-    std::thread::sleep(std::time::Duration::from_secs(3));
+fn delete_unrecoverable_session(session_dir: String) -> Result<(), RecordingError> {
+    recover::discard(Path::new(&session_dir))
+}
 
-    // kill helper and wait for it to exit
-    if let Some(mut h) = state.helper.lock().unwrap().take() {
-        h.kill().map_err(|e| format!("Failed to kill event capture: {}", e))?;
-        match h.wait() {
-            Ok(status) => {
-                if !status.success() {
-                    eprintln!("Event capture exited with status: {}", status);
-                }
-            }
-            Err(e) => eprintln!("Failed to wait for event capture: {}", e),
-        }
+#[tauri::command]
+fn get_recording_state(state: State<AppState>) -> RecordingState {
+    let elapsed = state.started_at.lock().unwrap().map(|t| t.elapsed()).unwrap_or_default();
+    let media_duration = state.session.lock().unwrap().as_ref().map_or(0.0, |session| {
+        let speed = session.opts.speed_factor().ok().flatten().unwrap_or(1.0);
+        // vfr frames have no fixed duration; the video runs with the clock
+        let secs = if session.opts.vfr {
+            elapsed.as_secs_f64()
+        } else {
+            state.frames_captured.load(Ordering::Relaxed) as f64 / session.opts.fps.max(1) as f64
+        };
+        secs / speed
+    });
+    RecordingState {
+        is_recording: state.is_recording.load(Ordering::Relaxed) || state.restarting.load(Ordering::Relaxed),
+        duration: elapsed.as_secs(),
+        wall_duration: elapsed.as_secs_f64(),
+        media_duration,
+        error: state.error.lock().unwrap().clone(),
+        dropped_frames: state.dropped_frames.load(Ordering::Relaxed),
     }
+}
 
-    // Wait for ffmpeg to finish processing
-    if let Some(mut c) = state.ffmpeg.lock().unwrap().take() {
-        match c.wait() {
-            Ok(status) => {
-                if !status.success() {
-                    eprintln!("FFmpeg exited with status: {}", status);
-                }
-            }
-            Err(e) => {
-                eprintln!("Failed to wait for ffmpeg: {}", e);
-                // If waiting fails, then kill it
-                let _ = c.kill();
+#[tauri::command]
+fn list_windows() -> Vec<WindowInfo> {
+    get_all_targets()
+        .into_iter()
+        .filter_map(|t| match t {
+            Target::Window(w) => Some(WindowInfo { id: w.id, title: w.title }),
+            Target::Display(_) => None,
+        })
+        .collect()
+}
+
+#[tauri::command]
+fn list_cameras(ffmpeg_path: Option<String>) -> Vec<CameraInfo> {
+    devices::list_cameras(ffmpeg_path.as_deref().unwrap_or(DEFAULT_FFMPEG))
+}
+
+#[tauri::command]
+fn list_monitors(app: AppHandle) -> Result<Vec<MonitorInfo>, RecordingError> {
+    let screens = Screen::all().map_err(|e| RecordingError::Capture(e.to_string()))?;
+    let names = monitor_names(&app, &screens);
+    Ok(screens
+        .iter()
+        .zip(names)
+        .enumerate()
+        .map(|(index, (s, name))| {
+            let d = s.display_info;
+            MonitorInfo {
+                index,
+                name,
+                id: d.id,
+                width: d.width,
+                height: d.height,
+                x: d.x,
+                y: d.y,
+                scale_factor: d.scale_factor,
+                refresh_rate: d.frequency,
+                is_primary: d.is_primary,
             }
-        }
+        })
+        .collect())
+}
+
+/// scap's capture API on this platform and the frame layouts it hands out.
+fn capture_backend() -> (&'static str, &'static [&'static str]) {
+    match std::env::consts::OS {
+        "macos" => ("ScreenCaptureKit", &["YUV (NV12)", "BGRA"]),
+        "windows" => ("Windows.Graphics.Capture", &["BGRA"]),
+        "linux" => ("PipeWire (xdg-desktop-portal)", &["BGRx", "RGBx", "XBGR", "BGRA", "RGB"]),
+        _ => ("none", &[]),
     }
+}
 
-    // return path
-    let out = state.output_dir.lock().unwrap().clone().unwrap().join("output.mp4");
-    
-    // Verify the file exists and has size > 0
-    match std::fs::metadata(&out) {
-        Ok(metadata) => {
-            if metadata.len() == 0 {
-                return Err("Recording failed: output file is empty".into());
+/// Capture diagnostics for bug reports; starts nothing and prompts for nothing.
+#[tauri::command]
+fn get_capture_info(app: AppHandle) -> Result<CaptureInfo, RecordingError> {
+    let (backend, frame_types) = capture_backend();
+    Ok(CaptureInfo {
+        backend: backend.into(),
+        supported: is_supported(),
+        screen_recording: permissions::check().screen_recording,
+        frame_types: frame_types.iter().map(|t| t.to_string()).collect(),
+        requested_frame_type: "BGRA".into(),
+        displays: list_monitors(app)?,
+    })
+}
+
+/// Roughly how much a minute of recording with `opts` takes on disk, for
+/// warning before a long recording. Worked out from the display layout and
+/// the encoder settings; nothing is captured or encoded.
+#[tauri::command]
+fn estimate_size_per_minute(app: AppHandle, mut opts: RecordingOptions) -> Result<SizeEstimate, RecordingError> {
+    resolve_monitor_name(&app, &mut opts)?;
+    opts.apply_rate_overrides();
+    let (frame_w, frame_h) = expected_frame_size(&opts)?;
+    let (w, h) = match (opts.region, opts.follow_cursor()?) {
+        (Some(requested), _) => clamp_region(requested, frame_w, frame_h).map(|(_, _, w, h)| (w, h)).ok_or_else(|| {
+            RecordingError::InvalidOptions(format!(
+                "Region {:?} lies outside the {}x{} frame",
+                requested, frame_w, frame_h
+            ))
+        })?,
+        (None, Some((fw, fh))) => (fw.min(frame_w) & !1, fh.min(frame_h) & !1),
+        (None, None) => (frame_w, frame_h),
+    };
+    let (width, height) = output_geometry(&opts, (w, h), display_scale_factor(&opts))?.size;
+    Ok(SizeEstimate {
+        bytes_per_minute: (estimate::bytes_per_sec(&opts, (width, height))? * 60.0) as u64,
+        width,
+        height,
+        fps: opts.output_fps(),
+    })
+}
+
+#[tauri::command]
+fn check_ffmpeg(ffmpeg_path: Option<String>) -> FfmpegStatus {
+    let bin = ffmpeg_path.as_deref().unwrap_or(DEFAULT_FFMPEG);
+    match Command::new(bin).arg("-version").stdin(Stdio::null()).stderr(Stdio::null()).output() {
+        Ok(out) if out.status.success() => FfmpegStatus {
+            found: true,
+            // first line looks like "ffmpeg version 6.1.1 Copyright (c) ..."
+            version: String::from_utf8_lossy(&out.stdout).lines().next().map(str::to_owned),
+            error: None,
+        },
+        Ok(out) => FfmpegStatus {
+            found: false,
+            version: None,
+            error: Some(format!("{} -version exited with status {}", bin, out.status)),
+        },
+        Err(e) => FfmpegStatus {
+            found: false,
+            version: None,
+            error: Some(format!("{}: {}", bin, e)),
+        },
+    }
+}
+
+/// Seconds each of `samples` frames took to arrive from `source`, timed after
+/// a warm-up frame, and the frames' size.
+fn time_frames(source: &mut SourceGuard, samples: usize) -> Result<((u32, u32), Vec<f64>), RecordingError> {
+    let (w, h, _) = source.require_frame()?;
+    let mut times = Vec::with_capacity(samples);
+    for _ in 0..samples {
+        let start = Instant::now();
+        source.require_frame()?;
+        times.push(start.elapsed().as_secs_f64());
+    }
+    Ok(((w, h), times))
+}
+
+/// Time how fast display `monitor_index` (the primary by default) delivers
+/// frames, over `sample_frames` frames with the capturer asking for `fps`
+/// (`MAX_FPS` unless given), to pick a realistic fps before recording.
+#[tauri::command(async)]
+fn benchmark_capture(
+    state: State<AppState>,
+    monitor_index: Option<usize>,
+    sample_frames: Option<usize>,
+    fps: Option<u32>,
+) -> Result<CaptureBenchmark, RecordingError> {
+    // a running capture would compete for the same frames
+    ensure_idle(&state)?;
+    if !is_supported() {
+        return Err(RecordingError::Unsupported);
+    }
+    if !request_permission() {
+        return Err(RecordingError::PermissionDenied);
+    }
+    let samples = sample_frames.unwrap_or(DEFAULT_BENCHMARK_FRAMES);
+    if !(2..=MAX_BENCHMARK_FRAMES).contains(&samples) {
+        return Err(RecordingError::InvalidOptions(format!(
+            "Invalid sample_frames {}: must be between 2 and {}",
+            samples, MAX_BENCHMARK_FRAMES
+        )));
+    }
+    let requested_fps = fps.unwrap_or(MAX_FPS);
+    if requested_fps == 0 || requested_fps > MAX_FPS {
+        return Err(RecordingError::InvalidOptions(format!(
+            "Invalid fps {}: must be between 1 and {}",
+            requested_fps, MAX_FPS
+        )));
+    }
+    let target = monitor_index.map(display_target).transpose()?;
+
+    let mut capturer = Capturer::build(scap::capturer::Options {
+        fps: requested_fps,
+        target,
+        output_type: scap::frame::FrameType::BGRAFrame,
+        output_resolution: scap::capturer::Resolution::Captured,
+        ..Default::default()
+    }).map_err(|e| RecordingError::Capture(e.to_string()))?;
+    capturer.start_capture();
+    // stopped when dropped, also if a frame fails midway
+    let mut capturer = SourceGuard::new(Box::new(capturer));
+    let ((width, height), mut times) = time_frames(&mut capturer, samples)?;
+    drop(capturer);
+
+    times.sort_by(f64::total_cmp);
+    let total: f64 = times.iter().sum();
+    let p95 = times[(times.len() * 95).div_ceil(100) - 1];
+    Ok(CaptureBenchmark {
+        width,
+        height,
+        requested_fps,
+        fps: times.len() as f64 / total,
+        frames: times.len(),
+        min_ms: times[0] * 1000.0,
+        avg_ms: total / times.len() as f64 * 1000.0,
+        max_ms: times[times.len() - 1] * 1000.0,
+        p95_ms: p95 * 1000.0,
+    })
+}
+
+/// Capture the main display for about a second, encode it to `out` and
+/// return the resulting file size.
+fn test_capture(bin: &str, out: &Path) -> Result<u64, RecordingError> {
+    const TEST_FPS: u32 = 10;
+    let mut capturer = Capturer::build(scap::capturer::Options {
+        fps: TEST_FPS,
+        output_type: scap::frame::FrameType::BGRAFrame,
+        ..Default::default()
+    }).map_err(|e| RecordingError::Capture(e.to_string()))?;
+    capturer.start_capture();
+    let mut capturer = SourceGuard::new(Box::new(capturer));
+
+    let mut frames = Vec::new();
+    let started = Instant::now();
+    while started.elapsed() < Duration::from_secs(1) {
+        frames.push(capturer.require_frame()?);
+    }
+    drop(capturer);
+    let Some(&(w, h, _)) = frames.first() else {
+        return Err(RecordingError::Capture("no frames were delivered".into()));
+    };
+
+    let mut child = Command::new(bin)
+        .args(["-y", "-loglevel", "error", "-f", "rawvideo", "-pix_fmt", "bgra",
+               "-s", &format!("{w}x{h}"),
+               "-r", &TEST_FPS.to_string(),
+               "-i", "-",
+               "-c:v", "libx264", "-preset", "ultrafast", "-pix_fmt", "yuv420p"])
+        .arg(out)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| ffmpeg::spawn_error(bin, e))?;
+    if let Some(mut stdin) = child.stdin.take() {
+        // a display that changed size mid-test would corrupt the raw stream
+        for (_, _, data) in frames.iter().filter(|(fw, fh, _)| (*fw, *fh) == (w, h)) {
+            if stdin.write_all(data).is_err() {
+                break;
             }
         }
-        Err(e) => {
-            return Err(format!("Recording failed: {}", e));
-        }
     }
+    let output = child.wait_with_output()?;
+    if !output.status.success() {
+        return Err(RecordingError::FfmpegFailed(format!(
+            "test encode exited with {}: {}",
+            output.status,
+            String::from_utf8_lossy(&output.stderr).trim()
+        )));
+    }
+    match std::fs::metadata(out).map(|m| m.len()) {
+        Ok(0) | Err(_) => Err(RecordingError::FfmpegFailed("test encode produced an empty file".into())),
+        Ok(size) => Ok(size),
+    }
+}
+
+/// Check everything a recording depends on, including a one-second test
+/// capture and encode, without touching the recordings directory.
+#[tauri::command(async)]
+fn self_test(
+    state: State<AppState>,
+    ffmpeg_path: Option<String>,
+    capture_keystrokes: bool,
+) -> Result<SelfTestReport, RecordingError> {
+    if state.is_recording.load(Ordering::Relaxed) || state.counting_down.load(Ordering::Relaxed) {
+        return Err(RecordingError::AlreadyRecording);
+    }
+    let mut checks = Vec::new();
+    let mut check = |name: &str, result: Result<Option<String>, String>| {
+        let passed = result.is_ok();
+        checks.push(SelfTestCheck {
+            name: name.into(),
+            passed,
+            detail: result.unwrap_or_else(Some),
+        });
+        passed
+    };
 
-    Ok(out.to_string_lossy().into())
+    let supported = check(
+        "platform_supported",
+        if is_supported() { Ok(None) } else { Err("screen capture is not supported on this system".into()) },
+    );
+    let permissions = permissions::check();
+    let screen = check(
+        "screen_recording_permission",
+        match permissions.screen_recording {
+            PermissionStatus::Granted => Ok(None),
+            _ => Err("screen recording permission has not been granted".into()),
+        },
+    );
+    let bin = ffmpeg_path.as_deref().unwrap_or(DEFAULT_FFMPEG);
+    let ffmpeg = check_ffmpeg(ffmpeg_path.clone());
+    let ffmpeg = check(
+        "ffmpeg",
+        if ffmpeg.found {
+            Ok(ffmpeg.version)
+        } else {
+            Err(ffmpeg.error.unwrap_or_else(|| format!("{} not found", bin)))
+        },
+    );
+
+    let test_result = if supported && screen && ffmpeg {
+        let out = std::env::temp_dir().join(format!("screenrec-self-test-{}.mp4", std::process::id()));
+        let result = test_capture(bin, &out)
+            .map(|size| Some(format!("encoded {} bytes", size)))
+            .map_err(|e| e.to_string());
+        let _ = std::fs::remove_file(&out);
+        result
+    } else {
+        Err("skipped: an earlier check failed".into())
+    };
+    check("test_recording", test_result);
+
+    if capture_keystrokes {
+        check(
+            "input_monitoring_permission",
+            match permissions.input_monitoring {
+                PermissionStatus::Granted => Ok(None),
+                _ => Err("input monitoring permission is needed for keystroke capture".into()),
+            },
+        );
+    }
+
+    Ok(SelfTestReport { passed: checks.iter().all(|c| c.passed), checks })
+}
+
+/// Join finished recordings, in order, into `output`. Matching recordings are
+/// stream-copied; otherwise they're re-encoded at the first one's resolution,
+/// with a warning left in the recording state.
+#[tauri::command(async)]
+fn concat_recordings(
+    state: State<AppState>,
+    paths: Vec<String>,
+    output: String,
+    ffmpeg_path: Option<String>,
+) -> Result<String, RecordingError> {
+    let bin = ffmpeg_path.as_deref().unwrap_or(DEFAULT_FFMPEG);
+    let inputs: Vec<PathBuf> = paths.iter().map(PathBuf::from).collect();
+    let output = PathBuf::from(output);
+    if let Some(warning) = concat::concat(bin, &inputs, &output)? {
+        *state.error.lock().unwrap() = Some(warning);
+    }
+    Ok(output.to_string_lossy().into())
+}
+
+/// Write the `start_secs..end_secs` part of a recording to `output`, stream-copied
+/// when `start_secs` is on a keyframe and re-encoded for frame accuracy otherwise.
+#[tauri::command(async)]
+fn trim_recording(
+    input: String,
+    start_secs: f64,
+    end_secs: f64,
+    output: String,
+    ffmpeg_path: Option<String>,
+) -> Result<String, RecordingError> {
+    let bin = ffmpeg_path.as_deref().unwrap_or(DEFAULT_FFMPEG);
+    let output = PathBuf::from(output);
+    trim::trim(bin, Path::new(&input), start_secs, end_secs, &output)?;
+    Ok(output.to_string_lossy().into())
+}
+
+/// Render a finished session's mouse movement as `mouse_heatmap.png`: a density
+/// heatmap (`style` "heatmap", the default) or the cursor's path ("trail").
+#[tauri::command(async)]
+fn export_mouse_heatmap(session_dir: String, style: Option<String>) -> Result<String, RecordingError> {
+    let style = heatmap::Style::parse(style.as_deref())?;
+    let path = heatmap::export(Path::new(&session_dir), style)?;
+    Ok(path.to_string_lossy().into())
+}
+
+/// Hardware H.264 encoders that this ffmpeg lists and can actually open.
+#[tauri::command(async)]
+fn list_encoders(ffmpeg_path: Option<String>) -> Vec<String> {
+    let bin = ffmpeg_path.as_deref().unwrap_or(DEFAULT_FFMPEG);
+    ffmpeg::list_encoders(bin)
+        .into_iter()
+        .filter(|e| HW_H264_ENCODERS.contains(&e.as_str()) && ffmpeg::encoder_works(bin, e))
+        .collect()
 }
 
 #[tauri::command]
-fn get_recording_state(state: State<AppState>) -> RecordingState {
-    RecordingState {
-        is_recording: state.is_recording.load(Ordering::Relaxed),
-        duration: state.started_at.lock().unwrap().map(|t| t.elapsed().as_secs()).unwrap_or(0),
-        error: None,
+fn register_hotkey(app: AppHandle, state: State<AppState>, combo: String, opts: RecordingOptions) -> Result<(), RecordingError> {
+    let shortcuts = app.global_shortcut();
+    let mut current = state.hotkey.lock().unwrap();
+    if current.as_deref() == Some(combo.as_str()) || shortcuts.is_registered(combo.as_str()) {
+        return Err(RecordingError::Hotkey(format!("{} is already registered", combo)));
     }
+
+    shortcuts
+        .on_shortcut(combo.as_str(), move |app, _shortcut, event| {
+            if event.state != ShortcutState::Pressed {
+                return;
+            }
+            // start/stop block on the pipeline, keep them off the event loop
+            let app = app.clone();
+            let opts = opts.clone();
+            thread::spawn(move || toggle_recording(&app, opts));
+        })
+        .map_err(|e| {
+            RecordingError::Hotkey(format!(
+                "Could not register {} (it may be in use by another application): {}",
+                combo, e
+            ))
+        })?;
+
+    // only one toggle hotkey at a time
+    if let Some(old) = current.replace(combo) {
+        let _ = shortcuts.unregister(old.as_str());
+    }
+    Ok(())
+}
+
+#[tauri::command]
+fn unregister_hotkey(app: AppHandle, state: State<AppState>) -> Result<(), RecordingError> {
+    let Some(combo) = state.hotkey.lock().unwrap().take() else {
+        return Ok(());
+    };
+    app.global_shortcut()
+        .unregister(combo.as_str())
+        .map_err(|e| RecordingError::Hotkey(format!("Could not unregister {}: {}", combo, e)))
+}
+
+/// Current screen-recording and input-monitoring permission, without prompting.
+#[tauri::command]
+fn check_permissions() -> Permissions {
+    permissions::check()
 }
 
 #[tauri::command]
@@ -273,19 +4388,72 @@ fn get_platform() -> String { std::env::consts::OS.into() }
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
     tauri::Builder::default()
-        .manage(AppState {
-            is_recording: Arc::new(AtomicBool::new(false)),
-            started_at:   Arc::new(Mutex::new(None)),
-            output_dir:   Arc::new(Mutex::new(None)),
-            ffmpeg:       Arc::new(Mutex::new(None)),
-            helper:       Arc::new(Mutex::new(None)),
+        .plugin(tauri_plugin_global_shortcut::Builder::new().build())
+        .plugin(tauri_plugin_opener::init())
+        .manage(AppState::default())
+        .setup(|app| {
+            if let Err(e) = tray::install(app.handle()) {
+                eprintln!("System tray unavailable, using window controls only: {}", e);
+            }
+            shutdown::handle_signals(app.handle().clone());
+            let app = app.handle().clone();
+            thread::spawn(move || match recordings_root(&app, None).and_then(|root| recover::recover(DEFAULT_FFMPEG, &root)) {
+                Ok(sessions) if !sessions.is_empty() => {
+                    let _ = app.emit("sessions-recovered", sessions);
+                }
+                Ok(_) => {}
+                Err(e) => eprintln!("Session recovery failed: {}", e),
+            });
+            Ok(())
         })
         .invoke_handler(tauri::generate_handler![
             start_recording,
+            start_recording_with_preset,
             stop_recording,
+            cancel_recording,
+            restart_recording,
+            schedule_recording,
+            toggle_keystroke_capture,
+            subscribe_events,
+            unsubscribe_events,
+            cancel_scheduled_recording,
+            self_test,
+            concat_recordings,
+            trim_recording,
+            export_mouse_heatmap,
+            recover_sessions,
+            list_recordings,
+            delete_recording,
+            delete_unrecoverable_session,
+            verify_integrity,
+            rename_recording,
+            begin_region_selection,
+            finish_region_selection,
+            open_recordings_folder,
+            reveal_in_folder,
+            save_replay,
             get_recording_state,
+            get_last_recording,
             get_platform,
+            check_permissions,
+            list_monitors,
+            get_capture_info,
+            benchmark_capture,
+            estimate_size_per_minute,
+            list_windows,
+            list_cameras,
+            take_screenshot,
+            get_preview_frame,
+            check_ffmpeg,
+            list_encoders,
+            register_hotkey,
+            unregister_hotkey,
         ])
-        .run(tauri::generate_context!())
-        .expect("tauri run failed");
+        .build(tauri::generate_context!())
+        .expect("tauri build failed")
+        .run(|app, event| {
+            if let RunEvent::ExitRequested { api, .. } = event {
+                shutdown::on_exit_requested(app, &api);
+            }
+        });
 }
@@ -0,0 +1,92 @@
+//! The recordings manager: finished sessions under the recordings root, and
+//! deleting them. Only folders directly inside the root are touched, so a
+//! crafted path can't reach anything else.
+
+use std::path::{Path, PathBuf};
+
+use serde::Serialize;
+
+use crate::{recorded_size, RecordingError, RecordingManifest};
+
+/// One finished recording, as listed by `list_recordings`.
+#[derive(Debug, Serialize, Clone)]
+pub struct RecordingEntry {
+    pub session_dir: String,
+    pub output_path: String,
+    pub started_at: String, // RFC 3339, local time
+    pub duration_secs: f64,
+    /// Bytes on disk for the output, every segment included.
+    pub size_bytes: u64,
+    pub thumbnail_path: Option<String>,
+    pub recovered: bool,
+}
+
+fn entry(dir: &Path) -> Option<(f64, RecordingEntry)> {
+    let manifest: RecordingManifest = std::fs::read_to_string(dir.join("manifest.json"))
+        .ok()
+        .and_then(|json| serde_json::from_str(&json).ok())?;
+    let output = PathBuf::from(&manifest.output_path);
+    let segments: Vec<PathBuf> = manifest.segments.iter().map(PathBuf::from).collect();
+    let size_bytes = recorded_size(&output, &segments);
+    if size_bytes == 0 {
+        return None;
+    }
+    let thumbnail_path = manifest.thumbnail_file.filter(|p| Path::new(p).is_file());
+    Some((
+        manifest.started_epoch,
+        RecordingEntry {
+            session_dir: dir.to_string_lossy().into(),
+            output_path: manifest.output_path,
+            started_at: manifest.started_at,
+            duration_secs: manifest.duration_secs,
+            size_bytes,
+            thumbnail_path,
+            recovered: manifest.recovered,
+        },
+    ))
+}
+
+/// Every session in `root` with a readable manifest and output, newest first.
+/// Folders still recording, awaiting recovery or otherwise broken are left out.
+pub fn list(root: &Path) -> Result<Vec<RecordingEntry>, RecordingError> {
+    if !root.is_dir() {
+        return Ok(Vec::new());
+    }
+    let mut entries: Vec<(f64, RecordingEntry)> = std::fs::read_dir(root)?
+        .filter_map(|e| e.ok())
+        .map(|e| e.path())
+        .filter(|p| p.is_dir())
+        .filter_map(|dir| entry(&dir))
+        .collect();
+    entries.sort_by(|a, b| b.0.total_cmp(&a.0));
+    Ok(entries.into_iter().map(|(_, entry)| entry).collect())
+}
+
+/// `session_dir` resolved, if it is a folder directly inside `root`.
+/// Symlinks and `..` are resolved first, so neither can lead outside.
+pub fn session_in_root(root: &Path, session_dir: &Path) -> Result<PathBuf, RecordingError> {
+    let outside = || {
+        RecordingError::InvalidOptions(format!("{} is not a recording in {}", session_dir.display(), root.display()))
+    };
+    let root = root.canonicalize().map_err(|_| outside())?;
+    let dir = session_dir.canonicalize().map_err(|_| outside())?;
+    if !dir.is_dir() || dir.parent() != Some(root.as_path()) {
+        return Err(outside());
+    }
+    Ok(dir)
+}
+
+// files only the recorder writes; a folder holding neither isn't a session
+const SESSION_MARKERS: &[&str] = &["manifest.json", "session.log"];
+
+/// Delete the session folder `session_dir` in `root` with everything in it.
+/// The root comes from the caller too, so the folder must also look like a
+/// session; otherwise any folder's parent would pass as a recordings root.
+pub fn delete(root: &Path, session_dir: &Path) -> Result<(), RecordingError> {
+    let dir = session_in_root(root, session_dir)?;
+    if !SESSION_MARKERS.iter().any(|marker| dir.join(marker).is_file()) {
+        return Err(RecordingError::InvalidOptions(format!("{} is not a recording session", dir.display())));
+    }
+    std::fs::remove_dir_all(&dir)
+        .map_err(|e| RecordingError::Io(format!("Failed to delete {}: {}", dir.display(), e)))
+}
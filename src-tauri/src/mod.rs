@@ -1 +0,0 @@
-pub mod recording; 
\ No newline at end of file
@@ -0,0 +1,110 @@
+//! Gating for `motion_only`: frames pass while the screen is changing and stop
+//! once it has been still for a moment. Idle stretches are left out of the
+//! output entirely rather than frozen, so the video is just the activity.
+//! Cutting them means video time no longer follows the wall clock, so
+//! nothing that maps events onto the video can be combined with it.
+
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+use crate::pixels;
+
+// keep recording this long after the last change, so activity doesn't end abruptly
+const ACTIVITY_TAIL: Duration = Duration::from_secs(1);
+// most frame data held while a change is confirmed; past it the oldest go
+const MAX_HELD_BYTES: usize = 256 * 1024 * 1024;
+
+pub struct MotionGate {
+    /// Fraction of pixels (0–1) that must differ for a frame to count as changed.
+    threshold: f64,
+    /// How long a change must persist before recording resumes.
+    min_activity: Duration,
+    /// The last frame passed on; while idle, the still screen changes are measured against.
+    reference: Option<Vec<u8>>,
+    active: bool,
+    /// While active: when the screen last changed.
+    last_change: Instant,
+    /// While idle: since when the screen has differed from `reference`.
+    changed_since: Option<Instant>,
+    /// While idle: the frames since `changed_since`, recorded once the change
+    /// lasts `min_activity` so the start of the activity isn't lost.
+    held: VecDeque<(Instant, Vec<u8>)>,
+    held_bytes: usize,
+}
+
+impl MotionGate {
+    pub fn new(threshold: f64, min_activity: Duration) -> Self {
+        MotionGate {
+            threshold,
+            min_activity,
+            reference: None,
+            active: false,
+            last_change: Instant::now(),
+            changed_since: None,
+            held: VecDeque::new(),
+            held_bytes: 0,
+        }
+    }
+
+    pub fn is_idle(&self) -> bool {
+        !self.active
+    }
+
+    /// Whether `frame` should be recorded. The first frame always is, so the
+    /// output opens on the screen as it was. When it's admitted because a
+    /// change has lasted long enough, the frames of that change come first:
+    /// see `take_held`.
+    pub fn admit(&mut self, frame: &[u8], now: Instant) -> bool {
+        let Some(reference) = &self.reference else {
+            self.reference = Some(frame.to_vec());
+            return true;
+        };
+        let changed = pixels::differs(reference, frame, self.threshold);
+        if self.active {
+            if changed {
+                self.last_change = now;
+            } else if now.duration_since(self.last_change) >= ACTIVITY_TAIL {
+                self.active = false;
+                return false;
+            }
+        } else {
+            // measured against the frame the screen went still on, so a flicker
+            // that reverts never adds up to min_activity
+            if !changed {
+                self.changed_since = None;
+                self.held.clear();
+                self.held_bytes = 0;
+                return false;
+            }
+            let since = *self.changed_since.get_or_insert(now);
+            if now.duration_since(since) < self.min_activity {
+                self.held_bytes += frame.len();
+                self.held.push_back((now, frame.to_vec()));
+                while self.held_bytes > MAX_HELD_BYTES {
+                    let Some((_, oldest)) = self.held.pop_front() else { break };
+                    self.held_bytes -= oldest.len();
+                }
+                return false;
+            }
+            self.active = true;
+            self.changed_since = None;
+            self.last_change = now;
+        }
+        // reuses the allocation; this runs for every recorded frame
+        let reference = self.reference.get_or_insert_with(Vec::new);
+        reference.clear();
+        reference.extend_from_slice(frame);
+        true
+    }
+
+    /// The frames held back while the change just admitted was confirmed,
+    /// oldest first with the time each was seen, to record before the frame
+    /// that confirmed it. Empty at any other time.
+    pub fn take_held(&mut self) -> Vec<(Instant, Vec<u8>)> {
+        if !self.active {
+            return Vec::new();
+        }
+        self.held_bytes = 0;
+        self.held.drain(..).collect()
+    }
+}
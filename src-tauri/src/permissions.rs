@@ -0,0 +1,62 @@
+//! Permission status queries that don't trigger any prompt.
+
+use serde::Serialize;
+
+#[derive(Debug, Serialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum PermissionStatus {
+    Granted,
+    Denied,
+    NotDetermined,
+}
+
+/// Returned by `check_permissions`.
+#[derive(Debug, Serialize)]
+pub struct Permissions {
+    pub screen_recording: PermissionStatus,
+    /// Needed by the event helper for keystroke/mouse capture.
+    pub input_monitoring: PermissionStatus,
+}
+
+#[cfg(target_os = "macos")]
+mod platform {
+    use super::PermissionStatus;
+
+    const IOHID_REQUEST_TYPE_LISTEN_EVENT: u32 = 1;
+
+    #[link(name = "IOKit", kind = "framework")]
+    extern "C" {
+        fn IOHIDCheckAccess(request_type: u32) -> u32;
+    }
+
+    /// Input Monitoring (TCC `ListenEvent`) status, as the CGEventTap behind rdev needs it.
+    pub fn input_monitoring() -> PermissionStatus {
+        // SAFETY: plain C call with no pointers; available since macOS 10.15
+        match unsafe { IOHIDCheckAccess(IOHID_REQUEST_TYPE_LISTEN_EVENT) } {
+            0 => PermissionStatus::Granted,
+            1 => PermissionStatus::Denied,
+            _ => PermissionStatus::NotDetermined,
+        }
+    }
+}
+
+#[cfg(not(target_os = "macos"))]
+mod platform {
+    use super::PermissionStatus;
+
+    pub fn input_monitoring() -> PermissionStatus {
+        PermissionStatus::Granted
+    }
+}
+
+pub fn check() -> Permissions {
+    let screen_recording = if !scap::is_supported() {
+        PermissionStatus::Denied
+    } else if std::env::consts::OS != "macos" || scap::has_permission() {
+        PermissionStatus::Granted
+    } else {
+        // macOS only offers a yes/no preflight, so never-asked also reads as denied
+        PermissionStatus::Denied
+    };
+    Permissions { screen_recording, input_monitoring: platform::input_monitoring() }
+}
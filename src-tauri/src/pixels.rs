@@ -0,0 +1,112 @@
+//! Converting whatever pixel format the capture backend delivers into the
+//! tightly packed BGRA the rest of the pipeline (and ffmpeg's `-pix_fmt bgra`) expects.
+
+use scap::frame::{Frame, YUVFrame};
+
+use crate::RecordingError;
+
+/// Convert a captured frame to packed BGRA, returning `(width, height, data)`.
+pub fn to_bgra(frame: Frame) -> Result<(u32, u32, Vec<u8>), RecordingError> {
+    match frame {
+        Frame::BGRA(f) => packed(f.width, f.height, f.data, 4, "BGRA", |px| [px[0], px[1], px[2], px[3]]),
+        Frame::BGRx(f) => packed(f.width, f.height, f.data, 4, "BGRx", |px| [px[0], px[1], px[2], 255]),
+        Frame::BGR0(f) => packed(f.width, f.height, f.data, 4, "BGR0", |px| [px[0], px[1], px[2], 255]),
+        Frame::RGBx(f) => packed(f.width, f.height, f.data, 4, "RGBx", |px| [px[2], px[1], px[0], 255]),
+        Frame::XBGR(f) => packed(f.width, f.height, f.data, 4, "XBGR", |px| [px[1], px[2], px[3], 255]),
+        Frame::RGB(f) => packed(f.width, f.height, f.data, 3, "RGB", |px| [px[2], px[1], px[0], 255]),
+        Frame::YUVFrame(f) => nv12(f),
+    }
+}
+
+fn unsupported(kind: &str, detail: String) -> RecordingError {
+    RecordingError::Capture(format!("Unsupported frame type from capture backend: {} ({})", kind, detail))
+}
+
+/// Repack an interleaved frame, dropping any row padding the backend added.
+fn packed(
+    width: i32,
+    height: i32,
+    data: Vec<u8>,
+    bpp: usize,
+    kind: &str,
+    convert: impl Fn(&[u8]) -> [u8; 4],
+) -> Result<(u32, u32, Vec<u8>), RecordingError> {
+    let (w, h) = (width.max(0) as usize, height.max(0) as usize);
+    let stride = data.len().checked_div(h).unwrap_or(0);
+    if w == 0 || stride < w * bpp {
+        return Err(unsupported(kind, format!("{} bytes for {}x{}", data.len(), width, height)));
+    }
+    // already what we need
+    if kind == "BGRA" && stride == w * 4 {
+        return Ok((w as u32, h as u32, data));
+    }
+    let mut out = Vec::with_capacity(w * h * 4);
+    for row in data.chunks_exact(stride).take(h) {
+        for px in row[..w * bpp].chunks_exact(bpp) {
+            out.extend_from_slice(&convert(px));
+        }
+    }
+    Ok((w as u32, h as u32, out))
+}
+
+/// NV12 (a full-resolution Y plane plus interleaved half-resolution CbCr),
+/// video range, BT.709 — what macOS hands out for its YUV output.
+fn nv12(f: YUVFrame) -> Result<(u32, u32, Vec<u8>), RecordingError> {
+    let (w, h) = (f.width.max(0) as usize, f.height.max(0) as usize);
+    let (y_stride, uv_stride) = (f.luminance_stride.max(0) as usize, f.chrominance_stride.max(0) as usize);
+    let uv_rows = h.div_ceil(2);
+    if w == 0
+        || h == 0
+        || y_stride < w
+        || uv_stride < w.div_ceil(2) * 2
+        || f.luminance_bytes.len() < y_stride * (h - 1) + w
+        || f.chrominance_bytes.len() < uv_stride * (uv_rows - 1) + w.div_ceil(2) * 2
+    {
+        return Err(unsupported(
+            "YUV",
+            format!("{}+{} bytes for {}x{}", f.luminance_bytes.len(), f.chrominance_bytes.len(), f.width, f.height),
+        ));
+    }
+
+    let mut out = Vec::with_capacity(w * h * 4);
+    for row in 0..h {
+        let luma = &f.luminance_bytes[row * y_stride..];
+        let chroma = &f.chrominance_bytes[(row / 2) * uv_stride..];
+        for col in 0..w {
+            let y = (luma[col] as f32 - 16.0) * 1.164;
+            let cb = chroma[col / 2 * 2] as f32 - 128.0;
+            let cr = chroma[col / 2 * 2 + 1] as f32 - 128.0;
+            let r = y + 1.793 * cr;
+            let g = y - 0.213 * cb - 0.533 * cr;
+            let b = y + 2.112 * cb;
+            out.extend_from_slice(&[clamp(b), clamp(g), clamp(r), 255]);
+        }
+    }
+    Ok((w as u32, h as u32, out))
+}
+
+fn clamp(v: f32) -> u8 {
+    v.round().clamp(0.0, 255.0) as u8
+}
+
+/// Whether more than `threshold` (a fraction, 0–1) of the pixels of two BGRA
+/// frames differ. Stops scanning as soon as the answer is known.
+pub fn differs(a: &[u8], b: &[u8], threshold: f64) -> bool {
+    if a.len() != b.len() {
+        return true;
+    }
+    let allowed = (threshold * (a.len() / 4) as f64) as usize;
+    if allowed == 0 {
+        return a != b;
+    }
+    let mut changed = 0;
+    for (pa, pb) in a.chunks_exact(4).zip(b.chunks_exact(4)) {
+        if pa != pb {
+            changed += 1;
+            if changed > allowed {
+                return true;
+            }
+        }
+    }
+    false
+}
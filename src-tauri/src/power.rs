@@ -0,0 +1,51 @@
+//! Power saving on laptops: `power_saver` records at a lower rate from the
+//! start, and `auto_power_saver` lowers it only while running on battery.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+use starship_battery::{Manager, State};
+
+// the frame rate power saving records at, or just under
+pub const POWER_SAVER_FPS: u32 = 15;
+
+/// Whether the machine is running on battery; `None` when it has no battery
+/// or its state can't be read.
+pub fn on_battery() -> Option<bool> {
+    let manager = Manager::new().ok()?;
+    let states: Vec<State> = manager.batteries().ok()?.filter_map(Result::ok).map(|b| b.state()).collect();
+    if states.is_empty() {
+        return None;
+    }
+    Some(states.contains(&State::Discharging))
+}
+
+/// How `auto_power_saver` asks whether the machine is on battery: `on_battery`
+/// unless the app state was given another, e.g. a fake one in tests.
+pub type BatteryProbe = Arc<dyn Fn() -> Option<bool> + Send + Sync>;
+
+/// Ask `probe` right away and then every `interval` while `alive`, on a
+/// thread of its own since reading the battery can take a while. The flag
+/// returned follows the answers; an unknown state leaves it as it was.
+pub fn watch(probe: BatteryProbe, interval: Duration, alive: Arc<AtomicBool>) -> Arc<AtomicBool> {
+    let on_battery = Arc::new(AtomicBool::new(false));
+    let flag = on_battery.clone();
+    thread::spawn(move || {
+        // also ends with the recording that reads the flag, should a new one
+        // have set `alive` again in the meantime
+        while alive.load(Ordering::Relaxed) && Arc::strong_count(&flag) > 1 {
+            if let Some(state) = probe() {
+                flag.store(state, Ordering::Relaxed);
+            }
+            thread::sleep(interval);
+        }
+    });
+    on_battery
+}
+
+/// Capture only every nth frame slot at `fps` to get down to `POWER_SAVER_FPS`.
+pub fn frame_stride(fps: u32) -> u32 {
+    fps.div_ceil(POWER_SAVER_FPS).max(1)
+}
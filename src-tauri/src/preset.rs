@@ -0,0 +1,82 @@
+//! Named starting points for `RecordingOptions`, so a typical recording
+//! doesn't need every field chosen by hand.
+
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+use crate::{ffmpeg, RecordingError, RecordingOptions, HW_H264_ENCODERS};
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+pub enum RecordingPreset {
+    /// Tutorials: 30 fps, cursor and clicks shown, microphone narration.
+    Screencast,
+    /// 60 fps on a hardware encoder where one works, game and voice audio mixed.
+    Gameplay,
+    /// Small files for sharing: 24 fps, lower quality, no audio.
+    Demo,
+}
+
+impl RecordingPreset {
+    /// The preset's fields as a `RecordingOptions` object.
+    fn fields(self) -> Value {
+        match self {
+            RecordingPreset::Screencast => json!({
+                "fps": 30,
+                "show_cursor": true,
+                "show_highlight": false,
+                "capture_keystrokes": false,
+                "highlight_clicks": true,
+                "quality": "medium",
+                "audio_source": "Microphone",
+            }),
+            RecordingPreset::Gameplay => json!({
+                "fps": 60,
+                "show_cursor": false,
+                "show_highlight": false,
+                "capture_keystrokes": false,
+                "quality": "high",
+                "audio_source": "Both",
+            }),
+            RecordingPreset::Demo => json!({
+                "fps": 24,
+                "show_cursor": true,
+                "show_highlight": false,
+                "capture_keystrokes": false,
+                "quality": "low",
+                "preset": "veryfast",
+            }),
+        }
+    }
+
+    /// The preset with any fields in `overrides` (a partial `RecordingOptions`
+    /// object) replacing its own.
+    pub fn expand(self, overrides: Option<Value>) -> Result<RecordingOptions, RecordingError> {
+        let mut fields = self.fields();
+        let overrides = match overrides {
+            None | Some(Value::Null) => serde_json::Map::new(),
+            Some(Value::Object(map)) => map,
+            Some(other) => {
+                return Err(RecordingError::InvalidOptions(format!(
+                    "preset overrides must be an object of option fields, not {}",
+                    other
+                )));
+            }
+        };
+        let encoder_chosen = overrides.contains_key("encoder");
+        if let Value::Object(map) = &mut fields {
+            map.extend(overrides);
+        }
+        let mut opts: RecordingOptions = serde_json::from_value(fields)
+            .map_err(|e| RecordingError::InvalidOptions(format!("invalid preset overrides: {}", e)))?;
+
+        // the first hardware encoder that actually works here; libx264 otherwise
+        if self == RecordingPreset::Gameplay && !encoder_chosen {
+            let bin = opts.ffmpeg_bin().to_string();
+            opts.encoder = HW_H264_ENCODERS
+                .iter()
+                .find(|encoder| ffmpeg::encoder_works(&bin, encoder))
+                .map(|encoder| encoder.to_string());
+        }
+        Ok(opts)
+    }
+}
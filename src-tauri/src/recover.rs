@@ -0,0 +1,266 @@
+//! Finalizing sessions left behind when the app quit or crashed mid-recording.
+//! A finished session always has a `manifest.json`; one without it has either a
+//! partial output that a stream-copy remux can usually make playable, or
+//! nothing worth keeping, in which case it is marked so it can be deleted.
+
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+use chrono::{DateTime, Local, NaiveDateTime, TimeZone};
+use serde::Serialize;
+
+use crate::{ffmpeg, segment, RecordingError, RecordingManifest, OUTPUT_FORMATS};
+
+/// Written into a session that couldn't be recovered, holding the reason.
+const UNRECOVERABLE_MARKER: &str = "unrecoverable.txt";
+
+/// What `recover_sessions` found for one interrupted session.
+#[derive(Debug, Serialize, Clone)]
+pub struct RecoveredSession {
+    pub session_dir: String,
+    /// Playable output after recovery; `None` when the session is unrecoverable.
+    pub output_path: Option<String>,
+    pub manifest_path: Option<String>,
+    /// What was salvaged, or why nothing could be.
+    pub message: String,
+}
+
+/// Geometry and timing of a salvaged output.
+struct Salvaged {
+    output: PathBuf,
+    segments: Vec<PathBuf>, // all of them, when the output was split
+    width: u32,
+    height: u32,
+    fps: u32,
+    duration_secs: f64,
+}
+
+/// Whether `dir` looks like a recording session that never got its manifest.
+fn interrupted(dir: &Path) -> bool {
+    if dir.join("manifest.json").exists() {
+        return false;
+    }
+    // screenshots share the session layout but never get a manifest
+    std::fs::read_dir(dir).is_ok_and(|entries| {
+        !entries
+            .filter_map(|e| e.ok())
+            .any(|e| e.file_name().to_string_lossy().starts_with("screenshot_"))
+    })
+}
+
+/// `30/1`-style ffprobe rate to whole frames per second.
+fn parse_rate(rate: &str) -> u32 {
+    let (num, den) = rate.split_once('/').unwrap_or((rate, "1"));
+    match (num.parse::<f64>(), den.parse::<f64>()) {
+        (Ok(num), Ok(den)) if den > 0.0 => (num / den).round() as u32,
+        _ => 0,
+    }
+}
+
+/// Copy the streams of a partial output into a fresh container, which rewrites
+/// the index and duration a killed ffmpeg never got to. The original is only
+/// replaced once the copy succeeded.
+fn remux(bin: &str, video: &Path) -> Result<(), String> {
+    let ext = video.extension().and_then(|e| e.to_str()).unwrap_or("mp4");
+    let tmp = video.with_extension(format!("recovered.{}", ext));
+    let out = Command::new(bin)
+        .args(["-y", "-hide_banner", "-loglevel", "error", "-err_detect", "ignore_err", "-i"])
+        .arg(video)
+        .args(["-map", "0", "-c", "copy"])
+        .arg(&tmp)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .output()
+        .map_err(|e| ffmpeg::spawn_error(bin, e).to_string())?;
+    if !out.status.success() {
+        let _ = std::fs::remove_file(&tmp);
+        let stderr = String::from_utf8_lossy(&out.stderr);
+        return Err(format!(
+            "the partial {} could not be read: {}",
+            ext,
+            stderr.lines().last().unwrap_or("ffmpeg failed").trim()
+        ));
+    }
+    std::fs::rename(&tmp, video).map_err(|e| e.to_string())
+}
+
+fn salvage_video(bin: &str, video: &Path) -> Result<Salvaged, String> {
+    if std::fs::metadata(video).map_or(0, |m| m.len()) == 0 {
+        return Err("the output is empty; ffmpeg stopped before writing any video".into());
+    }
+    remux(bin, video)?;
+    let ffprobe = ffmpeg::ffprobe_bin(bin);
+    let args = ["-select_streams", "v:0", "-show_entries", "stream=width,height,avg_frame_rate"];
+    let stream = ffmpeg::ffprobe_lines(&ffprobe, &args, video).unwrap_or_default();
+    let [width, height, rate] = stream.as_slice() else {
+        return Err("the remuxed output has no readable video stream".into());
+    };
+    Ok(Salvaged {
+        output: video.to_path_buf(),
+        segments: Vec::new(),
+        width: width.parse().unwrap_or(0),
+        height: height.parse().unwrap_or(0),
+        fps: parse_rate(rate),
+        duration_secs: ffmpeg::duration(&ffprobe, video).unwrap_or(0.0),
+    })
+}
+
+/// Frames mode writes finished images as it goes, so whatever is there is kept.
+fn salvage_frames(frames: &Path) -> Result<Salvaged, String> {
+    let mut images: Vec<PathBuf> = std::fs::read_dir(frames)
+        .map_err(|e| e.to_string())?
+        .filter_map(|e| e.ok())
+        .map(|e| e.path())
+        .filter(|p| p.is_file())
+        .collect();
+    images.sort();
+    // the last image may have been cut off mid-write
+    let (width, height) = images
+        .iter()
+        .find_map(|p| image::image_dimensions(p).ok())
+        .ok_or("no frame images were written")?;
+    Ok(Salvaged { output: frames.to_path_buf(), segments: Vec::new(), width, height, fps: 0, duration_secs: 0.0 })
+}
+
+fn salvage(bin: &str, dir: &Path) -> Result<Salvaged, String> {
+    let frames = dir.join("frames");
+    if frames.is_dir() {
+        return salvage_frames(&frames);
+    }
+    if let Some(video) = OUTPUT_FORMATS.iter().map(|ext| dir.join(format!("output.{}", ext))).find(|p| p.is_file()) {
+        return salvage_video(bin, &video);
+    }
+    // a split recording: earlier segments were closed cleanly, only the last was cut off
+    let segments: Vec<PathBuf> = OUTPUT_FORMATS
+        .iter()
+        .map(|ext| (1..).map(|i| segment::segment_path(dir, ext, i)).take_while(|p| p.is_file()).collect::<Vec<_>>())
+        .find(|found| !found.is_empty())
+        .ok_or("no output was written before the app stopped")?;
+    let last = segments.last().cloned().unwrap_or_default();
+    let mut salvaged = salvage_video(bin, &last)?;
+    let ffprobe = ffmpeg::ffprobe_bin(bin);
+    salvaged.duration_secs = segments.iter().filter_map(|p| ffmpeg::duration(&ffprobe, p)).sum();
+    salvaged.output = segments[0].clone();
+    salvaged.segments = segments;
+    Ok(salvaged)
+}
+
+/// When the session started, from its `%Y%m%d_%H%M%S` directory name.
+fn session_start(dir: &Path) -> Option<DateTime<Local>> {
+    let name = dir.file_name()?.to_str()?;
+    let naive = NaiveDateTime::parse_from_str(name, "%Y%m%d_%H%M%S").ok()?;
+    Local.from_local_datetime(&naive).earliest()
+}
+
+fn recover_session(bin: &str, dir: &Path) -> RecoveredSession {
+    let session_dir = dir.to_string_lossy().into();
+    let salvaged = match salvage(bin, dir) {
+        Ok(salvaged) => salvaged,
+        Err(reason) => {
+            let _ = std::fs::write(dir.join(UNRECOVERABLE_MARKER), &reason);
+            return RecoveredSession { session_dir, output_path: None, manifest_path: None, message: reason };
+        }
+    };
+
+    let ended: DateTime<Local> = std::fs::metadata(&salvaged.output)
+        .and_then(|m| m.modified())
+        .map(DateTime::from)
+        .unwrap_or_else(|_| Local::now());
+    let started = session_start(dir).unwrap_or(ended);
+    let events_file = Some(dir.join("events.log")).filter(|p| p.is_file());
+    // taken from the first segment when the output was split
+    let thumb_secs = match salvaged.segments.len() {
+        0 => salvaged.duration_secs,
+        _ => ffmpeg::duration(&ffmpeg::ffprobe_bin(bin), &salvaged.output).unwrap_or(0.0),
+    };
+    let thumbnail = crate::write_thumbnail(bin, &salvaged.output, thumb_secs).ok();
+    let manifest = RecordingManifest {
+        output_path: salvaged.output.to_string_lossy().into(),
+        width: salvaged.width,
+        height: salvaged.height,
+        fps: salvaged.fps,
+        duration_secs: salvaged.duration_secs,
+        keystrokes_captured: events_file.is_some(),
+        mouse_captured: events_file.is_some(),
+        events_file: events_file.map(|p| p.to_string_lossy().into()),
+        events_started_at: None,
+        events_indexed_file: None,
+        chapters_file: None,
+        screen_mapping: None,
+        thumbnail_file: thumbnail.map(|p| p.to_string_lossy().into()),
+        started_epoch: started.timestamp_millis() as f64 / 1000.0,
+        started_at: started.to_rfc3339(),
+        ended_at: ended.to_rfc3339(),
+        platform: std::env::consts::OS.into(),
+        recovered: true,
+        segments: salvaged.segments.iter().map(|p| p.to_string_lossy().into()).collect(),
+        // kept, but the options that decide verifiability are gone with the crash
+        frame_hashes_file: Some(dir.join("frame_hashes.jsonl"))
+            .filter(|p| p.is_file())
+            .map(|p| p.to_string_lossy().into()),
+        hashes_verifiable: false,
+        window_activity_file: Some(dir.join("window_activity.jsonl"))
+            .filter(|p| p.is_file())
+            .map(|p| p.to_string_lossy().into()),
+        frame_timestamps_file: Some(dir.join("frame_timestamps.csv"))
+            .filter(|p| p.is_file())
+            .map(|p| p.to_string_lossy().into()),
+        appended: Vec::new(),
+    };
+    match crate::write_manifest(dir, &manifest) {
+        Ok(path) => RecoveredSession {
+            session_dir,
+            output_path: Some(manifest.output_path),
+            manifest_path: Some(path.to_string_lossy().into()),
+            message: format!("Recovered {:.1}s of an interrupted recording", salvaged.duration_secs),
+        },
+        Err(e) => RecoveredSession {
+            session_dir,
+            output_path: Some(manifest.output_path),
+            manifest_path: None,
+            message: format!("Output is playable but its manifest could not be written: {}", e),
+        },
+    }
+}
+
+/// Try to finalize every interrupted session under `root`. Sessions marked
+/// unrecoverable on an earlier run are reported again, without a retry, until
+/// they are deleted.
+pub fn recover(bin: &str, root: &Path) -> Result<Vec<RecoveredSession>, RecordingError> {
+    if !root.is_dir() {
+        return Ok(Vec::new());
+    }
+    let mut sessions: Vec<PathBuf> = std::fs::read_dir(root)?
+        .filter_map(|e| e.ok())
+        .map(|e| e.path())
+        .filter(|p| p.is_dir() && interrupted(p))
+        .collect();
+    sessions.sort();
+
+    Ok(sessions
+        .iter()
+        .map(|dir| match std::fs::read_to_string(dir.join(UNRECOVERABLE_MARKER)) {
+            Ok(reason) => RecoveredSession {
+                session_dir: dir.to_string_lossy().into(),
+                output_path: None,
+                manifest_path: None,
+                message: reason,
+            },
+            Err(_) => recover_session(bin, dir),
+        })
+        .collect())
+}
+
+/// Delete a session `recover` marked unrecoverable. Anything else is refused,
+/// so this can't remove a finished recording.
+pub fn discard(session_dir: &Path) -> Result<(), RecordingError> {
+    if !session_dir.join(UNRECOVERABLE_MARKER).is_file() {
+        return Err(RecordingError::InvalidOptions(format!(
+            "{} is not marked unrecoverable",
+            session_dir.display()
+        )));
+    }
+    std::fs::remove_dir_all(session_dir)?;
+    Ok(())
+}
@@ -0,0 +1,79 @@
+//! Choosing a capture region by dragging on a transparent overlay window.
+//! The overlay covers the display that will be recorded (or the whole desktop
+//! for `capture_all_monitors`) and works in logical points; the selection is
+//! converted to the frame pixels `region` expects here, with the same scale
+//! the capture uses.
+
+use crossbeam_channel::Sender;
+use screenshots::Screen;
+
+use crate::{desktop_layout, RecordingError};
+
+pub const WINDOW_LABEL: &str = "region-picker";
+
+/// Where the overlay goes, in logical desktop coordinates, and how many frame
+/// pixels one of its points covers.
+pub struct Overlay {
+    pub x: f64,
+    pub y: f64,
+    pub width: f64,
+    pub height: f64,
+    pub scale: f64,
+}
+
+/// A picker waiting for the user: where its answer goes, and the scale to
+/// convert it with.
+pub struct PendingSelection {
+    pub reply: Sender<Option<(u32, u32, u32, u32)>>,
+    pub scale: f64,
+}
+
+/// The overlay for display `monitor_index` (the primary by default), or one
+/// spanning every display with `all_monitors`, matching the composite canvas.
+pub fn overlay(monitor_index: Option<usize>, all_monitors: bool) -> Result<Overlay, RecordingError> {
+    let screens = Screen::all().map_err(|e| RecordingError::Capture(e.to_string()))?;
+    if screens.is_empty() {
+        return Err(RecordingError::Capture("No monitors detected".into()));
+    }
+    if all_monitors {
+        let infos: Vec<_> = screens.iter().map(|s| &s.display_info).collect();
+        let left = infos.iter().map(|d| d.x).min().unwrap_or(0);
+        let top = infos.iter().map(|d| d.y).min().unwrap_or(0);
+        let right = infos.iter().map(|d| d.x + d.width as i32).max().unwrap_or(0);
+        let bottom = infos.iter().map(|d| d.y + d.height as i32).max().unwrap_or(0);
+        return Ok(Overlay {
+            x: left as f64,
+            y: top as f64,
+            width: (right - left) as f64,
+            height: (bottom - top) as f64,
+            scale: desktop_layout(&screens).1,
+        });
+    }
+    let screen = match monitor_index {
+        Some(index) => screens.get(index).ok_or_else(|| {
+            RecordingError::InvalidOptions(format!(
+                "Monitor index {} out of range (valid: 0..={})",
+                index,
+                screens.len() - 1
+            ))
+        })?,
+        None => screens.iter().find(|s| s.display_info.is_primary).unwrap_or(&screens[0]),
+    };
+    let info = &screen.display_info;
+    Ok(Overlay {
+        x: info.x as f64,
+        y: info.y as f64,
+        width: info.width as f64,
+        height: info.height as f64,
+        scale: (info.scale_factor as f64).max(1.0),
+    })
+}
+
+/// A selection in overlay points as a `region` in frame pixels, or `None`
+/// when it is too small to record.
+pub fn to_region((x, y, w, h): (f64, f64, f64, f64), scale: f64) -> Option<(u32, u32, u32, u32)> {
+    let px = |v: f64| (v.max(0.0) * scale).round() as u32;
+    let region = (px(x), px(y), px(w), px(h));
+    // encoders need at least 2x2
+    (region.2 >= 2 && region.3 >= 2).then_some(region)
+}
@@ -0,0 +1,154 @@
+//! Giving a finished recording a user-chosen name. The output (every segment,
+//! for a split recording) is renamed in place, optionally along with its
+//! session folder, and the manifest is rewritten to match.
+
+use std::path::{Path, PathBuf};
+
+use chrono::NaiveDateTime;
+
+use crate::{write_manifest, RecordingError, RecordingManifest};
+
+// keeps room for a collision suffix and segment numbers within common path limits
+const MAX_NAME_CHARS: usize = 120;
+
+// characters Windows refuses in file names; elsewhere only '/' is
+#[cfg(windows)]
+const ILLEGAL_CHARS: &[char] = &['<', '>', ':', '"', '/', '\\', '|', '?', '*'];
+#[cfg(not(windows))]
+const ILLEGAL_CHARS: &[char] = &['/'];
+
+/// Device names Windows reserves regardless of extension.
+#[cfg(windows)]
+fn reserved(name: &str) -> bool {
+    let stem = name.split('.').next().unwrap_or_default().to_ascii_uppercase();
+    matches!(stem.as_str(), "CON" | "PRN" | "AUX" | "NUL")
+        || ["COM", "LPT"].iter().any(|p| {
+            stem.strip_prefix(p).is_some_and(|n| n.len() == 1 && n.chars().all(|c| c.is_ascii_digit()))
+        })
+}
+
+#[cfg(not(windows))]
+fn reserved(_name: &str) -> bool {
+    false
+}
+
+/// `name` made safe as a file name on this platform: illegal and control
+/// characters become `_`, and trailing dots and spaces (which Windows drops
+/// silently) are trimmed. Fails if nothing usable is left.
+pub fn sanitize_name(name: &str) -> Result<String, RecordingError> {
+    let cleaned: String = name
+        .chars()
+        .map(|c| if c.is_control() || ILLEGAL_CHARS.contains(&c) { '_' } else { c })
+        .take(MAX_NAME_CHARS)
+        .collect();
+    let cleaned = cleaned.trim().trim_end_matches(['.', ' ']).to_string();
+    if cleaned.is_empty() || cleaned == "." || cleaned == ".." {
+        return Err(RecordingError::InvalidOptions(format!("'{}' is not a usable file name", name)));
+    }
+    if reserved(&cleaned) {
+        return Err(RecordingError::InvalidOptions(format!("'{}' is reserved on this platform", cleaned)));
+    }
+    Ok(cleaned)
+}
+
+/// The first suffix (none, then 2, 3, …) for which none of `make`'s paths
+/// exist. `keep` lists paths that may be reused because they are the ones
+/// being renamed.
+fn free_suffix(keep: &[PathBuf], make: impl Fn(Option<u32>) -> Vec<PathBuf>) -> Option<u32> {
+    let free = |n| make(n).iter().all(|p| keep.contains(p) || !p.exists());
+    std::iter::once(None).chain((2..).map(Some)).find(|&n| free(n)).flatten()
+}
+
+fn with_suffix(name: &str, n: Option<u32>) -> String {
+    match n {
+        Some(n) => format!("{} ({})", name, n),
+        None => name.to_string(),
+    }
+}
+
+/// `path` moved from under `from` to under `to`; paths elsewhere are kept.
+fn rebase(path: &str, from: &Path, to: &Path) -> String {
+    match Path::new(path).strip_prefix(from) {
+        Ok(rest) => to.join(rest).to_string_lossy().into(),
+        Err(_) => path.to_string(),
+    }
+}
+
+/// Rename the recording in `session_dir` to `name`, and the folder itself to
+/// `<timestamp>_<name>` when `rename_folder` is set (the timestamp keeps
+/// sessions sorting by age). Returns the new output path.
+pub fn rename(session_dir: &Path, name: &str, rename_folder: bool) -> Result<PathBuf, RecordingError> {
+    let name = sanitize_name(name)?;
+    let mut manifest: RecordingManifest = std::fs::read_to_string(session_dir.join("manifest.json"))
+        .ok()
+        .and_then(|json| serde_json::from_str(&json).ok())
+        .ok_or_else(|| {
+            RecordingError::InvalidOptions(format!("{} has no finished recording", session_dir.display()))
+        })?;
+
+    let output = PathBuf::from(&manifest.output_path);
+    let ext = output.extension().map(|e| format!(".{}", e.to_string_lossy())).unwrap_or_default();
+    // a name typed with the extension shouldn't end up with it twice
+    let stem = name.strip_suffix(ext.as_str()).filter(|s| !s.is_empty()).unwrap_or(&name).to_string();
+    let dir = output.parent().unwrap_or(session_dir).to_path_buf();
+
+    let old: Vec<PathBuf> = match manifest.segments.len() {
+        0 => vec![output.clone()],
+        _ => manifest.segments.iter().map(PathBuf::from).collect(),
+    };
+    let count = old.len();
+    let paths = |n: Option<u32>| -> Vec<PathBuf> {
+        let base = with_suffix(&stem, n);
+        match count {
+            1 => vec![dir.join(format!("{}{}", base, ext))],
+            _ => (1..=count).map(|i| dir.join(format!("{}_{:03}{}", base, i, ext))).collect(),
+        }
+    };
+    let renamed = paths(free_suffix(&old, paths));
+    for (from, to) in old.iter().zip(&renamed) {
+        if from != to {
+            std::fs::rename(from, to)?;
+        }
+    }
+    manifest.output_path = renamed[0].to_string_lossy().into();
+    if count > 1 {
+        manifest.segments = renamed.iter().map(|p| p.to_string_lossy().into()).collect();
+    }
+
+    let mut session_dir = session_dir.to_path_buf();
+    if rename_folder {
+        let folder = session_dir.file_name().map(|n| n.to_string_lossy().into_owned()).unwrap_or_default();
+        // the `%Y%m%d_%H%M%S` of the original name, dropping an earlier rename
+        let timestamp: String = folder.chars().take(15).collect();
+        let prefix = match NaiveDateTime::parse_from_str(&timestamp, "%Y%m%d_%H%M%S") {
+            Ok(_) => format!("{}_", timestamp),
+            Err(_) => String::new(),
+        };
+        let parent = session_dir.parent().unwrap_or(Path::new(".")).to_path_buf();
+        let folder_path = |n| parent.join(format!("{}{}", prefix, with_suffix(&stem, n)));
+        let target = folder_path(free_suffix(std::slice::from_ref(&session_dir), |n| vec![folder_path(n)]));
+        if target != session_dir {
+            std::fs::rename(&session_dir, &target)?;
+            let rebase_opt = |p: &mut Option<String>| {
+                if let Some(path) = p {
+                    *path = rebase(path, &session_dir, &target);
+                }
+            };
+            manifest.output_path = rebase(&manifest.output_path, &session_dir, &target);
+            rebase_opt(&mut manifest.events_file);
+            rebase_opt(&mut manifest.events_indexed_file);
+            rebase_opt(&mut manifest.chapters_file);
+            rebase_opt(&mut manifest.window_activity_file);
+            rebase_opt(&mut manifest.thumbnail_file);
+            rebase_opt(&mut manifest.frame_hashes_file);
+            rebase_opt(&mut manifest.frame_timestamps_file);
+            for path in manifest.segments.iter_mut().chain(&mut manifest.appended) {
+                *path = rebase(path, &session_dir, &target);
+            }
+            session_dir = target;
+        }
+    }
+
+    write_manifest(&session_dir, &manifest)?;
+    Ok(PathBuf::from(manifest.output_path))
+}
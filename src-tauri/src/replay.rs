@@ -0,0 +1,107 @@
+//! In-memory ring buffer of raw frames for "instant replay" recordings.
+//! Frames are reference-counted so a snapshot is just a copy of pointers taken
+//! under the lock; encoding then runs without blocking capture.
+
+use std::collections::VecDeque;
+use std::io::Write;
+use std::path::Path;
+use std::process::{Command, Stdio};
+use std::sync::Arc;
+
+use crate::ffmpeg;
+use crate::RecordingError;
+
+/// Frames kept for the last `capacity / fps` seconds, plus what's needed to encode them.
+pub struct ReplayBuffer {
+    frames: VecDeque<Arc<Vec<u8>>>,
+    capacity: usize,
+    encoder: ReplayEncoder,
+}
+
+/// Everything ffmpeg needs besides the frames themselves.
+#[derive(Clone)]
+pub struct ReplayEncoder {
+    pub ffmpeg: String,
+    pub width: u32,
+    pub height: u32,
+    pub fps: u32,
+    /// Filter, codec and metadata arguments placed between the input and the output file.
+    pub output_args: Vec<String>,
+}
+
+/// A consistent copy of the buffer at one instant.
+pub struct ReplaySnapshot {
+    frames: Vec<Arc<Vec<u8>>>,
+    encoder: ReplayEncoder,
+}
+
+impl ReplayBuffer {
+    /// Buffer `secs` seconds of frames, holding at most `memory_limit` bytes.
+    /// Returns the buffer and the number of seconds it can actually hold.
+    pub fn new(secs: u32, memory_limit: u64, encoder: ReplayEncoder) -> (Self, f64) {
+        let frame_len = encoder.width as u64 * encoder.height as u64 * 4;
+        let wanted = secs as u64 * encoder.fps as u64;
+        let capacity = wanted.min(memory_limit / frame_len.max(1)).max(1) as usize;
+        let held_secs = capacity as f64 / encoder.fps as f64;
+        let buffer = ReplayBuffer { frames: VecDeque::with_capacity(capacity), capacity, encoder };
+        (buffer, held_secs)
+    }
+
+    pub fn push(&mut self, frame: Vec<u8>) {
+        if self.frames.len() == self.capacity {
+            self.frames.pop_front();
+        }
+        self.frames.push_back(Arc::new(frame));
+    }
+
+    /// The newest frame again, sharing its pixels.
+    pub fn repeat(&mut self) {
+        if let Some(last) = self.frames.back().cloned() {
+            if self.frames.len() == self.capacity {
+                self.frames.pop_front();
+            }
+            self.frames.push_back(last);
+        }
+    }
+
+    pub fn snapshot(&self) -> ReplaySnapshot {
+        ReplaySnapshot {
+            frames: self.frames.iter().cloned().collect(),
+            encoder: self.encoder.clone(),
+        }
+    }
+}
+
+impl ReplaySnapshot {
+    /// Pipe the snapshot's frames through ffmpeg into `out`.
+    pub fn encode(&self, out: &Path) -> Result<(), RecordingError> {
+        if self.frames.is_empty() {
+            return Err(RecordingError::Capture("the replay buffer is still empty".into()));
+        }
+        let enc = &self.encoder;
+        let mut child = Command::new(&enc.ffmpeg)
+            .args(["-y", "-f", "rawvideo", "-pix_fmt", "bgra",
+                   "-s", &format!("{}x{}", enc.width, enc.height),
+                   "-r", &enc.fps.to_string(),
+                   "-i", "-"])
+            .args(&enc.output_args)
+            .arg(out)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn()
+            .map_err(|e| ffmpeg::spawn_error(&enc.ffmpeg, e))?;
+
+        let mut stdin = child
+            .stdin
+            .take()
+            .ok_or_else(|| RecordingError::FfmpegFailed("stdin unavailable".into()))?;
+        let written = self.frames.iter().try_for_each(|frame| stdin.write_all(frame));
+        drop(stdin);
+        let status = child.wait()?;
+        if written.is_err() || !status.success() {
+            return Err(RecordingError::FfmpegFailed(format!("replay encode exited with {}", status)));
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,167 @@
+//! Size-capped output: one ffmpeg per numbered segment file, rolled over to
+//! the next once the current file nears `max_file_size_mb`. Capture never
+//! pauses; the next encoder is running before the previous one is closed.
+
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::{Child, ChildStdin, Command, Stdio};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+use crate::ffmpeg::{self, StderrTail};
+use crate::levels::LevelMeter;
+use crate::session_log::SessionLog;
+use crate::RecordingError;
+
+// appended after the output path on every launch
+const LOG_ARGS: [&str; 3] = ["-loglevel", "warning", "-nostats"];
+
+// roll over at this share of the cap; the container trailer and frames still
+// inside the encoder land after the size check
+const ROLLOVER_RATIO: f64 = 0.95;
+// how often the segment's size is checked; a stat per frame adds up at high fps
+const SIZE_CHECK_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Everything needed to (re)launch the encoder for another output file.
+pub struct EncoderCommand {
+    pub bin: String,
+    /// Every argument before the output path.
+    pub args: Vec<String>,
+    /// Reads the audio levels `args` print to stdout, when they meter audio.
+    pub meter: Option<LevelMeter>,
+}
+
+impl EncoderCommand {
+    pub fn spawn(&self, out: &Path) -> Result<(Child, ChildStdin, Option<StderrTail>), RecordingError> {
+        let mut child = Command::new(&self.bin)
+            .args(&self.args)
+            .arg(out)
+            // only warnings and errors, without the \r-terminated progress line
+            .args(LOG_ARGS)
+            .stdin(Stdio::piped())
+            .stdout(if self.meter.is_some() { Stdio::piped() } else { Stdio::null() })
+            .stderr(Stdio::piped())
+            .spawn()
+            .map_err(|e| ffmpeg::spawn_error(&self.bin, e))?;
+        if let (Some(meter), Some(stdout)) = (&self.meter, child.stdout.take()) {
+            meter.watch(stdout);
+        }
+        let stdin = child
+            .stdin
+            .take()
+            .ok_or_else(|| RecordingError::FfmpegFailed("stdin unavailable".into()))?;
+        let stderr = child.stderr.take().map(StderrTail::capture);
+        Ok((child, stdin, stderr))
+    }
+
+    /// The full invocation for `out`, quoted for pasting into a shell.
+    pub fn command_line(&self, out: &Path) -> String {
+        let out = out.to_string_lossy();
+        std::iter::once(self.bin.as_str())
+            .chain(self.args.iter().map(String::as_str))
+            .chain(std::iter::once(out.as_ref()))
+            .chain(LOG_ARGS)
+            .map(|arg| {
+                if arg.contains(|c: char| c.is_whitespace() || c == '\'') {
+                    format!("'{}'", arg.replace('\'', r"'\''"))
+                } else {
+                    arg.to_string()
+                }
+            })
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+}
+
+/// `output_001.mp4`, `output_002.mp4`, … in the session directory.
+pub fn segment_path(dir: &Path, ext: &str, index: u32) -> PathBuf {
+    dir.join(format!("output_{:03}.{}", index, ext))
+}
+
+/// Writer-thread target that feeds the current segment's ffmpeg. The running
+/// process lives in the app state like a plain recording's, so stopping and
+/// cancelling treat the last segment as they would any output.
+pub struct Segmenter {
+    pub encoder: EncoderCommand,
+    pub stdin: ChildStdin,
+    pub dir: PathBuf,
+    pub ext: String,
+    pub limit_bytes: u64,
+    pub index: u32,
+    /// Every segment started so far, in order.
+    pub segments: Arc<Mutex<Vec<PathBuf>>>,
+    pub ffmpeg: Arc<Mutex<Option<Child>>>,
+    pub stderr: Arc<Mutex<Option<StderrTail>>>,
+    /// Earlier segments' ffmpegs, finishing their files in the background.
+    pub closing: Vec<JoinHandle<()>>,
+    pub log: SessionLog,
+    /// When the current segment's size was last checked.
+    pub checked: Instant,
+}
+
+impl Segmenter {
+    pub fn write(&mut self, buf: &[u8]) -> bool {
+        if self.stdin.write_all(buf).is_err() {
+            return false;
+        }
+        if self.checked.elapsed() < SIZE_CHECK_INTERVAL {
+            return true;
+        }
+        self.checked = Instant::now();
+        let current = segment_path(&self.dir, &self.ext, self.index);
+        let size = std::fs::metadata(&current).map_or(0, |m| m.len());
+        if size as f64 >= self.limit_bytes as f64 * ROLLOVER_RATIO {
+            if let Err(e) = self.roll_over() {
+                // keep recording into the oversized segment rather than lose frames
+                eprintln!("Could not start segment {}: {}", self.index + 1, e);
+                self.log.write(format_args!("Could not start segment {}: {}", self.index + 1, e));
+                self.limit_bytes = u64::MAX;
+            }
+        }
+        true
+    }
+
+    fn roll_over(&mut self) -> Result<(), RecordingError> {
+        let next = segment_path(&self.dir, &self.ext, self.index + 1);
+        let (child, stdin, stderr) = self.encoder.spawn(&next)?;
+        // the new target is live before the old one closes, so no frame waits on it
+        let old_stdin = std::mem::replace(&mut self.stdin, stdin);
+        // errors and stalls from here on are the new segment's
+        let old_stderr = std::mem::replace(&mut *self.stderr.lock().unwrap(), stderr);
+        let old = self.ffmpeg.lock().unwrap().replace(child);
+        self.index += 1;
+        self.log.write(format_args!("Segment {} started: {}", self.index, next.display()));
+        self.segments.lock().unwrap().push(next);
+
+        // EOF on stdin lets the previous ffmpeg write its trailer and exit
+        drop(old_stdin);
+        if let Some(mut old) = old {
+            let index = self.index - 1;
+            let log = self.log.clone();
+            self.closing.push(thread::spawn(move || match old.wait() {
+                Ok(status) => {
+                    let mut message = format!("Segment {} ffmpeg exited with {}", index, status);
+                    if !status.success() {
+                        if let Some(output) = old_stderr.as_ref().map(StderrTail::text).filter(|t| !t.is_empty()) {
+                            message = format!("{}:\n{}", message, output);
+                        }
+                        eprintln!("{}", message);
+                    }
+                    log.write(&message);
+                }
+                Err(e) => eprintln!("Failed to wait for segment {} ffmpeg: {}", index, e),
+            }));
+        }
+        Ok(())
+    }
+}
+
+impl Drop for Segmenter {
+    fn drop(&mut self) {
+        // earlier segments must be complete before the recording counts as finished
+        for closer in self.closing.drain(..) {
+            let _ = closer.join();
+        }
+    }
+}
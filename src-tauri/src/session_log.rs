@@ -0,0 +1,33 @@
+//! `session.log`: a plain-text diagnostics trail in every session directory.
+//! stderr is swallowed in a packaged app, so this is what a user can attach
+//! to a bug report when a recording fails.
+
+use std::fmt::Display;
+use std::fs::File;
+use std::io::Write;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+
+use chrono::Local;
+
+use crate::RecordingError;
+
+/// Shared handle to the log; clones append to the same file. The default
+/// logs nowhere, for code paths that may run without a session.
+#[derive(Clone, Default)]
+pub struct SessionLog(Option<Arc<Mutex<File>>>);
+
+impl SessionLog {
+    pub fn create(session_dir: &Path) -> Result<Self, RecordingError> {
+        Ok(SessionLog(Some(Arc::new(Mutex::new(File::create(session_dir.join("session.log"))?)))))
+    }
+
+    /// Append one timestamped line. Logging never fails the recording, so
+    /// write errors are dropped.
+    pub fn write(&self, message: impl Display) {
+        let Some(file) = &self.0 else { return };
+        let mut file = file.lock().unwrap();
+        // unbuffered, so the log is complete up to the moment of a crash
+        let _ = writeln!(file, "[{}] {}", Local::now().format("%H:%M:%S%.3f"), message);
+    }
+}
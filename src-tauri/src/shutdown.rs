@@ -0,0 +1,76 @@
+//! Quitting while recording. Closing the app, `exit`, Ctrl+C and SIGTERM all
+//! go through the normal stop first, so ffmpeg finishes the file and the
+//! event helper is stopped rather than orphaned. The exit waits for that,
+//! up to a limit.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread;
+use std::time::Duration;
+
+use crossbeam_channel::bounded;
+use tauri::{AppHandle, ExitRequestApi, Manager};
+
+use crate::{session_stop_timeout, stop_active_recording, AppState, PIPELINE_JOIN_TIMEOUT};
+
+// on top of the pipeline and ffmpeg stop timeouts, for the helper and the manifest
+const SHUTDOWN_MARGIN: Duration = Duration::from_secs(15);
+
+/// Turn SIGINT and SIGTERM (Ctrl+C and closing the console on Windows) into
+/// an app exit, which stops a recording cleanly. A second signal exits at once.
+pub fn handle_signals(app: AppHandle) {
+    let signalled = AtomicBool::new(false);
+    let installed = ctrlc::set_handler(move || {
+        if signalled.swap(true, Ordering::Relaxed) {
+            std::process::exit(130);
+        }
+        app.exit(0);
+    });
+    if let Err(e) = installed {
+        eprintln!("Signal handling unavailable, quitting by signal may lose a recording: {}", e);
+    }
+}
+
+/// For `RunEvent::ExitRequested`: while recording, hold the exit, finish the
+/// recording on a background thread and exit again once it's saved.
+pub fn on_exit_requested(app: &AppHandle, api: &ExitRequestApi) {
+    let state = app.state::<AppState>();
+    // the exit that follows finalizing finds neither flag set
+    if state.shutting_down.load(Ordering::Relaxed) {
+        api.prevent_exit();
+        return;
+    }
+    if !state.is_recording.load(Ordering::Relaxed) {
+        return;
+    }
+    api.prevent_exit();
+    state.shutting_down.store(true, Ordering::Relaxed);
+    // the longest the stop itself waits, so a slow but working stop isn't cut short
+    let timeout = PIPELINE_JOIN_TIMEOUT + session_stop_timeout(&state) + SHUTDOWN_MARGIN;
+
+    let app = app.clone();
+    thread::spawn(move || {
+        let (done_tx, done) = bounded(1);
+        let finisher = app.clone();
+        thread::spawn(move || {
+            let _ = done_tx.send(stop_active_recording(&finisher));
+        });
+        let state = app.state::<AppState>();
+        match done.recv_timeout(timeout) {
+            Ok(Ok(result)) => eprintln!("Recording saved to {} before exiting", result.output_path),
+            Ok(Err(e)) => eprintln!("Recording could not be finalized before exiting: {}", e),
+            Err(_) => {
+                eprintln!("Recording not finalized within {:?}, exiting anyway", timeout);
+                // whatever the stuck stop hasn't reached yet would outlive the app
+                for process in [&state.ffmpeg, &state.helper] {
+                    if let Ok(mut process) = process.try_lock() {
+                        if let Some(child) = process.as_mut() {
+                            let _ = child.kill();
+                        }
+                    }
+                }
+            }
+        }
+        state.shutting_down.store(false, Ordering::Relaxed);
+        app.exit(0);
+    });
+}
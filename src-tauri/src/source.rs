@@ -0,0 +1,99 @@
+//! Where recorded frames come from. The pipeline only sees `FrameSource`, so
+//! anything that can produce packed BGRA can stand in for the screen; the
+//! integration tests feed it synthetic frames on machines without a display.
+
+use std::thread;
+use std::time::Duration;
+
+use scap::capturer::Capturer;
+
+use crate::{pixels, RecordingError};
+
+// failed grabs retried before the recording gives up, waiting a little longer each time
+const FRAME_RETRIES: u32 = 3;
+const FRAME_RETRY_BACKOFF: Duration = Duration::from_millis(100);
+
+/// A stream of frames, pulled from a dedicated grabber thread.
+pub trait FrameSource: Send {
+    /// Block for the next frame as packed BGRA `(width, height, data)`.
+    /// `Ok(None)` means the source has ended and won't deliver again.
+    fn next_frame(&mut self) -> Result<Option<(u32, u32, Vec<u8>)>, RecordingError>;
+
+    /// Release the underlying device. Called once, after the last frame.
+    fn stop(&mut self) {}
+}
+
+impl FrameSource for Capturer {
+    fn next_frame(&mut self) -> Result<Option<(u32, u32, Vec<u8>)>, RecordingError> {
+        // the capture is only torn down after the last frame, so a failure
+        // here is real and its cause worth keeping
+        let frame = self.get_next_frame().map_err(|e| RecordingError::Capture(e.to_string()))?;
+        pixels::to_bgra(frame).map(Some)
+    }
+
+    fn stop(&mut self) {
+        self.stop_capture();
+    }
+}
+
+/// The next frame's data from `source`, which must stay `size` pixels. A failed
+/// grab is retried a few times, since a display being reconfigured fails
+/// briefly; a frame of another size means the display changed (or, for
+/// `window` capture, the window was resized) under the recording, which a
+/// raw stream of fixed-size frames can't follow.
+pub fn next_frame_checked(
+    source: &mut dyn FrameSource,
+    (w, h): (u32, u32),
+    window: bool,
+) -> Result<Option<Vec<u8>>, String> {
+    let mut attempt = 0;
+    loop {
+        match source.next_frame() {
+            Ok(Some((fw, fh, _))) if (fw, fh) != (w, h) => {
+                let change = if window { "The window was resized" } else { "The display changed" };
+                return Err(format!(
+                    "{} from {}x{} to {}x{} during recording; stopped to keep the video intact",
+                    change, w, h, fw, fh
+                ));
+            }
+            Ok(frame) => return Ok(frame.map(|(_, _, data)| data)),
+            Err(e) if attempt == FRAME_RETRIES => return Err(e.to_string()),
+            Err(_) => {
+                attempt += 1;
+                thread::sleep(FRAME_RETRY_BACKOFF * attempt);
+            }
+        }
+    }
+}
+
+/// Stops the source if dropped on an early return, before it reaches the
+/// grabber thread.
+pub struct SourceGuard(Option<Box<dyn FrameSource>>);
+
+impl SourceGuard {
+    pub fn new(source: Box<dyn FrameSource>) -> Self {
+        SourceGuard(Some(source))
+    }
+
+    pub fn into_inner(mut self) -> Box<dyn FrameSource> {
+        self.0.take().expect("source already taken")
+    }
+
+    /// Like `next_frame`, but an ended source is an error; for frames the
+    /// caller can't do without, such as the first one.
+    pub fn require_frame(&mut self) -> Result<(u32, u32, Vec<u8>), RecordingError> {
+        self.0
+            .as_mut()
+            .expect("source already taken")
+            .next_frame()?
+            .ok_or_else(|| RecordingError::Capture("the capture source stopped delivering frames".into()))
+    }
+}
+
+impl Drop for SourceGuard {
+    fn drop(&mut self) {
+        if let Some(source) = self.0.as_mut() {
+            source.stop();
+        }
+    }
+}
@@ -0,0 +1,85 @@
+//! `output_target`: sending the encoded stream somewhere other than the
+//! session folder, such as a streaming server, a named pipe another process
+//! reads, or a file of the caller's choosing. ffmpeg writes there directly.
+//! The session folder still gets the log, events and manifest.
+
+use std::path::Path;
+
+use crate::RecordingError;
+
+// URL schemes ffmpeg can push a live stream to, and the container each carries
+const STREAM_SCHEMES: &[(&str, &str)] =
+    &[("rtmp", "flv"), ("rtmps", "flv"), ("srt", "mpegts"), ("udp", "mpegts"), ("tcp", "mpegts")];
+
+/// A validated `output_target` and the muxer ffmpeg should use for it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct OutputTarget {
+    pub location: String,
+    pub muxer: String,
+}
+
+impl OutputTarget {
+    /// `target` as a URL with a known scheme or an absolute path, carrying
+    /// `format` (the validated `output_format`).
+    pub fn parse(target: &str, format: &str) -> Result<Self, RecordingError> {
+        if let Some((scheme, _)) = target.split_once("://") {
+            let Some(&(_, muxer)) = STREAM_SCHEMES.iter().find(|(s, _)| s.eq_ignore_ascii_case(scheme)) else {
+                return Err(RecordingError::InvalidOptions(format!(
+                    "Unsupported output_target scheme '{}': expected one of {}",
+                    scheme,
+                    STREAM_SCHEMES.iter().map(|(s, _)| *s).collect::<Vec<_>>().join(", ")
+                )));
+            };
+            // both containers carry H.264, which is what mp4 output encodes
+            if format != "mp4" {
+                return Err(RecordingError::InvalidOptions(format!(
+                    "Streaming to {}:// needs output_format mp4, not {}",
+                    scheme, format
+                )));
+            }
+            return Ok(OutputTarget { location: target.into(), muxer: muxer.into() });
+        }
+        let path = Path::new(target);
+        if !path.is_absolute() {
+            return Err(RecordingError::InvalidOptions(format!(
+                "Invalid output_target '{}': expected an absolute path or a stream URL",
+                target
+            )));
+        }
+        if path.is_dir() {
+            return Err(RecordingError::InvalidOptions(format!("output_target {} is a folder", target)));
+        }
+        // an existing pipe or file is written to as it is
+        if !path.exists() && !path.parent().is_some_and(Path::is_dir) {
+            return Err(RecordingError::InvalidOptions(format!(
+                "output_target {} is in a folder that doesn't exist",
+                target
+            )));
+        }
+        Ok(OutputTarget { location: target.into(), muxer: format.into() })
+    }
+
+    /// Whether the target is a named pipe rather than a file or a URL.
+    fn is_pipe(&self) -> bool {
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::FileTypeExt;
+            std::fs::metadata(&self.location).is_ok_and(|m| m.file_type().is_fifo())
+        }
+        #[cfg(windows)]
+        {
+            self.location.to_ascii_lowercase().starts_with(r"\\.\pipe\")
+        }
+    }
+
+    /// Arguments that go just before the location on ffmpeg's command line.
+    pub fn output_args(&self) -> Vec<String> {
+        let mut args = vec!["-f".to_string(), self.muxer.clone()];
+        // a pipe can't be seeked back to write the index, so mp4 goes out in
+        // fragments there; a file gets the usual index at the end
+        if self.muxer == "mp4" && self.is_pipe() {
+            args.extend(["-movflags".to_string(), "frag_keyframe+empty_moov".to_string()]);
+        }
+        args
+    }
+}
@@ -0,0 +1,141 @@
+//! The system tray icon: start and stop recording and open the recordings
+//! folder without going to the main window. Tray actions run the same
+//! commands the window uses and report through `recording-toggled`, so the
+//! window's controls follow along. The icon gains a red dot while recording,
+//! whichever way the recording was started.
+
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::atomic::Ordering;
+use std::thread;
+use std::time::Duration;
+
+use tauri::image::Image;
+use tauri::menu::{Menu, MenuItem, PredefinedMenuItem};
+use tauri::tray::{TrayIcon, TrayIconBuilder};
+use tauri::{AppHandle, Manager};
+
+use crate::preset::RecordingPreset;
+use crate::{open_recordings_folder, report_start, report_stop, AppState};
+
+// how often the icon checks for recordings started or stopped elsewhere
+const STATE_POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+const START_ID: &str = "start";
+const STOP_ID: &str = "stop";
+const OPEN_FOLDER_ID: &str = "open-folder";
+
+/// Add the tray icon. Fails where the platform has no tray, e.g. a Linux
+/// desktop without the appindicator library, leaving the window's controls.
+pub fn install(app: &AppHandle) -> Result<(), String> {
+    // tray-icon panics rather than erroring when appindicator can't be loaded
+    panic::catch_unwind(AssertUnwindSafe(|| build(app)))
+        .map_err(|_| "the tray library could not be loaded".to_string())?
+        .map_err(|e| e.to_string())
+}
+
+fn build(app: &AppHandle) -> tauri::Result<()> {
+    let start = MenuItem::with_id(app, START_ID, "Start Recording", true, None::<&str>)?;
+    let stop = MenuItem::with_id(app, STOP_ID, "Stop Recording", false, None::<&str>)?;
+    let open_folder = MenuItem::with_id(app, OPEN_FOLDER_ID, "Open Recordings Folder", true, None::<&str>)?;
+    let menu = Menu::with_items(app, &[&start, &stop, &PredefinedMenuItem::separator(app)?, &open_folder])?;
+
+    let idle_icon = app.default_window_icon().cloned().map(Image::to_owned);
+    let mut builder = TrayIconBuilder::with_id("main")
+        .tooltip("Not recording")
+        .menu(&menu)
+        .on_menu_event(|app, event| {
+            // start/stop block on the pipeline, keep them off the event loop
+            let app = app.clone();
+            match event.id().as_ref() {
+                START_ID => {
+                    thread::spawn(move || start_recording(&app));
+                }
+                STOP_ID => {
+                    thread::spawn(move || report_stop(&app));
+                }
+                OPEN_FOLDER_ID => open_folder_for(&app),
+                _ => {}
+            }
+        });
+    if let Some(icon) = &idle_icon {
+        builder = builder.icon(icon.clone());
+    }
+    let tray = builder.build(app)?;
+    follow_state(app.clone(), tray, idle_icon, start, stop);
+    Ok(())
+}
+
+/// Start with the options of the last recording, or the screencast preset
+/// when there hasn't been one yet.
+fn start_recording(app: &AppHandle) {
+    let last = app.state::<AppState>().last_options.lock().unwrap().clone();
+    let opts = match last {
+        Some(mut opts) => {
+            // that session has had its take added
+            opts.append_to_session = None;
+            Ok(opts)
+        }
+        None => RecordingPreset::Screencast.expand(None),
+    };
+    match opts {
+        Ok(opts) => report_start(app, opts),
+        Err(e) => eprintln!("Tray could not start a recording: {}", e),
+    }
+}
+
+/// The recordings folder of the last recording's `output_dir`, else the default one.
+fn open_folder_for(app: &AppHandle) {
+    let output_dir = app
+        .state::<AppState>()
+        .last_options
+        .lock()
+        .unwrap()
+        .as_ref()
+        .and_then(|opts| opts.output_dir.clone());
+    if let Err(e) = open_recordings_folder(app.clone(), output_dir) {
+        eprintln!("Tray could not open the recordings folder: {}", e);
+    }
+}
+
+/// Keep the icon, tooltip and menu in step with the recording state.
+fn follow_state(app: AppHandle, tray: TrayIcon, idle_icon: Option<Image<'static>>, start: MenuItem, stop: MenuItem) {
+    let recording_icon = idle_icon.as_ref().map(with_red_dot);
+    thread::spawn(move || {
+        let mut shown = false;
+        loop {
+            thread::sleep(STATE_POLL_INTERVAL);
+            let state = app.state::<AppState>();
+            let recording =
+                state.is_recording.load(Ordering::Relaxed) || state.counting_down.load(Ordering::Relaxed);
+            if recording == shown {
+                continue;
+            }
+            shown = recording;
+            let icon = if recording { &recording_icon } else { &idle_icon };
+            if let Some(icon) = icon {
+                let _ = tray.set_icon(Some(icon.clone()));
+            }
+            let _ = tray.set_tooltip(Some(if recording { "Recording" } else { "Not recording" }));
+            let _ = start.set_enabled(!recording);
+            let _ = stop.set_enabled(recording);
+        }
+    });
+}
+
+/// `icon` with a red dot over its bottom-right corner.
+fn with_red_dot(icon: &Image<'_>) -> Image<'static> {
+    let (w, h) = (icon.width(), icon.height());
+    let mut rgba = icon.rgba().to_vec();
+    let radius = w.min(h) as f64 * 0.22;
+    let (cx, cy) = (w as f64 - radius - 1.0, h as f64 - radius - 1.0);
+    for y in 0..h {
+        for x in 0..w {
+            let (dx, dy) = (x as f64 + 0.5 - cx, y as f64 + 0.5 - cy);
+            if dx * dx + dy * dy <= radius * radius {
+                let i = (y * w + x) as usize * 4;
+                rgba[i..i + 4].copy_from_slice(&[230, 30, 30, 255]);
+            }
+        }
+    }
+    Image::new_owned(rgba, w, h)
+}
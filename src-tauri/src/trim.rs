@@ -0,0 +1,86 @@
+//! Cutting a recording down to a time range: a stream copy when the start
+//! lands on a keyframe, otherwise a frame-accurate re-encode.
+
+use std::path::Path;
+use std::process::{Command, Stdio};
+
+use crate::ffmpeg;
+use crate::RecordingError;
+
+// a start within this many seconds of a keyframe counts as on it
+const KEYFRAME_TOLERANCE: f64 = 0.001;
+
+/// Presentation times of the video keyframes.
+fn keyframes(ffprobe: &Path, path: &Path) -> Vec<f64> {
+    let args = ["-select_streams", "v:0", "-skip_frame", "nokey", "-show_entries", "frame=pts_time"];
+    ffmpeg::ffprobe_lines(ffprobe, &args, path)
+        .unwrap_or_default()
+        .iter()
+        .filter_map(|t| t.parse().ok())
+        .collect()
+}
+
+/// Write the `start_secs..end_secs` part of `input` to `output`.
+pub fn trim(bin: &str, input: &Path, start_secs: f64, end_secs: f64, output: &Path) -> Result<(), RecordingError> {
+    if !(start_secs >= 0.0 && start_secs < end_secs) {
+        return Err(RecordingError::InvalidOptions(format!(
+            "Invalid trim range {}..{}: start must be at least 0 and before end",
+            start_secs, end_secs
+        )));
+    }
+    match std::fs::metadata(input) {
+        Ok(m) if m.is_file() && m.len() > 0 => {}
+        Ok(_) => return Err(RecordingError::InvalidOptions(format!("{} is empty", input.display()))),
+        Err(e) => return Err(RecordingError::Io(format!("{}: {}", input.display(), e))),
+    }
+    if input == output {
+        return Err(RecordingError::InvalidOptions("the output must differ from the input".into()));
+    }
+
+    let ffprobe = ffmpeg::ffprobe_bin(bin);
+    let length = ffmpeg::duration(&ffprobe, input).ok_or_else(|| {
+        RecordingError::FfmpegFailed(format!("could not read the duration of {}", input.display()))
+    })?;
+    if end_secs > length {
+        return Err(RecordingError::InvalidOptions(format!(
+            "Invalid trim range {}..{}: the recording is only {:.3}s long",
+            start_secs, end_secs, length
+        )));
+    }
+
+    // a copy can only start cleanly on a keyframe; the end may fall anywhere
+    let copy = keyframes(&ffprobe, input).iter().any(|k| (k - start_secs).abs() <= KEYFRAME_TOLERANCE);
+    let mut cmd = Command::new(bin);
+    cmd.args(["-y", "-hide_banner", "-loglevel", "error"]);
+    if copy {
+        // before -i: seek the input, so the copy starts exactly on that keyframe
+        cmd.args(["-ss", &start_secs.to_string(), "-to", &end_secs.to_string(), "-i"]).arg(input);
+        cmd.args(["-map", "0", "-c", "copy", "-avoid_negative_ts", "make_zero"]);
+    } else {
+        cmd.arg("-i").arg(input);
+        cmd.args(["-ss", &start_secs.to_string(), "-to", &end_secs.to_string()]);
+        cmd.args(["-map", "0:v:0", "-map", "0:a?"]);
+        let codec: &[&str] = match output.extension().and_then(|e| e.to_str()) {
+            Some("webm") => &["-c:v", "libvpx-vp9", "-crf", "30", "-b:v", "0", "-c:a", "libopus"],
+            Some("gif") => &[],
+            _ => &["-c:v", "libx264", "-preset", "veryfast", "-crf", "18", "-pix_fmt", "yuv420p", "-c:a", "aac"],
+        };
+        cmd.args(codec);
+    }
+    let result = cmd
+        .arg(output)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .output()
+        .map_err(|e| ffmpeg::spawn_error(bin, e))?;
+    if !result.status.success() {
+        let _ = std::fs::remove_file(output);
+        return Err(RecordingError::FfmpegFailed(format!(
+            "trim exited with {}: {}",
+            result.status,
+            String::from_utf8_lossy(&result.stderr).trim()
+        )));
+    }
+    Ok(())
+}
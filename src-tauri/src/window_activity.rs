@@ -0,0 +1,54 @@
+//! `window_activity.jsonl`: which window was in front, and from when, for
+//! indexing a tutorial by the app on screen ("switched to the terminal at
+//! 0:45"). The foreground window is polled at a low rate and only changes
+//! are written.
+
+use std::fs::File;
+use std::io::Write;
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+use chrono::{DateTime, Local};
+use serde_json::json;
+
+use crate::RecordingError;
+
+// foreground changes shorter than this may be missed, which suits an index
+const POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Log foreground window changes to `path` until `alive` clears. Each line
+/// has the app and window title and when the switch happened, as `timestamp`
+/// (Unix seconds, like the event log) and `offset_secs` from `started`.
+pub fn spawn_tracker(
+    path: &Path,
+    alive: Arc<AtomicBool>,
+    started: DateTime<Local>,
+) -> Result<JoinHandle<()>, RecordingError> {
+    let mut file = File::create(path)?;
+    Ok(thread::spawn(move || {
+        let mut current: Option<(String, String)> = None;
+        while alive.load(Ordering::Relaxed) {
+            // nothing in front (a locked screen, say) keeps the last entry
+            if let Ok(window) = active_win_pos_rs::get_active_window() {
+                let seen = (window.app_name, window.title);
+                if current.as_ref() != Some(&seen) {
+                    let now = Local::now();
+                    let line = json!({
+                        "timestamp": now.timestamp_millis() as f64 / 1000.0,
+                        "offset_secs": (now - started).num_milliseconds() as f64 / 1000.0,
+                        "app_name": seen.0,
+                        "title": seen.1,
+                    });
+                    if writeln!(file, "{}", line).is_err() {
+                        break;
+                    }
+                    current = Some(seen);
+                }
+            }
+            thread::sleep(POLL_INTERVAL);
+        }
+    }))
+}
@@ -0,0 +1,275 @@
+//! End-to-end recordings through `start_recording_with_source`, fed by a
+//! synthetic frame source so they run without a display or capture permission.
+//! The encoder tests need ffmpeg and ffprobe on PATH and are skipped without them.
+
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::thread;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use serde_json::json;
+use tauri::test::{mock_builder, mock_context, noop_assets, MockRuntime};
+use tauri::App;
+use tauri_app_lib::{
+    start_recording_with_source, stop_active_recording, AppState, FrameSource, RecordingError, RecordingOptions,
+};
+
+const WIDTH: u32 = 320;
+const HEIGHT: u32 = 240;
+const FPS: u32 = 10;
+
+/// A gradient that scrolls one pixel per frame, so consecutive frames differ
+/// and every run produces the same sequence.
+struct FakeSource {
+    index: u32,
+    /// Frames to deliver before ending, or `None` to run until stopped.
+    limit: Option<u32>,
+}
+
+impl FrameSource for FakeSource {
+    fn next_frame(&mut self) -> Result<Option<(u32, u32, Vec<u8>)>, RecordingError> {
+        if self.limit.is_some_and(|limit| self.index >= limit) {
+            return Ok(None);
+        }
+        let mut data = Vec::with_capacity((WIDTH * HEIGHT * 4) as usize);
+        for y in 0..HEIGHT {
+            for x in 0..WIDTH {
+                let shade = ((x + self.index) % 256) as u8;
+                data.extend_from_slice(&[shade, (y % 256) as u8, 255 - shade, 255]);
+            }
+        }
+        self.index += 1;
+        Ok(Some((WIDTH, HEIGHT, data)))
+    }
+}
+
+/// `FakeSource` until frame `after`, then half-size frames, like a display
+/// switching resolution mid-recording.
+struct ResizingSource {
+    inner: FakeSource,
+    after: u32,
+}
+
+impl FrameSource for ResizingSource {
+    fn next_frame(&mut self) -> Result<Option<(u32, u32, Vec<u8>)>, RecordingError> {
+        if self.inner.index < self.after {
+            return self.inner.next_frame();
+        }
+        Ok(Some((WIDTH / 2, HEIGHT / 2, vec![0; (WIDTH / 2 * HEIGHT / 2 * 4) as usize])))
+    }
+}
+
+fn mock_app() -> App<MockRuntime> {
+    mock_builder()
+        .manage(AppState::default())
+        .build(mock_context(noop_assets()))
+        .expect("mock app")
+}
+
+/// A fresh, empty directory per test; tests run in parallel.
+fn scratch_dir(name: &str) -> PathBuf {
+    let nanos = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_nanos();
+    let dir = std::env::temp_dir().join(format!("screenrec-{}-{}-{}", name, std::process::id(), nanos));
+    std::fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+fn options(output_dir: &Path, extra: serde_json::Value) -> RecordingOptions {
+    let mut opts = json!({
+        "fps": FPS,
+        "fps_auto_adjust": false,
+        "show_cursor": false,
+        "show_highlight": false,
+        "capture_keystrokes": false,
+        "preset": "ultrafast",
+        "output_dir": output_dir,
+    });
+    opts.as_object_mut().unwrap().extend(extra.as_object().unwrap().clone());
+    serde_json::from_value(opts).expect("valid options")
+}
+
+fn have_ffmpeg() -> bool {
+    ["ffmpeg", "ffprobe"]
+        .iter()
+        .all(|bin| Command::new(bin).arg("-version").output().is_ok_and(|o| o.status.success()))
+}
+
+fn probe_duration(path: &Path) -> f64 {
+    let out = Command::new("ffprobe")
+        .args(["-v", "error", "-show_entries", "format=duration", "-of", "default=noprint_wrappers=1:nokey=1"])
+        .arg(path)
+        .output()
+        .expect("ffprobe runs");
+    String::from_utf8_lossy(&out.stdout).trim().parse().expect("ffprobe reports a duration")
+}
+
+fn record(app: &App<MockRuntime>, opts: RecordingOptions, source: FakeSource, secs: f64) -> tauri_app_lib::RecordingResult {
+    start_recording_with_source(app.handle(), opts, Box::new(source)).expect("recording starts");
+    thread::sleep(Duration::from_secs_f64(secs));
+    stop_active_recording(app.handle()).expect("recording stops cleanly")
+}
+
+#[test]
+fn records_mp4_with_expected_duration() {
+    if !have_ffmpeg() {
+        eprintln!("skipping: ffmpeg/ffprobe not found");
+        return;
+    }
+    let app = mock_app();
+    let dir = scratch_dir("mp4");
+    let result = record(&app, options(&dir, json!({})), FakeSource { index: 0, limit: None }, 2.0);
+
+    let output = Path::new(&result.output_path);
+    assert!(output.starts_with(&dir));
+    assert!(std::fs::metadata(output).unwrap().len() > 0, "output is empty");
+    assert!(Path::new(&result.manifest_path).is_file());
+
+    // the video holds every delivered frame at the capture rate
+    let duration = probe_duration(output);
+    let expected = result.stats.frames_captured as f64 / FPS as f64;
+    assert!((duration - expected).abs() <= 0.2, "duration {}s, expected {}s", duration, expected);
+    assert!((1.5..=2.5).contains(&duration), "duration {}s for a 2s recording", duration);
+
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn records_webm() {
+    if !have_ffmpeg() {
+        eprintln!("skipping: ffmpeg/ffprobe not found");
+        return;
+    }
+    let app = mock_app();
+    let dir = scratch_dir("webm");
+    let opts = options(&dir, json!({ "output_format": "webm" }));
+    let result = record(&app, opts, FakeSource { index: 0, limit: None }, 1.0);
+
+    let output = Path::new(&result.output_path);
+    assert_eq!(output.extension().and_then(|e| e.to_str()), Some("webm"));
+    assert!(std::fs::metadata(output).unwrap().len() > 0, "output is empty");
+    assert!(probe_duration(output) > 0.5);
+
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn output_target_is_written_instead_of_the_session_file() {
+    if !have_ffmpeg() {
+        eprintln!("skipping: ffmpeg/ffprobe not found");
+        return;
+    }
+    let app = mock_app();
+    let dir = scratch_dir("target");
+    let target = dir.join("stream.mp4");
+    let opts = options(&dir, json!({ "output_target": target }));
+    let result = record(&app, opts, FakeSource { index: 0, limit: None }, 1.0);
+
+    // the stream goes to the target; the session folder keeps the manifest
+    assert_eq!(Path::new(&result.output_path), target);
+    assert!(probe_duration(&target) > 0.5);
+    let manifest = Path::new(&result.manifest_path);
+    assert!(manifest.is_file());
+    assert_ne!(manifest.parent(), Some(dir.as_path()));
+
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn frames_mode_writes_one_image_per_frame() {
+    let app = mock_app();
+    let dir = scratch_dir("frames");
+    let opts = options(&dir, json!({ "output_mode": "frames", "frame_format": "png" }));
+    let result = record(&app, opts, FakeSource { index: 0, limit: None }, 1.0);
+
+    let images = std::fs::read_dir(&result.output_path).unwrap().count() as u64;
+    assert!(images > 0);
+    assert_eq!(images, result.stats.frames_captured);
+    // numbered from 0, like the frame indices in frame_hashes and frame_timestamps
+    let frames = Path::new(&result.output_path);
+    assert_eq!(image::image_dimensions(frames.join("frame_000000.png")).unwrap(), (WIDTH, HEIGHT));
+    assert!(frames.join(format!("frame_{:06}.png", images - 1)).is_file());
+    assert!(!frames.join(format!("frame_{:06}.png", images)).exists());
+
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn auto_power_saver_lowers_the_capture_rate_on_battery() {
+    let app = mock_builder()
+        .manage(AppState::default().with_battery_probe(|| Some(true)))
+        .build(mock_context(noop_assets()))
+        .expect("mock app");
+    let dir = scratch_dir("battery");
+    let opts = options(&dir, json!({ "fps": 30, "auto_power_saver": true, "output_mode": "frames" }));
+    let result = record(&app, opts, FakeSource { index: 0, limit: None }, 2.0);
+
+    // recording starts at the full rate and drops to 15 fps once the probe answers
+    let frames = Path::new(&result.output_path);
+    let log = std::fs::read_to_string(frames.parent().unwrap().join("session.log")).unwrap();
+    assert!(log.contains("Battery power: capturing at 15.0 fps"), "session.log:\n{}", log);
+    let images = std::fs::read_dir(frames).unwrap().count();
+    assert!((1..=40).contains(&images), "{} images in 2s", images);
+
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn ended_source_stops_the_recording() {
+    if !have_ffmpeg() {
+        eprintln!("skipping: ffmpeg/ffprobe not found");
+        return;
+    }
+    let app = mock_app();
+    let dir = scratch_dir("ended");
+    start_recording_with_source(app.handle(), options(&dir, json!({})), Box::new(FakeSource { index: 0, limit: Some(5) }))
+        .expect("recording starts");
+
+    // the auto-stop finalizes the session, leaving nothing for a manual stop
+    thread::sleep(Duration::from_secs(3));
+    assert!(matches!(stop_active_recording(app.handle()), Err(RecordingError::NotRecording)));
+    let manifest = std::fs::read_dir(&dir)
+        .unwrap()
+        .filter_map(|e| e.ok())
+        .map(|e| e.path().join("manifest.json"))
+        .find(|p| p.is_file());
+    assert!(manifest.is_some(), "no manifest was written");
+
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn display_change_stops_the_recording() {
+    let app = mock_app();
+    let dir = scratch_dir("resized");
+    let source = ResizingSource { inner: FakeSource { index: 0, limit: None }, after: 5 };
+    start_recording_with_source(app.handle(), options(&dir, json!({ "output_mode": "frames" })), Box::new(source))
+        .expect("recording starts");
+
+    // frames of the new size never reach the output; the recording ends instead
+    thread::sleep(Duration::from_secs(3));
+    assert!(matches!(stop_active_recording(app.handle()), Err(RecordingError::NotRecording)));
+    let images = std::fs::read_dir(&dir)
+        .unwrap()
+        .filter_map(|e| e.ok())
+        .map(|e| e.path().join("frames"))
+        .find(|p| p.is_dir())
+        .map(|frames| std::fs::read_dir(frames).unwrap().count())
+        .unwrap_or(0);
+    assert!((1..=5).contains(&images), "{} images written", images);
+
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn second_start_is_refused() {
+    let app = mock_app();
+    let dir = scratch_dir("busy");
+    let opts = options(&dir, json!({ "output_mode": "frames" }));
+    start_recording_with_source(app.handle(), opts.clone(), Box::new(FakeSource { index: 0, limit: None }))
+        .expect("recording starts");
+    let second = start_recording_with_source(app.handle(), opts, Box::new(FakeSource { index: 0, limit: None }));
+    assert!(matches!(second, Err(RecordingError::AlreadyRecording)));
+    stop_active_recording(app.handle()).expect("recording stops cleanly");
+
+    let _ = std::fs::remove_dir_all(&dir);
+}
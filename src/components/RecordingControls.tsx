@@ -1,7 +1,8 @@
 import React, { useState, useEffect } from 'react';
 import { invoke } from '@tauri-apps/api/core';
+import { listen } from '@tauri-apps/api/event';
 import { RecordingFactory } from '../services/recording-factory.service';
-import { RecordingOptions, RecordingState } from '../types/recording';
+import { RecordingOptions, RecordingState, RecordingToggled } from '../types/recording';
 
 export const RecordingControls: React.FC = () => {
   const [isRecording, setIsRecording] = useState(false);
@@ -49,9 +50,18 @@ export const RecordingControls: React.FC = () => {
     };
     
     document.addEventListener('visibilitychange', handleVisibilityChange);
+
+    // recordings started or stopped from the tray or the hotkey
+    const unlistenToggled = listen<RecordingToggled>('recording-toggled', ({ payload }) => {
+      setIsRecording(payload.is_recording);
+      setError(payload.error ? payload.error.message : null);
+      setDuration(0);
+      setRecordingPath(payload.result ? payload.result.output_path : null);
+    });
     
     return () => {
       document.removeEventListener('visibilitychange', handleVisibilityChange);
+      unlistenToggled.then(unlisten => unlisten());
     };
   }, []);
 
@@ -0,0 +1,59 @@
+import { invoke } from '@tauri-apps/api/core';
+
+// Drag a rectangle and report it to `finish_region_selection` in window
+// points; the backend converts it to frame pixels.
+
+const shade = document.getElementById('shade') as HTMLDivElement;
+const selection = document.getElementById('selection') as HTMLDivElement;
+const size = document.getElementById('size') as HTMLSpanElement;
+const hint = document.getElementById('hint') as HTMLDivElement;
+
+let start: { x: number; y: number } | null = null;
+let finished = false;
+
+type Rect = [number, number, number, number];
+
+function rectTo(x: number, y: number): Rect {
+  const s = start ?? { x, y };
+  return [Math.min(s.x, x), Math.min(s.y, y), Math.abs(x - s.x), Math.abs(y - s.y)];
+}
+
+function finish(rect: Rect | null): void {
+  if (finished) return;
+  finished = true;
+  invoke('finish_region_selection', { selection: rect }).catch(console.error);
+}
+
+window.addEventListener('mousedown', (e) => {
+  if (e.button !== 0) return;
+  start = { x: e.clientX, y: e.clientY };
+  hint.style.display = 'none';
+  // the selection's box-shadow takes over the dimming
+  shade.style.display = 'none';
+  selection.style.display = 'block';
+});
+
+window.addEventListener('mousemove', (e) => {
+  if (!start) return;
+  const [x, y, w, h] = rectTo(e.clientX, e.clientY);
+  Object.assign(selection.style, { left: `${x}px`, top: `${y}px`, width: `${w}px`, height: `${h}px` });
+  const scale = window.devicePixelRatio;
+  size.textContent = `${Math.round(w * scale)} × ${Math.round(h * scale)}`;
+});
+
+window.addEventListener('mouseup', (e) => {
+  if (!start || e.button !== 0) return;
+  const rect = rectTo(e.clientX, e.clientY);
+  start = null;
+  // a click without a drag starts over rather than recording a sliver
+  if (rect[2] < 4 || rect[3] < 4) {
+    selection.style.display = 'none';
+    shade.style.display = 'block';
+    return;
+  }
+  finish(rect);
+});
+
+window.addEventListener('keydown', (e) => {
+  if (e.key === 'Escape') finish(null);
+});
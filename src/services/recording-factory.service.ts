@@ -1,5 +1,5 @@
 import { invoke } from '@tauri-apps/api/core';
-import { RecordingOptions, RecordingState } from '../types/recording';
+import { RecordingOptions, RecordingResult, RecordingState } from '../types/recording';
 
 export class RecordingFactory {
   static createRecordingService(platform: string) {
@@ -18,9 +18,9 @@ export class RecordingFactory {
       
       stopRecording: async () => {
         console.log('Stopping recording');
-        const outputPath = await invoke<string>('stop_recording');
-        console.log('Recording stopped, saved to:', outputPath);
-        return outputPath;
+        const result = await invoke<RecordingResult>('stop_recording');
+        console.log('Recording stopped, saved to:', result.output_path, 'dropped frames:', result.dropped_frames);
+        return result.output_path;
       },
       
       getState: async (): Promise<RecordingState> => {
@@ -6,11 +6,97 @@ export interface RecordingOptions {
     capture_keystrokes: boolean;
 }
 
+/** Payload of the `audio-level` event, in dBFS (-100 for silence). */
+export interface AudioLevel {
+    peak_db: number;
+    rms_db: number;
+}
+
+/** Payload of the `recording-error` event; the recording stops on its own after it. */
+export interface RecordingFailed {
+    stage: 'capture' | 'writer';
+    reason: string;
+}
+
+/** Payload of the `power-saver-changed` event; the video keeps its frame rate. */
+export interface PowerSaverChanged {
+    on_battery: boolean;
+    capture_fps: number;
+}
+
+export type RecordingPreset = 'Screencast' | 'Gameplay' | 'Demo';
+
+/** Payload of the `preset-expanded` event. */
+export interface PresetExpanded {
+    preset: RecordingPreset;
+    options: RecordingOptions;
+}
+
 export interface RecordingState {
     is_recording: boolean;
     duration: number;
+    wall_duration: number;
+    media_duration: number;
     error?: string;
     output_path?: string;
+    dropped_frames?: number;
+}
+
+export interface RecordingSession {
+    session_dir: string;
+    output_path: string;
+    events_file?: string;
+}
+
+export interface RecordingResult {
+    output_path: string;
+    manifest_path: string;
+    dropped_frames: number;
+    stats: CaptureStats;
+    thumbnail_path?: string;
+    segments: string[];
+}
+
+/** Payload of the `recording-toggled` event, sent when the hotkey or the tray starts or stops a recording. */
+export interface RecordingToggled {
+    is_recording: boolean;
+    result?: RecordingResult;
+    error?: RecordingError;
+}
+
+export interface RecoveredSession {
+    session_dir: string;
+    output_path?: string;
+    manifest_path?: string;
+    message: string;
+}
+
+/** One finished recording, as returned by `list_recordings` (newest first). */
+export interface RecordingEntry {
+    session_dir: string;
+    output_path: string;
+    started_at: string;
+    duration_secs: number;
+    size_bytes: number;
+    thumbnail_path?: string;
+    recovered: boolean;
+}
+
+/** Returned by `estimate_size_per_minute`: a rough figure for planning. */
+export interface SizeEstimate {
+    bytes_per_minute: number;
+    width: number;
+    height: number;
+    fps: number;
+}
+
+export interface CaptureStats {
+    duration_secs: number;
+    frames_captured: number;
+    average_fps: number;
+    dropped_frames: number;
+    channel_full: number;
+    file_size: number;
 }
 
 export interface RecordingService {
@@ -28,4 +114,8 @@ export interface RecordingStats {
     bitrate: number;
     fileSize: number;
     duration: number;
-} 
\ No newline at end of file
+} 
+export interface RecordingError {
+    code: string;
+    message: string;
+}
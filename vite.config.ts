@@ -8,6 +8,16 @@ const host = process.env.TAURI_DEV_HOST;
 export default defineConfig(async () => ({
   plugins: [react()],
 
+  // the region picker overlay is a second page, loaded in its own window
+  build: {
+    rollupOptions: {
+      input: {
+        main: "index.html",
+        "region-picker": "region-picker.html",
+      },
+    },
+  },
+
   // Vite options tailored for Tauri development and only applied in `tauri dev` or `tauri build`
   //
   // 1. prevent vite from obscuring rust errors